@@ -0,0 +1,74 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use devsim::device::{AccessMode, Device};
+
+/// Size of the memory transfers used by the benchmarks, roughly the size of a typical elf
+const TRANSFER_SIZE: usize = 64 * 1024;
+
+/// Address used for all memory transfers
+const TRANSFER_ADDR: u32 = 0x1000;
+
+fn devices() -> Vec<(&'static str, Device)> {
+    let mut unbatched = Device::new();
+    unbatched.set_access_mode(AccessMode::Frontdoor);
+    unbatched.set_clock_batching(false);
+
+    let mut batched = Device::new();
+    batched.set_access_mode(AccessMode::Frontdoor);
+
+    let mut backdoor = Device::new();
+    backdoor.set_access_mode(AccessMode::Backdoor);
+
+    vec![
+        ("unbatched", unbatched),
+        ("batched", batched),
+        ("backdoor", backdoor),
+    ]
+}
+
+fn write_bytes(c: &mut Criterion) {
+    let data = (0..TRANSFER_SIZE).map(|i| i as u8).collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("write_bytes");
+    group.throughput(Throughput::Bytes(TRANSFER_SIZE as u64));
+    for (name, mut device) in devices() {
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                device.write_memory(TRANSFER_ADDR, &data).unwrap();
+
+                // Writes are only queued on the host until the device is clocked, a register read forces them out
+                device.query_is_halted().unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+fn read_bytes(c: &mut Criterion) {
+    let mut data = vec![0; TRANSFER_SIZE];
+
+    let mut group = c.benchmark_group("read_bytes");
+    group.throughput(Throughput::Bytes(TRANSFER_SIZE as u64));
+    for (name, mut device) in devices() {
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| device.read_memory(TRANSFER_ADDR, &mut data).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn dump_framebuffer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dump_framebuffer");
+    for (name, mut device) in devices() {
+        let (width, height) = device.query_framebuffer_size().unwrap();
+        let mut fb_data = vec![0; (width * height * 4) as usize];
+
+        group.throughput(Throughput::Bytes(fb_data.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| device.dump_framebuffer(&mut fb_data).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, write_bytes, read_bytes, dump_framebuffer);
+criterion_main!(benches);
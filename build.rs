@@ -1,16 +1,167 @@
+#[cfg(feature = "verilator")]
 use cmake::Config;
 use std::env;
+use std::fs;
+#[cfg(feature = "verilator")]
 use std::path::Path;
+use std::path::PathBuf;
+
+/// A parameter of hw/top.sv that can be overridden through an environment variable at build time
+struct HwParam {
+    /// Name of the parameter in hw/top.sv
+    name: &'static str,
+    /// Environment variable that overrides the default value
+    var: &'static str,
+    default: u32,
+}
+
+const HW_PARAMS: [HwParam; 6] = [
+    HwParam {
+        name: "MEM_SIZE",
+        var: "DEVSIM_MEM_SIZE",
+        default: 1024 * 1024,
+    },
+    HwParam {
+        name: "FIFO_DEPTH",
+        var: "DEVSIM_FIFO_DEPTH",
+        default: 16,
+    },
+    HwParam {
+        name: "FB_ADDR",
+        var: "DEVSIM_FB_ADDR",
+        default: 0xC0000,
+    },
+    HwParam {
+        name: "FB_DIM_LOG2",
+        var: "DEVSIM_FB_DIM_LOG2",
+        default: 6,
+    },
+    HwParam {
+        name: "FETCH_WAIT_STATES",
+        var: "DEVSIM_FETCH_WAIT_STATES",
+        default: 0,
+    },
+    HwParam {
+        name: "DATA_WAIT_STATES",
+        var: "DEVSIM_DATA_WAIT_STATES",
+        default: 0,
+    },
+];
+
+/// Start of the host's register address space, which memory can't overlap
+const REG_SPACE_BASE: u32 = 0x3ffff000;
+
+/// Number of framebuffers that follow FB_ADDR, matches NUM_FB_BUFFERS in hw/top.sv
+const NUM_FB_BUFFERS: u64 = 2;
+
+/// Parses a decimal or 0x prefixed hexadecimal value
+fn parse_value(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+/// Returns the value of each hardware parameter, taking overrides from the environment into account
+fn hw_param_values() -> Vec<u32> {
+    let values: Vec<u32> = HW_PARAMS
+        .iter()
+        .map(|param| {
+            println!("cargo:rerun-if-env-changed={}", param.var);
+            match env::var(param.var) {
+                Ok(text) => parse_value(&text).unwrap_or_else(|| {
+                    panic!("{} must be a number but was \"{}\"", param.var, text)
+                }),
+                Err(_) => param.default,
+            }
+        })
+        .collect();
+
+    // The registers are decoded from the low bits of the cpu's address, so they need to start on a 4KB boundary
+    let (mem_size, fifo_depth, fb_addr, fb_dim_log2) = (values[0], values[1], values[2], values[3]);
+    let (fetch_wait_states, data_wait_states) = (values[4], values[5]);
+    assert!(
+        (mem_size > 0) && (mem_size % 4096 == 0) && (mem_size <= REG_SPACE_BASE),
+        "DEVSIM_MEM_SIZE must be a multiple of 4096 no larger than {:#x}",
+        REG_SPACE_BASE
+    );
+    assert!(
+        (fifo_depth >= 2) && fifo_depth.is_power_of_two(),
+        "DEVSIM_FIFO_DEPTH must be a power of two of at least 2"
+    );
+    assert!(
+        (1..=8).contains(&fb_dim_log2),
+        "DEVSIM_FB_DIM_LOG2 must be between 1 and 8"
+    );
+    assert!(
+        (fetch_wait_states <= 0xff) && (data_wait_states <= 0xff),
+        "DEVSIM_FETCH_WAIT_STATES and DEVSIM_DATA_WAIT_STATES must be at most 255"
+    );
+
+    // Pixels are written as whole words and every framebuffer has to fit in memory after reset
+    let fb_size = (1u64 << (2 * fb_dim_log2)) * 4;
+    assert!(fb_addr % 4 == 0, "DEVSIM_FB_ADDR must be a multiple of 4");
+    assert!(
+        u64::from(fb_addr) + (NUM_FB_BUFFERS * fb_size) <= u64::from(mem_size),
+        "DEVSIM_FB_ADDR leaves no room for {} framebuffers of {:#x} bytes in {:#x} bytes of memory",
+        NUM_FB_BUFFERS,
+        fb_size,
+        mem_size
+    );
+
+    values
+}
+
+/// Writes the hardware parameters into a rust file that's included by the library as devsim::hw_params
+fn write_hw_params(values: &[u32]) {
+    let mut contents = String::new();
+    for (param, value) in HW_PARAMS.iter().zip(values) {
+        contents.push_str(&format!(
+            "/// Value of the {} parameter of hw/top.sv in this build\npub const {}: u32 = {:#x};\n",
+            param.name, param.name, value
+        ));
+    }
+    contents.push_str(&format!(
+        "/// Verilator arguments that apply the parameters above, as a cmake list\npub const VERILATOR_ARGS: &str = \"{}\";\n",
+        verilator_args(values)
+    ));
+
+    let path = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("hw_params.rs");
+    fs::write(path, contents).unwrap();
+}
+
+/// Returns the -G options that set the hardware parameters, as a cmake list
+fn verilator_args(values: &[u32]) -> String {
+    HW_PARAMS
+        .iter()
+        .zip(values)
+        .map(|(param, value)| format!("-G{}={}", param.name, value))
+        .collect::<Vec<String>>()
+        .join(";")
+}
+
+#[cfg(feature = "verilator")]
+fn build_protobridge(hw_param_values: &[u32]) {
+    println!("cargo:rerun-if-changed=protobridge");
 
-fn main() {
     let hw_dir_path = env::current_dir().unwrap().join(Path::new("hw"));
-    let mut config = Config::new("third_party/protobridge");
+    let mut config = Config::new("protobridge");
 
     config.define("PROTOBRIDGE_HW_PATH", hw_dir_path);
+    config.define(
+        "PROTOBRIDGE_VERILATOR_ARGS",
+        verilator_args(hw_param_values),
+    );
 
-    // Only enable VCD traces in debug builds
-    if cfg!(debug_assertions) {
-        config.define("PROTOBRIDGE_TRACE", "ON");
+    // Tracing is always built in since traces are only written when the host requests one
+    config.define("PROTOBRIDGE_TRACE", "ON");
+    let is_fst = env::var_os("CARGO_FEATURE_TRACE_FST").is_some();
+    if is_fst {
+        config.define("PROTOBRIDGE_TRACE_FORMAT", "FST");
+    } else {
+        config.define("PROTOBRIDGE_TRACE_FORMAT", "VCD");
     }
 
     let dst = config.build();
@@ -30,4 +181,22 @@ fn main() {
         #[cfg(target_os = "macos")]
         println!("cargo:rustc-link-lib=dylib=c++");
     }
+
+    // Verilator's fst writer compresses with zlib, which the static library doesn't carry along
+    if is_fst {
+        println!("cargo:rustc-link-lib=dylib=z");
+    }
+}
+
+fn main() {
+    // Printing rerun-if-env-changed disables the default of rerunning whenever a file in the package changes
+    println!("cargo:rerun-if-changed=hw");
+
+    let hw_param_values = hw_param_values();
+    write_hw_params(&hw_param_values);
+
+    // The hardware model is only built when the verilator feature is enabled. Otherwise the library falls back to
+    // the software model in src/protobridge/model.rs and doesn't require any of the HDL toolchain.
+    #[cfg(feature = "verilator")]
+    build_protobridge(&hw_param_values);
 }
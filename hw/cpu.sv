@@ -13,12 +13,44 @@ module cpu
     input  logic [31:0]         i_mem_data,
 
     input  logic                i_start_signal,
-    output logic                o_is_idle
+    output logic                o_is_idle,
+
+    // Number of extra cycles that instruction fetches and data loads or stores wait for memory
+    input  logic [7:0]          i_fetch_wait_states,
+    input  logic [7:0]          i_data_wait_states,
+
+    // Debug access to the cpu state for the host
+    // The cpu holds its current state while halted so it can be inspected consistently.
+    input  logic                i_dbg_halt,
+    input  logic [4:0]          i_dbg_reg_idx,
+    output logic [31:0]         o_dbg_reg_data,
+    output logic [31:0]         o_dbg_pc,
+    output logic [31:0]         o_dbg_inst,
+    output logic [31:0]         o_dbg_state,
+    output logic [31:0]         o_dbg_inst_count,
+    output logic [63:0]         o_dbg_cycle_count,
+    output logic [63:0]         o_dbg_instret,
+    output logic [31:0]         o_dbg_active_cycles,
+    output logic [31:0]         o_dbg_fetch_stall_cycles,
+    output logic [31:0]         o_dbg_data_stall_cycles
 );
 
 logic [31:0] r_pc;
 logic [31:0] r_inst_buf;
 
+// Number of instructions executed since reset
+// This is also the instret counter from Zicsr.
+logic [63:0] r_inst_count;
+
+// Number of cycles spent out of the idle state since reset, and how many of them were spent waiting on memory
+// This is also the cycle counter from Zicsr, so idle cycles and cycles halted by the host are never counted.
+logic [63:0] r_cycle_count;
+logic [31:0] r_fetch_stall_cycles;
+logic [31:0] r_data_stall_cycles;
+
+// Counts the wait states of the current memory access
+logic [7:0] r_wait_count;
+
 reg [31:0] r_regs[30:0];
 
 typedef enum
@@ -29,7 +61,8 @@ typedef enum
     cpu_state_decode,
     cpu_state_execute,
     cpu_state_memory_load_wait,
-    cpu_state_memory_load_execute
+    cpu_state_memory_load_execute,
+    cpu_state_memory_store_wait
 } cpu_state;
 
 cpu_state r_state;
@@ -69,19 +102,93 @@ assign w_decode_rs2_reg_val = (w_decode_rs2 != 0) ? r_regs[(w_decode_rs2 - 1)] :
 
 assign o_is_idle = (r_state == cpu_state_idle);
 
+// Only the read-only counters from Zicsr are implemented
+// The device has no real time clock, so time counts cycles like cycle does.
+logic [31:0] w_csr_read_data;
+logic        w_csr_is_valid;
+
+always_comb
+    begin
+        w_csr_is_valid = 1;
+        case (w_decode_imm[11:0])
+            // cycle, time
+            12'hC00,
+            12'hC01:
+                begin
+                    w_csr_read_data = r_cycle_count[31:0];
+                end
+            // instret
+            12'hC02:
+                begin
+                    w_csr_read_data = r_inst_count[31:0];
+                end
+            // cycleh, timeh
+            12'hC80,
+            12'hC81:
+                begin
+                    w_csr_read_data = r_cycle_count[63:32];
+                end
+            // instreth
+            12'hC82:
+                begin
+                    w_csr_read_data = r_inst_count[63:32];
+                end
+            default:
+                begin
+                    w_csr_read_data = 0;
+                    w_csr_is_valid = 0;
+                end
+        endcase
+    end
+
+// csrrw and csrrwi always write, while the other csr instructions only write when rs1 or the immediate is non-zero
+logic w_csr_is_write;
+assign w_csr_is_write = (w_decode_func[1:0] == 2'b01) || (w_decode_rs1 != 0);
+
+assign o_dbg_reg_data = (i_dbg_reg_idx != 0) ? r_regs[(i_dbg_reg_idx - 1)] : 0;
+assign o_dbg_pc = r_pc;
+assign o_dbg_inst = r_inst_buf;
+assign o_dbg_state = 32'(r_state);
+assign o_dbg_inst_count = r_inst_count[31:0];
+assign o_dbg_cycle_count = r_cycle_count;
+assign o_dbg_instret = r_inst_count;
+assign o_dbg_active_cycles = r_cycle_count[31:0];
+assign o_dbg_fetch_stall_cycles = r_fetch_stall_cycles;
+assign o_dbg_data_stall_cycles = r_data_stall_cycles;
+
 always_ff @ (posedge i_clk)
     if (i_rst)
         begin
             r_state <= cpu_state_idle;
             r_pc <= 0;
             r_inst_buf <= 0;
+            r_inst_count <= 0;
+            r_cycle_count <= 0;
+            r_fetch_stall_cycles <= 0;
+            r_data_stall_cycles <= 0;
+            r_wait_count <= 0;
 
             o_mem_write_en <= 0;
             o_mem_req_size <= common::mem_req_size_word;
             o_mem_addr <= 0;
         end
-    else
+    else if (!i_dbg_halt)
         begin
+            if (r_state != cpu_state_idle)
+                begin
+                    r_cycle_count <= r_cycle_count + 1;
+                end
+
+            if (r_state == cpu_state_fetch_wait)
+                begin
+                    r_fetch_stall_cycles <= r_fetch_stall_cycles + 1;
+                end
+
+            if ((r_state == cpu_state_memory_load_wait) || (r_state == cpu_state_memory_store_wait))
+                begin
+                    r_data_stall_cycles <= r_data_stall_cycles + 1;
+                end
+
             case (r_state)
                 cpu_state_idle:
                     begin
@@ -101,7 +208,16 @@ always_ff @ (posedge i_clk)
                     end
                 cpu_state_fetch_wait:
                     begin
-                        r_state <= cpu_state_decode;
+                        // Wait for the fetch to complete, the memory keeps returning the same data while we wait
+                        if (r_wait_count < i_fetch_wait_states)
+                            begin
+                                r_wait_count <= r_wait_count + 8'd1;
+                            end
+                        else
+                            begin
+                                r_wait_count <= 0;
+                                r_state <= cpu_state_decode;
+                            end
                     end
                 cpu_state_decode:
                     begin
@@ -114,6 +230,8 @@ always_ff @ (posedge i_clk)
                         // Execute the instruction if it's valid
                         if (w_decode_valid)
                             begin
+                                r_inst_count <= r_inst_count + 1;
+
                                 // Default to moving the PC to the next instruction
                                 // Several instructions may override this behavior
                                 r_pc <= r_pc + 4;
@@ -269,6 +387,11 @@ always_ff @ (posedge i_clk)
                                             o_mem_req_size <= common::mem_req_size_byte;
                                             o_mem_addr <= w_decode_rs1_reg_val + { { 12 { w_decode_imm[19] } }, w_decode_imm };
                                             o_mem_data <= w_decode_rs2_reg_val;
+
+                                            if (i_data_wait_states != 0)
+                                                begin
+                                                    r_state <= cpu_state_memory_store_wait;
+                                                end
                                         end
 
                                     // sh
@@ -278,6 +401,11 @@ always_ff @ (posedge i_clk)
                                             o_mem_req_size <= common::mem_req_size_half;
                                             o_mem_addr <= w_decode_rs1_reg_val + { { 12 { w_decode_imm[19] } }, w_decode_imm };
                                             o_mem_data <= w_decode_rs2_reg_val;
+
+                                            if (i_data_wait_states != 0)
+                                                begin
+                                                    r_state <= cpu_state_memory_store_wait;
+                                                end
                                         end
 
                                     // sw
@@ -287,6 +415,11 @@ always_ff @ (posedge i_clk)
                                             o_mem_req_size <= common::mem_req_size_word;
                                             o_mem_addr <= w_decode_rs1_reg_val + { { 12 { w_decode_imm[19] } }, w_decode_imm };
                                             o_mem_data <= w_decode_rs2_reg_val;
+
+                                            if (i_data_wait_states != 0)
+                                                begin
+                                                    r_state <= cpu_state_memory_store_wait;
+                                                end
                                         end
 
                                     // addi
@@ -498,17 +631,33 @@ always_ff @ (posedge i_clk)
                                                 end
                                         end
 
+                                    // csrrw, csrrs, csrrc, csrrwi, csrrsi, csrrci
+                                    17'b???????0011110011,
+                                    17'b???????0101110011,
+                                    17'b???????0111110011,
+                                    17'b???????1011110011,
+                                    17'b???????1101110011,
+                                    17'b???????1111110011:
+                                        begin
+                                            if (w_csr_is_valid && !w_csr_is_write)
+                                                begin
+                                                    if (w_decode_rd_is_valid)
+                                                        begin
+                                                            r_regs[w_decode_rd_idx] <= w_csr_read_data;
+                                                        end
+                                                end
+                                            else
+                                                begin
+                                                    // Unknown csrs and writes to the read-only counters are illegal, so they stop the cpu like an invalid instruction
+                                                    r_state <= cpu_state_idle;
+                                                end
+                                        end
+
                                     // TODO: Unsupported Instructions
                                     //       fence
                                     //       fence.i
                                     //       ecall
                                     //       ebreak
-                                    //       csrrw
-                                    //       csrrs
-                                    //       csrrc
-                                    //       csrrwi
-                                    //       csrrsi
-                                    //       csrrci
 
                                 endcase
                             end
@@ -520,8 +669,16 @@ always_ff @ (posedge i_clk)
                     end
                 cpu_state_memory_load_wait:
                     begin
-                        // Wait a cycle for a memory load to occur
-                        r_state <= cpu_state_memory_load_execute;
+                        // Wait a cycle for a memory load to occur, plus any wait states
+                        if (r_wait_count < i_data_wait_states)
+                            begin
+                                r_wait_count <= r_wait_count + 8'd1;
+                            end
+                        else
+                            begin
+                                r_wait_count <= 0;
+                                r_state <= cpu_state_memory_load_execute;
+                            end
                     end
                 cpu_state_memory_load_execute:
                     begin
@@ -561,6 +718,21 @@ always_ff @ (posedge i_clk)
 
                         r_state <= cpu_state_fetch;
                     end
+                cpu_state_memory_store_wait:
+                    begin
+                        // The store is performed during the first cycle of this state so the write is only active once
+                        o_mem_write_en <= 0;
+
+                        if (r_wait_count < (i_data_wait_states - 8'd1))
+                            begin
+                                r_wait_count <= r_wait_count + 8'd1;
+                            end
+                        else
+                            begin
+                                r_wait_count <= 0;
+                                r_state <= cpu_state_fetch;
+                            end
+                    end
             endcase
         end
 
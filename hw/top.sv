@@ -1,6 +1,21 @@
 `include "common.sv"
 
+// The parameters can be overridden with verilator's -G option, see build.rs
+// The host discovers the memory size and fifo depth at runtime through the DEV_MEM_SIZE and DEV_FIFO_DEPTH registers.
 module top
+#(
+    // Size of the device memory in bytes, the cpu sees the registers directly after it
+    parameter MEM_SIZE = 1024 * 1024,
+    // Number of bytes that fit inside each of the host interface fifos, must be a power of two
+    parameter FIFO_DEPTH = 16,
+    // Address of the first framebuffer after reset, the other buffers follow it
+    parameter FB_ADDR = 32'hC0000,
+    // Log2 of the framebuffer width and height after reset
+    parameter FB_DIM_LOG2 = 6,
+    // Number of extra cycles that cpu instruction fetches and data accesses wait for memory after reset
+    parameter FETCH_WAIT_STATES = 0,
+    parameter DATA_WAIT_STATES = 0
+)
 (
     input  logic i_clk,
     input  logic i_rst_n,
@@ -18,7 +33,7 @@ wire       w_in_fifo_empty;
 reg        r_in_fifo_read;
 wire [7:0] w_in_fifo_output;
 
-fifo #(.BITS_PER_ELEMENT(8), .MAX_ELEMENTS(16)) input_fifo
+fifo #(.BITS_PER_ELEMENT(8), .MAX_ELEMENTS(FIFO_DEPTH)) input_fifo
 (
     .i_clk(i_clk),
     .i_rst(!i_rst_n),
@@ -37,7 +52,7 @@ wire       w_out_fifo_full;
 reg        r_out_fifo_write;
 reg [7:0]  r_out_fifo_input;
 
-fifo #(.BITS_PER_ELEMENT(8), .MAX_ELEMENTS(16)) output_fifo
+fifo #(.BITS_PER_ELEMENT(8), .MAX_ELEMENTS(FIFO_DEPTH)) output_fifo
 (
     .i_clk(i_clk),
     .i_rst(!i_rst_n),
@@ -75,10 +90,8 @@ assign w_dev_rst = !i_rst_n || r_dev_rst;
 // 4KB of 32 bit registers
 localparam REG_SPACE_SIZE = 4 * 1024;
 
-// 1MB of memory
-localparam MEM_SIZE = 1024 * 1024;
-
-logic [7:0] r_mem[MEM_SIZE-1:0];
+// Memory is public so the host can access it directly through protobridge without going through the fifos
+logic [7:0] r_mem[MEM_SIZE-1:0] /*verilator public*/;
 
 logic                w_cpu_mem_write_en;
 common::mem_req_size w_cpu_mem_req_size_out;
@@ -87,6 +100,51 @@ logic [31:0]         w_cpu_mem_data_out;
 logic [31:0]         r_cpu_mem_data_in;
 logic                r_cpu_start_signal;
 logic                w_cpu_is_idle;
+logic                r_cpu_dbg_halt;
+logic [4:0]          w_cpu_dbg_reg_idx;
+logic [31:0]         w_cpu_dbg_reg_data;
+logic [31:0]         w_cpu_dbg_pc;
+logic [31:0]         w_cpu_dbg_inst;
+logic [31:0]         w_cpu_dbg_state;
+logic [31:0]         w_cpu_dbg_inst_count;
+logic [63:0]         w_cpu_dbg_cycle_count;
+logic [63:0]         w_cpu_dbg_instret;
+logic [31:0]         w_cpu_dbg_active_cycles;
+logic [31:0]         w_cpu_dbg_fetch_stall_cycles;
+logic [31:0]         w_cpu_dbg_data_stall_cycles;
+
+// Wait states let the memory approximate the latency of a real SRAM or DRAM target
+// The fetch wait states are held in bits 7:0 and the data wait states in bits 15:8.
+logic [31:0]         r_mem_wait_states;
+
+// Hardware breakpoints halt the cpu as it's about to fetch an instruction at a matching address
+// Each breakpoint holds a word aligned address with bit 0 set while the breakpoint is enabled.
+localparam NUM_BREAKPOINTS = 4;
+localparam CPU_STATE_FETCH = 1;
+
+logic [31:0] r_dbg_breakpoints[NUM_BREAKPOINTS-1:0];
+logic        r_dbg_break_hit;
+// Lets the cpu fetch the instruction it stopped at once the host resumes from a breakpoint
+logic        r_dbg_break_skip;
+logic        w_dbg_break_match;
+logic        w_cpu_halt;
+
+always_comb
+    begin
+        w_dbg_break_match = 0;
+        for (int i = 0; i < NUM_BREAKPOINTS; i++)
+            begin
+                if (r_dbg_breakpoints[i][0] && (r_dbg_breakpoints[i][31:2] == w_cpu_dbg_pc[31:2]))
+                    begin
+                        w_dbg_break_match = 1;
+                    end
+            end
+
+        w_dbg_break_match = w_dbg_break_match && (w_cpu_dbg_state == CPU_STATE_FETCH) && !r_dbg_break_skip;
+    end
+
+// The match halts the cpu immediately so it never leaves the fetch state before the hit is registered
+assign w_cpu_halt = r_cpu_dbg_halt || r_dbg_break_hit || w_dbg_break_match;
 
 cpu cpu
 (
@@ -101,7 +159,23 @@ cpu cpu
     .i_mem_data(r_cpu_mem_data_in),
 
     .i_start_signal(r_cpu_start_signal),
-    .o_is_idle(w_cpu_is_idle)
+    .o_is_idle(w_cpu_is_idle),
+
+    .i_fetch_wait_states(r_mem_wait_states[7:0]),
+    .i_data_wait_states(r_mem_wait_states[15:8]),
+
+    .i_dbg_halt(w_cpu_halt),
+    .i_dbg_reg_idx(w_cpu_dbg_reg_idx),
+    .o_dbg_reg_data(w_cpu_dbg_reg_data),
+    .o_dbg_pc(w_cpu_dbg_pc),
+    .o_dbg_inst(w_cpu_dbg_inst),
+    .o_dbg_state(w_cpu_dbg_state),
+    .o_dbg_inst_count(w_cpu_dbg_inst_count),
+    .o_dbg_cycle_count(w_cpu_dbg_cycle_count),
+    .o_dbg_instret(w_cpu_dbg_instret),
+    .o_dbg_active_cycles(w_cpu_dbg_active_cycles),
+    .o_dbg_fetch_stall_cycles(w_cpu_dbg_fetch_stall_cycles),
+    .o_dbg_data_stall_cycles(w_cpu_dbg_data_stall_cycles)
 );
 
 wire w_cmd_parser_data_valid;
@@ -148,6 +222,9 @@ assign w_cmd_addr_is_reg = (w_cmd_parser_cmd_addr >= 'h3ffff000);
 wire [9:0] w_cmd_reg_idx;
 assign w_cmd_reg_idx = w_cmd_parser_cmd_addr[11:2];
 
+// The cpu register file is read through the debug registers
+assign w_cpu_dbg_reg_idx = w_cmd_reg_idx[4:0];
+
 wire [31:0] w_cmd_reg_data;
 assign w_cmd_reg_data = w_cmd_parser_cmd_size;
 
@@ -162,12 +239,27 @@ reg [3:0]  r_reg_read_bytes_remaining;
 wire [31:0] w_dev_en;
 assign w_dev_en = { 31'b0, !w_cpu_is_idle };
 
-localparam FB_ADDR = 32'hC0000;
-localparam FB_DIM = 3'($clog2(64) - 1);
+localparam FB_DIM = 3'(FB_DIM_LOG2 - 1);
+localparam FB_SIZE = (1 << (FB_DIM + 1)) * (1 << (FB_DIM + 1)) * 4;
+
+// The cpu draws into a back buffer and flips it to the front by writing FB_FRONT
+// The buffers are placed next to each other by default so firmware that only uses the first one is unaffected.
+// The FB_BUFFER registers start at index 16 so the low bits of a register index select the buffer, which limits
+// the number of buffers to 16.
+localparam NUM_FB_BUFFERS = 2;
 
-reg [31:0] r_fb_addr;
+reg [31:0] r_fb_buffers[NUM_FB_BUFFERS-1:0];
+reg [31:0] r_fb_front;
 reg [31:0] r_fb_config;
 
+// FB_ADDR always refers to the front buffer so the host never reads a buffer that's being drawn
+wire [31:0] w_fb_addr;
+assign w_fb_addr = r_fb_buffers[r_fb_front[$clog2(NUM_FB_BUFFERS)-1:0]];
+
+// Number of frames the cpu has presented by writing FB_PRESENT since reset
+// The host waits for this to change before reading the framebuffer so it never sees a partially drawn frame.
+reg [31:0] r_fb_frame_count;
+
 always_ff @ (posedge i_clk)
     if (!i_rst_n)
         begin
@@ -176,13 +268,26 @@ always_ff @ (posedge i_clk)
             r_in_fifo_read <= 0;
             r_out_fifo_write <= 0;
             r_cpu_start_signal <= 0;
+            r_cpu_dbg_halt <= 0;
+            r_dbg_break_hit <= 0;
+            r_dbg_break_skip <= 0;
+            for (int i = 0; i < NUM_BREAKPOINTS; i++)
+                begin
+                    r_dbg_breakpoints[i] <= 0;
+                end
             r_transfer_cur_addr <= 0;
             r_cmd_parser_clear_cmd <= 0;
             r_reg_read_data <= 0;
             r_reg_read_bytes_remaining <= 0;
 
-            r_fb_addr <= 32'hC0000;
+            for (int i = 0; i < NUM_FB_BUFFERS; i++)
+                begin
+                    r_fb_buffers[i] <= FB_ADDR + (i * FB_SIZE);
+                end
+            r_fb_front <= 0;
             r_fb_config <= { 26'b0, FB_DIM, FB_DIM };
+            r_fb_frame_count <= 0;
+            r_mem_wait_states <= { 16'b0, 8'(DATA_WAIT_STATES), 8'(FETCH_WAIT_STATES) };
         end
     else
         begin
@@ -192,6 +297,17 @@ always_ff @ (posedge i_clk)
                     r_cpu_start_signal <= 0;
                 end
 
+            if (w_dbg_break_match)
+                begin
+                    r_dbg_break_hit <= 1;
+                end
+
+            // The skip only applies to the fetch that the cpu stopped at
+            if (w_cpu_dbg_state != CPU_STATE_FETCH)
+                begin
+                    r_dbg_break_skip <= 0;
+                end
+
             case (r_state)
                 cmd_state_idle:
                     begin
@@ -237,7 +353,35 @@ always_ff @ (posedge i_clk)
                                             end
                                         else if (w_cpu_mem_addr_out < MEM_SIZE + REG_SPACE_SIZE)
                                             begin
-                                                // TODO: Support register writes from the cpu
+                                                case (w_cpu_mem_addr_out[11:2])
+                                                    // FB_PRESENT
+                                                    13:
+                                                        begin
+                                                            // The write stays active while the cpu is halted so it's only counted once the cpu moves on
+                                                            if (!w_cpu_halt)
+                                                                begin
+                                                                    r_fb_frame_count <= r_fb_frame_count + 1;
+                                                                end
+                                                        end
+                                                    // FB_FRONT
+                                                    15:
+                                                        begin
+                                                            // Flips to another buffer, out of range indices are ignored
+                                                            if (w_cpu_mem_data_out < NUM_FB_BUFFERS)
+                                                                begin
+                                                                    r_fb_front <= w_cpu_mem_data_out;
+                                                                end
+                                                        end
+                                                    default:
+                                                        begin
+                                                            // FB_BUFFER0 - FB_BUFFERn
+                                                            if ((w_cpu_mem_addr_out[11:2] >= 16) && (w_cpu_mem_addr_out[11:2] < (16 + NUM_FB_BUFFERS)))
+                                                                begin
+                                                                    r_fb_buffers[w_cpu_mem_addr_out[2 +: $clog2(NUM_FB_BUFFERS)]] <= w_cpu_mem_data_out;
+                                                                end
+                                                            // TODO: Support writes to the other registers from the cpu
+                                                        end
+                                                endcase
                                             end
                                         else
                                             begin
@@ -279,17 +423,50 @@ always_ff @ (posedge i_clk)
                                                     // FB_ADDR
                                                     1:
                                                         begin
-                                                            r_cpu_mem_data_in <= r_fb_addr;
+                                                            r_cpu_mem_data_in <= w_fb_addr;
                                                         end
                                                     // FB_CONFIG
                                                     2:
                                                         begin
                                                             r_cpu_mem_data_in <= r_fb_config;
                                                         end
+                                                    // FB_FRAME_COUNT
+                                                    14:
+                                                        begin
+                                                            r_cpu_mem_data_in <= r_fb_frame_count;
+                                                        end
+                                                    // FB_FRONT
+                                                    15:
+                                                        begin
+                                                            r_cpu_mem_data_in <= r_fb_front;
+                                                        end
+                                                    // DEV_MEM_SIZE
+                                                    64:
+                                                        begin
+                                                            r_cpu_mem_data_in <= MEM_SIZE;
+                                                        end
+                                                    // DEV_FIFO_DEPTH
+                                                    65:
+                                                        begin
+                                                            r_cpu_mem_data_in <= FIFO_DEPTH;
+                                                        end
+                                                    // MEM_WAIT_STATES
+                                                    66:
+                                                        begin
+                                                            r_cpu_mem_data_in <= r_mem_wait_states;
+                                                        end
                                                     default:
                                                         begin
-                                                            // Return 0 for unknown registers
-                                                            r_cpu_mem_data_in <= 0;
+                                                            // FB_BUFFER0 - FB_BUFFERn
+                                                            if ((w_cpu_mem_addr_out[11:2] >= 16) && (w_cpu_mem_addr_out[11:2] < (16 + NUM_FB_BUFFERS)))
+                                                                begin
+                                                                    r_cpu_mem_data_in <= r_fb_buffers[w_cpu_mem_addr_out[2 +: $clog2(NUM_FB_BUFFERS)]];
+                                                                end
+                                                            else
+                                                                begin
+                                                                    // Return 0 for unknown registers
+                                                                    r_cpu_mem_data_in <= 0;
+                                                                end
                                                         end
                                                 endcase
                                             end
@@ -326,17 +503,123 @@ always_ff @ (posedge i_clk)
                                                     // FB_ADDR
                                                     1:
                                                         begin
-                                                            r_reg_read_data <= r_fb_addr;
+                                                            r_reg_read_data <= w_fb_addr;
                                                         end
                                                     // FB_CONFIG
                                                     2:
                                                         begin
                                                             r_reg_read_data <= r_fb_config;
                                                         end
+                                                    // DBG_PC
+                                                    3:
+                                                        begin
+                                                            r_reg_read_data <= w_cpu_dbg_pc;
+                                                        end
+                                                    // DBG_INST
+                                                    4:
+                                                        begin
+                                                            r_reg_read_data <= w_cpu_dbg_inst;
+                                                        end
+                                                    // DBG_CPU_STATE
+                                                    5:
+                                                        begin
+                                                            r_reg_read_data <= w_cpu_dbg_state;
+                                                        end
+                                                    // DBG_INST_COUNT
+                                                    6:
+                                                        begin
+                                                            r_reg_read_data <= w_cpu_dbg_inst_count;
+                                                        end
+                                                    // DBG_HALT
+                                                    7:
+                                                        begin
+                                                            r_reg_read_data <= { 31'b0, r_cpu_dbg_halt };
+                                                        end
+                                                    // DBG_BREAK
+                                                    8:
+                                                        begin
+                                                            r_reg_read_data <= { 31'b0, r_dbg_break_hit };
+                                                        end
+                                                    // DBG_BREAKPOINT0 - DBG_BREAKPOINT3
+                                                    9, 10, 11, 12:
+                                                        begin
+                                                            r_reg_read_data <= r_dbg_breakpoints[2'(w_cmd_reg_idx - 9)];
+                                                        end
+                                                    // FB_FRAME_COUNT
+                                                    14:
+                                                        begin
+                                                            r_reg_read_data <= r_fb_frame_count;
+                                                        end
+                                                    // FB_FRONT
+                                                    15:
+                                                        begin
+                                                            r_reg_read_data <= r_fb_front;
+                                                        end
+                                                    // DEV_MEM_SIZE
+                                                    64:
+                                                        begin
+                                                            r_reg_read_data <= MEM_SIZE;
+                                                        end
+                                                    // DEV_FIFO_DEPTH
+                                                    65:
+                                                        begin
+                                                            r_reg_read_data <= FIFO_DEPTH;
+                                                        end
+                                                    // MEM_WAIT_STATES
+                                                    66:
+                                                        begin
+                                                            r_reg_read_data <= r_mem_wait_states;
+                                                        end
+                                                    // DBG_ACTIVE_CYCLES
+                                                    67:
+                                                        begin
+                                                            r_reg_read_data <= w_cpu_dbg_active_cycles;
+                                                        end
+                                                    // DBG_FETCH_STALL_CYCLES
+                                                    68:
+                                                        begin
+                                                            r_reg_read_data <= w_cpu_dbg_fetch_stall_cycles;
+                                                        end
+                                                    // DBG_DATA_STALL_CYCLES
+                                                    69:
+                                                        begin
+                                                            r_reg_read_data <= w_cpu_dbg_data_stall_cycles;
+                                                        end
+                                                    // DBG_CYCLE_LO, DBG_CYCLE_HI
+                                                    70:
+                                                        begin
+                                                            r_reg_read_data <= w_cpu_dbg_cycle_count[31:0];
+                                                        end
+                                                    71:
+                                                        begin
+                                                            r_reg_read_data <= w_cpu_dbg_cycle_count[63:32];
+                                                        end
+                                                    // DBG_INSTRET_LO, DBG_INSTRET_HI
+                                                    72:
+                                                        begin
+                                                            r_reg_read_data <= w_cpu_dbg_instret[31:0];
+                                                        end
+                                                    73:
+                                                        begin
+                                                            r_reg_read_data <= w_cpu_dbg_instret[63:32];
+                                                        end
                                                     default:
                                                         begin
-                                                            // Return 0 for unknown registers
-                                                            r_reg_read_data <= 0;
+                                                            // FB_BUFFER0 - FB_BUFFERn
+                                                            if ((w_cmd_reg_idx >= 16) && (w_cmd_reg_idx < (16 + NUM_FB_BUFFERS)))
+                                                                begin
+                                                                    r_reg_read_data <= r_fb_buffers[w_cmd_reg_idx[$clog2(NUM_FB_BUFFERS)-1:0]];
+                                                                end
+                                                            // DBG_X0 - DBG_X31
+                                                            else if (w_cmd_reg_idx[9:5] == 1)
+                                                                begin
+                                                                    r_reg_read_data <= w_cpu_dbg_reg_data;
+                                                                end
+                                                            else
+                                                                begin
+                                                                    // Return 0 for unknown registers
+                                                                    r_reg_read_data <= 0;
+                                                                end
                                                         end
                                                 endcase
 
@@ -355,13 +638,33 @@ always_ff @ (posedge i_clk)
                                                 r_state <= cmd_state_idle;
                                                 r_cmd_parser_clear_cmd <= 1;
 
-                                                if (w_cpu_is_idle)
+                                                if (w_cmd_reg_idx == 7)
+                                                    begin
+                                                        // DBG_HALT can be written at any time so execution can be inspected
+                                                        r_cpu_dbg_halt <= w_cmd_reg_data[0];
+                                                    end
+                                                else if (w_cmd_reg_idx == 8)
+                                                    begin
+                                                        // DBG_BREAK: Clearing the hit resumes execution past the breakpoint
+                                                        r_dbg_break_hit <= w_cmd_reg_data[0];
+                                                        r_dbg_break_skip <= !w_cmd_reg_data[0];
+                                                    end
+                                                else if ((w_cmd_reg_idx >= 9) && (w_cmd_reg_idx < (9 + NUM_BREAKPOINTS)))
+                                                    begin
+                                                        r_dbg_breakpoints[2'(w_cmd_reg_idx - 9)] <= w_cmd_reg_data;
+                                                    end
+                                                else if (w_cpu_is_idle)
                                                     begin
                                                         case (w_cmd_reg_idx)
                                                             0:
                                                                 begin
                                                                     r_cpu_start_signal <= w_cmd_reg_data[0];
                                                                 end
+                                                            // MEM_WAIT_STATES: Only changed while idle so an access never sees two settings
+                                                            66:
+                                                                begin
+                                                                    r_mem_wait_states <= { 16'b0, w_cmd_reg_data[15:0] };
+                                                                end
                                                             default:
                                                                 begin
                                                                     // Do nothing for unknown registers
@@ -0,0 +1,347 @@
+// Native side of src/protobridge.rs
+//
+// Wraps the verilated model of hw/top.sv behind a small C interface that the host clocks one cycle at a time.
+// src/protobridge/model.rs implements the same entry points in software and documents the expected behavior.
+//
+// Supports Verilator 4.028 or later. The backdoor memory access depends on where Verilator places the public r_mem
+// array of top.sv, which moved from the top level class into the root module in 4.210.
+
+#include <cstddef>
+#include <cstdint>
+
+#include <verilated.h>
+
+#include "Vtop.h"
+
+#if PROTOBRIDGE_TRACE
+#if PROTOBRIDGE_TRACE_FST
+#include <verilated_fst_c.h>
+#else
+#include <verilated_vcd_c.h>
+#endif
+#endif
+
+#if defined(VERILATOR_VERSION_INTEGER) && (VERILATOR_VERSION_INTEGER >= 4210000)
+#include "Vtop___024root.h"
+#define PROTOBRIDGE_MEM(top) ((top)->rootp->top__DOT__r_mem)
+#else
+#define PROTOBRIDGE_MEM(top) ((top)->top__DOT__r_mem)
+#endif
+
+// Entry points have to be exported explicitly when building a DLL
+#if defined(_WIN32) && PROTOBRIDGE_SHARED
+#define PROTOBRIDGE_API extern "C" __declspec(dllexport)
+#else
+#define PROTOBRIDGE_API extern "C"
+#endif
+
+// Number of cycles that the reset is held for when the device is created
+static const uint32_t kNumResetCycles = 4;
+
+// Matches the TRACE_FORMAT constants in src/protobridge.rs
+static const uint32_t kTraceFormatVcd = 0;
+static const uint32_t kTraceFormatFst = 1;
+
+#if PROTOBRIDGE_TRACE
+#if PROTOBRIDGE_TRACE_FST
+typedef VerilatedFstC ProtoBridgeTrace;
+static const uint32_t kTraceFormat = kTraceFormatFst;
+#else
+typedef VerilatedVcdC ProtoBridgeTrace;
+static const uint32_t kTraceFormat = kTraceFormatVcd;
+#endif
+
+// Number of levels of the design hierarchy that are written into traces
+static const int kTraceDepth = 99;
+#endif
+
+struct ProtoBridge
+{
+    Vtop* pTop;
+
+    // Number of cycles that the host has clocked the device for, which is used as the trace timestamp
+    uint64_t cycles;
+
+#if PROTOBRIDGE_TRACE
+    // Created by the first trace and reopened for every trace after it since the model can only be attached once
+    ProtoBridgeTrace* pTrace;
+#endif
+};
+
+// Matches DataStatus in src/protobridge.rs
+struct DataStatus
+{
+    uint8_t isInputFull;
+    uint8_t isOutputEmpty;
+};
+
+// Returns the number of elements in the model's memory array
+// The array is a plain C array in older versions of Verilator and a VlUnpacked in newer ones, but both store the
+// elements inline.
+template <typename T>
+static size_t MemorySize(const T& mem)
+{
+    return sizeof(mem) / sizeof(mem[0]);
+}
+
+// Returns true if the range lies entirely inside of device memory
+static bool IsMemoryRangeValid(size_t memSize, uint32_t addr, size_t size)
+{
+    return (addr <= memSize) && (size <= (memSize - addr));
+}
+
+static void Tick(Vtop* pTop)
+{
+    pTop->i_clk = 1;
+    pTop->eval();
+
+    pTop->i_clk = 0;
+    pTop->eval();
+}
+
+PROTOBRIDGE_API uint32_t CreateProtoBridge(ProtoBridge** ppBridge)
+{
+#if PROTOBRIDGE_TRACE
+    Verilated::traceEverOn(true);
+#endif
+
+    ProtoBridge* pBridge = new ProtoBridge();
+    pBridge->pTop = new Vtop();
+    pBridge->cycles = 0;
+
+#if PROTOBRIDGE_TRACE
+    pBridge->pTrace = nullptr;
+#endif
+
+    Vtop* pTop = pBridge->pTop;
+    pTop->i_clk = 0;
+    pTop->i_rst_n = 0;
+    pTop->i_data_valid = 0;
+    pTop->i_data = 0;
+    pTop->i_data_read = 0;
+    pTop->eval();
+
+    // All of the registers in the design are reset synchronously
+    for (uint32_t cycleIndex = 0; cycleIndex < kNumResetCycles; ++cycleIndex)
+    {
+        Tick(pTop);
+    }
+
+    pTop->i_rst_n = 1;
+    pTop->eval();
+
+    *ppBridge = pBridge;
+
+    return 0;
+}
+
+PROTOBRIDGE_API void DestroyProtoBridge(ProtoBridge* pBridge)
+{
+#if PROTOBRIDGE_TRACE
+    if (pBridge->pTrace != nullptr)
+    {
+        pBridge->pTrace->close();
+        delete pBridge->pTrace;
+    }
+#endif
+
+    pBridge->pTop->final();
+
+    delete pBridge->pTop;
+    delete pBridge;
+}
+
+PROTOBRIDGE_API DataStatus QueryProtoBridgeDataStatus(ProtoBridge* pBridge)
+{
+    DataStatus status = {};
+    status.isInputFull = pBridge->pTop->o_input_full;
+    status.isOutputEmpty = pBridge->pTop->o_output_empty;
+
+    return status;
+}
+
+// Clocks the device once
+// A non-null input is pushed into the input fifo and a non-null output receives the head of the output fifo, which
+// is popped on the same edge.
+PROTOBRIDGE_API void ClockProtoBridge(ProtoBridge* pBridge, const uint8_t* pInput, uint8_t* pOutput)
+{
+    Vtop* pTop = pBridge->pTop;
+
+    pTop->i_data_valid = (pInput != nullptr);
+    pTop->i_data = (pInput != nullptr) ? *pInput : 0;
+    pTop->i_data_read = (pOutput != nullptr);
+    pTop->eval();
+
+    // o_data is sampled before the edge since it reflects the head of the fifo until the read completes
+    if (pOutput != nullptr)
+    {
+        *pOutput = pTop->o_data;
+    }
+
+    Tick(pTop);
+
+    pTop->i_data_valid = 0;
+    pTop->i_data_read = 0;
+    pTop->eval();
+
+    ++pBridge->cycles;
+
+#if PROTOBRIDGE_TRACE
+    if ((pBridge->pTrace != nullptr) && pBridge->pTrace->isOpen())
+    {
+        pBridge->pTrace->dump(pBridge->cycles);
+    }
+#endif
+}
+
+// Clocks the device until maxCycles have elapsed or outputCapacity bytes have been received
+// Input bytes are pushed into the device whenever the input fifo has space. A zero capacity runs the full cycle count
+// without receiving any output. This behaves exactly like calling ClockProtoBridge once per cycle, but the host only
+// crosses into the bridge once per batch. Returns the number of cycles that elapsed.
+PROTOBRIDGE_API uint64_t ClockProtoBridgeBatch(ProtoBridge* pBridge,
+                                               const uint8_t* pInput,
+                                               size_t inputSize,
+                                               size_t* pInputConsumed,
+                                               uint8_t* pOutput,
+                                               size_t outputCapacity,
+                                               size_t* pOutputProduced,
+                                               uint64_t maxCycles)
+{
+    size_t inputConsumed = 0;
+    size_t outputProduced = 0;
+    uint64_t cycles = 0;
+
+    while ((cycles < maxCycles) && ((outputCapacity == 0) || (outputProduced < outputCapacity)))
+    {
+        const DataStatus status = QueryProtoBridgeDataStatus(pBridge);
+
+        const uint8_t* pCycleInput =
+            ((inputConsumed < inputSize) && (status.isInputFull == 0)) ? &pInput[inputConsumed] : nullptr;
+        uint8_t* pCycleOutput =
+            ((outputProduced < outputCapacity) && (status.isOutputEmpty == 0)) ? &pOutput[outputProduced] : nullptr;
+
+        ClockProtoBridge(pBridge, pCycleInput, pCycleOutput);
+
+        if (pCycleInput != nullptr)
+        {
+            ++inputConsumed;
+        }
+        if (pCycleOutput != nullptr)
+        {
+            ++outputProduced;
+        }
+
+        ++cycles;
+    }
+
+    *pInputConsumed = inputConsumed;
+    *pOutputProduced = outputProduced;
+
+    return cycles;
+}
+
+// Copies device memory into the provided buffer and returns the number of bytes that were read
+// Nothing is read and zero is returned if any part of the range lies outside of device memory.
+PROTOBRIDGE_API size_t ReadProtoBridgeMemory(ProtoBridge* pBridge, uint32_t addr, uint8_t* pData, size_t size)
+{
+    const auto& mem = PROTOBRIDGE_MEM(pBridge->pTop);
+    if (!IsMemoryRangeValid(MemorySize(mem), addr, size))
+    {
+        return 0;
+    }
+
+    for (size_t byteIndex = 0; byteIndex < size; ++byteIndex)
+    {
+        pData[byteIndex] = mem[addr + byteIndex];
+    }
+
+    return size;
+}
+
+// Copies the provided buffer into device memory and returns the number of bytes that were written
+// Nothing is written and zero is returned if any part of the range lies outside of device memory, so a rejected
+// access never leaves memory partially modified.
+PROTOBRIDGE_API size_t WriteProtoBridgeMemory(ProtoBridge* pBridge, uint32_t addr, const uint8_t* pData, size_t size)
+{
+    auto& mem = PROTOBRIDGE_MEM(pBridge->pTop);
+    if (!IsMemoryRangeValid(MemorySize(mem), addr, size))
+    {
+        return 0;
+    }
+
+    for (size_t byteIndex = 0; byteIndex < size; ++byteIndex)
+    {
+        mem[addr + byteIndex] = pData[byteIndex];
+    }
+
+    // Settle any combinational logic that depends on the memory contents
+    pBridge->pTop->eval();
+
+    return size;
+}
+
+// Begins writing a waveform trace of the device into the file at the provided path and returns zero on success
+// Traces can only be written in the format that the model was built with.
+PROTOBRIDGE_API uint32_t StartProtoBridgeTrace(ProtoBridge* pBridge, const char* pPath, uint32_t format)
+{
+#if PROTOBRIDGE_TRACE
+    if (format != kTraceFormat)
+    {
+        return 1;
+    }
+
+    if (pBridge->pTrace == nullptr)
+    {
+        pBridge->pTrace = new ProtoBridgeTrace();
+        pBridge->pTop->trace(pBridge->pTrace, kTraceDepth);
+    }
+
+    if (pBridge->pTrace->isOpen())
+    {
+        pBridge->pTrace->close();
+    }
+
+    pBridge->pTrace->open(pPath);
+    if (!pBridge->pTrace->isOpen())
+    {
+        return 1;
+    }
+
+    // The current state is written immediately so the trace begins on the cycle it was requested
+    pBridge->pTrace->dump(pBridge->cycles);
+
+    return 0;
+#else
+    (void)pBridge;
+    (void)pPath;
+    (void)format;
+
+    return 1;
+#endif
+}
+
+// Writes everything traced so far to disk without stopping the trace
+PROTOBRIDGE_API void FlushProtoBridgeTrace(ProtoBridge* pBridge)
+{
+#if PROTOBRIDGE_TRACE
+    if ((pBridge->pTrace != nullptr) && pBridge->pTrace->isOpen())
+    {
+        pBridge->pTrace->flush();
+    }
+#else
+    (void)pBridge;
+#endif
+}
+
+// Stops the current trace and flushes it to disk
+PROTOBRIDGE_API void StopProtoBridgeTrace(ProtoBridge* pBridge)
+{
+#if PROTOBRIDGE_TRACE
+    if ((pBridge->pTrace != nullptr) && pBridge->pTrace->isOpen())
+    {
+        pBridge->pTrace->close();
+    }
+#else
+    (void)pBridge;
+#endif
+}
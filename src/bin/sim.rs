@@ -1,6 +1,10 @@
 use clap::Clap;
-use devsim::device::Device;
+use devsim::device::{AccessMode, Device, MemoryWaitStates, PerfCounters, StallStats, TraceFormat};
 use image::RgbaImage;
+use std::cmp;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
 use std::time::Duration;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
@@ -12,18 +16,274 @@ struct SimOptions {
     elf_path: String,
 
     /// Path to write out the framebuffer as a png
+    /// When dumping frames, this contains a frame number pattern like frames/%05d.png.
     #[clap(short = 'o')]
     image_path: Option<String>,
+
+    /// Number of cycles between framebuffer dumps
+    #[clap(long)]
+    dump_every: Option<u64>,
+
+    /// Dumps the framebuffer each time the firmware presents a frame
+    #[clap(long)]
+    dump_on_present: bool,
+
+    /// Access device memory directly instead of through the host interface
+    #[clap(long)]
+    backdoor: bool,
+
+    /// Path to write a vcd waveform trace of the device
+    #[clap(long, conflicts_with = "fst")]
+    vcd: Option<String>,
+
+    /// Path to write an fst waveform trace of the device
+    #[clap(long)]
+    fst: Option<String>,
+
+    /// First cycle captured by the waveform trace
+    #[clap(long)]
+    trace_from: Option<u64>,
+
+    /// Cycle where the waveform trace stops
+    #[clap(long)]
+    trace_to: Option<u64>,
+
+    /// Extra cycles the cpu waits on each instruction fetch, overriding the value the hardware was built with
+    #[clap(long)]
+    fetch_wait_states: Option<u8>,
+
+    /// Extra cycles the cpu waits on each data load or store, overriding the value the hardware was built with
+    #[clap(long)]
+    data_wait_states: Option<u8>,
+}
+
+/// Output path used for frame dumps when none is specified
+const DEFAULT_FRAME_PATH: &str = "frames/%05d.png";
+
+/// Largest number of cycles the frame dumper executes before checking whether the device halted
+const MAX_CYCLES_PER_CHECK: u64 = 100_000;
+
+/// Replaces the printf style frame number pattern (%d or %05d) in a path with the provided frame number
+/// Returns None if the path doesn't contain a pattern.
+fn format_frame_path(pattern: &str, frame: u32) -> Option<String> {
+    let start = pattern.find('%')?;
+    let rest = &pattern[(start + 1)..];
+    let end = rest.find('d')?;
+    let width = &rest[..end];
+    if !width.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let width = if width.is_empty() {
+        0
+    } else {
+        width.parse().ok()?
+    };
+
+    Some(format!(
+        "{}{:0width$}{}",
+        &pattern[..start],
+        frame,
+        &rest[(end + 1)..],
+        width = width
+    ))
+}
+
+/// Reads the device framebuffer and writes it into a png file
+fn save_framebuffer(device: &mut Device, path: impl AsRef<Path>) -> Result<()> {
+    let (width, height) = device.query_framebuffer_size()?;
+    let fb_size = (width * height * 4) as usize;
+    let mut fb_data = vec![0; fb_size];
+
+    device.dump_framebuffer(&mut fb_data)?;
+
+    let image = RgbaImage::from_raw(width, height, fb_data)
+        .ok_or("Failed to create image from framebuffer")?;
+    image.save(path)?;
+
+    Ok(())
+}
+
+/// Controls when frames are dumped
+#[derive(Debug, Clone, Copy)]
+enum DumpTrigger {
+    /// Dumps a frame every time the specified number of cycles elapses
+    Interval(u64),
+    /// Dumps a frame every time the firmware presents one
+    Present,
+}
+
+/// Returns the clocks of the first interval boundary after clocks
+fn next_dump_clocks(clocks: u64, interval: u64) -> u64 {
+    ((clocks / interval) + 1).saturating_mul(interval)
+}
+
+/// Returns the number of the frame that belongs to the last interval boundary at or before clocks
+/// The frame at the first boundary is numbered 0.
+fn interval_frame_number(clocks: u64, interval: u64) -> u32 {
+    u32::try_from((clocks / interval).saturating_sub(1)).unwrap_or(u32::MAX)
+}
+
+/// Writes the framebuffer into a numbered png file whenever the trigger fires
+struct FrameDumper {
+    pattern: String,
+    trigger: DumpTrigger,
+    next_dump_clocks: u64,
+    /// Number of the next file that's written
+    next_frame: u32,
+    num_frames: u32,
+}
+
+impl FrameDumper {
+    fn new(pattern: String, trigger: DumpTrigger) -> Result<Self> {
+        let next_dump_clocks = match trigger {
+            DumpTrigger::Interval(0) => {
+                return Err("The frame dump interval must be at least one cycle".into())
+            }
+            DumpTrigger::Interval(interval) => interval,
+            DumpTrigger::Present => 0,
+        };
+        let first_path = format_frame_path(&pattern, 0)
+            .ok_or("The output path needs a frame number pattern like %05d to dump frames")?;
+        if let Some(dir) = Path::new(&first_path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        Ok(Self {
+            pattern,
+            trigger,
+            next_dump_clocks,
+            next_frame: 0,
+            num_frames: 0,
+        })
+    }
+
+    /// Executes the device until the next check and writes a frame if the trigger fired
+    fn update(&mut self, device: &mut Device) -> Result<()> {
+        match self.trigger {
+            DumpTrigger::Interval(interval) => {
+                // The device is run up to the next boundary so each dump shows the framebuffer at that exact cycle
+                let clocks = device.clocks();
+                if clocks < self.next_dump_clocks {
+                    let num_cycles = cmp::min(self.next_dump_clocks - clocks, MAX_CYCLES_PER_CHECK);
+                    device.run(num_cycles as usize);
+                }
+
+                let clocks = device.clocks();
+                if clocks >= self.next_dump_clocks {
+                    // Frames are numbered by their boundary, so a check that overshoots several boundaries leaves a gap
+                    // in the numbering instead of shifting every later frame
+                    self.next_frame = interval_frame_number(clocks, interval);
+                    self.dump(device)?;
+                    self.next_dump_clocks = next_dump_clocks(clocks, interval);
+                }
+            }
+            DumpTrigger::Present => {
+                if device.wait_for_present(MAX_CYCLES_PER_CHECK)?.is_some() {
+                    self.dump(device)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the current framebuffer into the next numbered file
+    fn dump(&mut self, device: &mut Device) -> Result<()> {
+        // The pattern was validated when the dumper was created
+        let path = format_frame_path(&self.pattern, self.next_frame).unwrap();
+        save_framebuffer(device, path)?;
+        self.next_frame += 1;
+        self.num_frames += 1;
+
+        Ok(())
+    }
+}
+
+/// Returns a count as a percentage of the total, or 0 if the total is 0
+fn percentage(count: u32, total: u32) -> f64 {
+    if total > 0 {
+        (count as f64 * 100.0) / total as f64
+    } else {
+        0.0
+    }
+}
+
+fn print_stall_stats(stats: &StallStats) {
+    println!("Active cycles: {}", stats.active_cycles);
+    println!(
+        "  Executing: {} ({:.1}%)",
+        stats.execute_cycles(),
+        percentage(stats.execute_cycles(), stats.active_cycles)
+    );
+    println!(
+        "  Fetch stalls: {} ({:.1}%)",
+        stats.fetch_stall_cycles,
+        percentage(stats.fetch_stall_cycles, stats.active_cycles)
+    );
+    println!(
+        "  Data stalls: {} ({:.1}%)",
+        stats.data_stall_cycles,
+        percentage(stats.data_stall_cycles, stats.active_cycles)
+    );
+}
+
+fn print_perf_counters(counters: &PerfCounters) {
+    println!("Instructions retired: {}", counters.instret);
+    match counters.cpi() {
+        Some(cpi) => println!("CPI: {:.3}", cpi),
+        None => println!("CPI: n/a"),
+    }
 }
 
 fn main() -> Result<()> {
     let opts = SimOptions::parse();
 
+    let trigger = match (opts.dump_every, opts.dump_on_present) {
+        (Some(_), true) => {
+            return Err("Frames can't be dumped on an interval and on present at once".into())
+        }
+        (Some(interval), false) => Some(DumpTrigger::Interval(interval)),
+        (None, true) => Some(DumpTrigger::Present),
+        (None, false) => None,
+    };
+    let mut frame_dumper = match trigger {
+        Some(trigger) => {
+            let pattern = opts.image_path.as_deref().unwrap_or(DEFAULT_FRAME_PATH);
+            Some(FrameDumper::new(pattern.to_string(), trigger)?)
+        }
+        None => None,
+    };
+
     let mut device = Device::new();
+    if opts.backdoor {
+        device.set_access_mode(AccessMode::Backdoor);
+    }
 
     // Load an elf from the command line arguments
     device.load_elf(&opts.elf_path)?;
 
+    // The wait states can only be changed while the cpu is idle
+    if opts.fetch_wait_states.is_some() || opts.data_wait_states.is_some() {
+        let wait_states = device.query_memory_wait_states()?;
+        device.set_memory_wait_states(MemoryWaitStates {
+            fetch: opts.fetch_wait_states.unwrap_or(wait_states.fetch),
+            data: opts.data_wait_states.unwrap_or(wait_states.data),
+        })?;
+    }
+
+    if opts.trace_from.is_some() || opts.trace_to.is_some() {
+        device.trace_window(
+            opts.trace_from.unwrap_or(0),
+            opts.trace_to.unwrap_or(u64::MAX),
+        );
+    }
+
+    if let Some(vcd_path) = &opts.vcd {
+        device.start_trace(vcd_path, TraceFormat::Vcd)?;
+    } else if let Some(fst_path) = &opts.fst {
+        device.start_trace(fst_path, TraceFormat::Fst)?;
+    }
+
     // Enable the device
     device.enable();
 
@@ -43,6 +303,9 @@ fn main() -> Result<()> {
             Ok(is_halted) => {
                 if !is_halted {
                     // Still executing...
+                    if let Some(frame_dumper) = &mut frame_dumper {
+                        frame_dumper.update(&mut device)?;
+                    }
                 } else {
                     // The device has halted, break out of the loop
                     progress.total = device.clocks();
@@ -66,21 +329,63 @@ fn main() -> Result<()> {
         println!("Execution stopped due to timeout");
     }
 
-    let (width, height) = device.query_framebuffer_size()?;
-    let fb_size = (width * height * 4) as usize;
-    let mut fb_data = vec![0; fb_size];
+    print_perf_counters(&device.perf_counters()?);
+    print_stall_stats(&device.query_stall_stats()?);
 
-    device
-        .dump_framebuffer(&mut fb_data)
-        .expect("Failed to dump device framebuffer!");
-
-    let image = RgbaImage::from_raw(width, height, fb_data)
-        .expect("Failed to create image from framebuffer");
+    device.stop_trace()?;
 
-    let image_path: Option<&str> = opts.image_path.as_deref();
-    image
-        .save(image_path.unwrap_or("image.png"))
-        .expect("Failed to write image output!");
+    match &mut frame_dumper {
+        Some(frame_dumper) => {
+            // The final state is always captured so the last frame of the animation isn't lost
+            frame_dumper.dump(&mut device)?;
+            println!("Dumped {} frames", frame_dumper.num_frames);
+        }
+        None => {
+            let image_path: Option<&str> = opts.image_path.as_deref();
+            save_framebuffer(&mut device, image_path.unwrap_or("image.png"))?;
+        }
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_path_patterns() {
+        assert_eq!(
+            format_frame_path("frame%d.png", 7).as_deref(),
+            Some("frame7.png")
+        );
+        assert_eq!(
+            format_frame_path("frames/%05d.png", 7).as_deref(),
+            Some("frames/00007.png")
+        );
+        assert_eq!(
+            format_frame_path("frames/%05d.png", 123_456).as_deref(),
+            Some("frames/123456.png")
+        );
+        assert_eq!(format_frame_path("image.png", 7), None);
+        assert_eq!(format_frame_path("frames/%x.png", 7), None);
+        assert_eq!(format_frame_path("frames/%x_d.png", 7), None);
+    }
+
+    #[test]
+    fn dump_intervals() {
+        assert_eq!(next_dump_clocks(0, 10), 10);
+        assert_eq!(next_dump_clocks(10, 10), 20);
+        assert_eq!(next_dump_clocks(13, 10), 20);
+        assert_eq!(next_dump_clocks(u64::MAX, 10), u64::MAX);
+
+        assert_eq!(interval_frame_number(10, 10), 0);
+        assert_eq!(interval_frame_number(13, 10), 0);
+        assert_eq!(interval_frame_number(20, 10), 1);
+
+        // Overshooting several boundaries skips their frames without shifting the following ones
+        assert_eq!(next_dump_clocks(35, 10), 40);
+        assert_eq!(interval_frame_number(35, 10), 2);
+        assert_eq!(interval_frame_number(40, 10), 3);
+    }
+}
@@ -0,0 +1,70 @@
+use image::RgbaImage;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Returns a name for a capture that's unique between runs of the application
+/// Captures are named after the current time so files from separate runs are never overwritten.
+pub fn capture_name(prefix: &str) -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or(0);
+    format!("{}_{}", prefix, millis)
+}
+
+/// Writes framebuffer data from the device into a png file
+pub fn save_png(path: &Path, width: u32, height: u32, fb_data: &[u8]) -> Result<()> {
+    let image = RgbaImage::from_raw(width, height, fb_data.to_vec())
+        .ok_or("Framebuffer data is smaller than its size")?;
+    image.save(path)?;
+    Ok(())
+}
+
+/// Writes each distinct framebuffer it receives into a numbered png file
+pub struct FrameRecorder {
+    dir: PathBuf,
+    num_frames: u32,
+    /// Data of the most recently written frame, used to skip frames where the device output didn't change
+    last_frame: Vec<u8>,
+}
+
+impl FrameRecorder {
+    /// Creates a recorder that writes frames into the provided directory, creating it if necessary
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            num_frames: 0,
+            last_frame: Vec::new(),
+        })
+    }
+
+    /// Returns the directory that frames are written into
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the number of frames written so far
+    pub fn num_frames(&self) -> u32 {
+        self.num_frames
+    }
+
+    /// Writes the framebuffer data into the next numbered file if it differs from the previous frame
+    pub fn record(&mut self, width: u32, height: u32, fb_data: &[u8]) -> Result<()> {
+        if fb_data == self.last_frame.as_slice() {
+            return Ok(());
+        }
+
+        let path = self.dir.join(format!("{:05}.png", self.num_frames));
+        save_png(&path, width, height, fb_data)?;
+
+        self.num_frames += 1;
+        self.last_frame.clear();
+        self.last_frame.extend_from_slice(fb_data);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use crate::simulation::Simulation;
+use devsim::device::CpuDebugState;
+use devsim::disasm::{self, ABI_NAMES};
+use imgui::{im_str, Ui};
+
+const CHANGED_COLOR: [f32; 4] = [1.0, 0.4, 0.3, 1.0];
+const LABEL_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+/// Number of registers displayed in each column
+const REGS_PER_COLUMN: usize = 16;
+
+/// ImGui panel that displays the registers and execution state of the device cpu
+pub struct CpuPanel {
+    pub is_open: bool,
+    /// Cpu state that is currently displayed
+    state: Option<CpuDebugState>,
+    /// Marks the registers that changed since the previous read
+    changed: [bool; 32],
+    is_pc_changed: bool,
+}
+
+impl CpuPanel {
+    pub fn new() -> Self {
+        Self {
+            is_open: false,
+            state: None,
+            changed: [false; 32],
+            is_pc_changed: false,
+        }
+    }
+
+    /// Compares the latest cpu state from the simulation with the displayed state to find which values changed
+    fn update_state(&mut self, sim: &Simulation) {
+        let state = match sim.cpu_state() {
+            Some(state) => state,
+            None => {
+                self.state = None;
+                return;
+            }
+        };
+
+        if self.state.as_ref() == Some(state) {
+            return;
+        }
+
+        if let Some(prev_state) = &self.state {
+            for (idx, is_changed) in self.changed.iter_mut().enumerate() {
+                *is_changed = prev_state.regs[idx] != state.regs[idx];
+            }
+            self.is_pc_changed = prev_state.pc != state.pc;
+        }
+
+        self.state = Some(state.clone());
+    }
+
+    /// Draws the panel if it's open
+    /// The simulation only reads the cpu state while cpu state capture is enabled.
+    pub fn draw(&mut self, ui: &Ui, sim: &Simulation) {
+        if self.is_open {
+            self.update_state(sim);
+
+            let mut is_open = self.is_open;
+            imgui::Window::new(im_str!("CPU"))
+                .size([420.0, 480.0], imgui::Condition::FirstUseEver)
+                .opened(&mut is_open)
+                .build(ui, || self.draw_contents(ui, sim));
+            self.is_open = is_open;
+        }
+    }
+
+    fn draw_contents(&self, ui: &Ui, sim: &Simulation) {
+        let state = match &self.state {
+            Some(state) => state,
+            None => {
+                ui.text_colored(LABEL_COLOR, "No cpu state available");
+                return;
+            }
+        };
+
+        let pc_text = match sim.symbols().lookup(state.pc) {
+            Some(symbol) => format!(
+                "{:08x} <{}+{:#x}>",
+                state.pc,
+                symbol.name,
+                state.pc - symbol.addr
+            ),
+            None => format!("{:08x}", state.pc),
+        };
+        ui.text_colored(LABEL_COLOR, "pc   ");
+        ui.same_line(0.0);
+        if self.is_pc_changed {
+            ui.text_colored(CHANGED_COLOR, pc_text);
+        } else {
+            ui.text(pc_text);
+        }
+
+        ui.text_colored(LABEL_COLOR, "inst ");
+        ui.same_line(0.0);
+        ui.text(format!(
+            "{:08x}  {}",
+            state.inst,
+            disasm::disassemble(state.inst, state.pc)
+        ));
+
+        ui.text_colored(LABEL_COLOR, "state");
+        ui.same_line(0.0);
+        ui.text(format!("{:?}", state.state));
+
+        ui.separator();
+        ui.text(format!("Cycles: {}", sim.clocks()));
+        ui.text(format!("Instructions: {}", state.inst_count));
+
+        ui.separator();
+        for row in 0..REGS_PER_COLUMN {
+            for column in 0..(ABI_NAMES.len() / REGS_PER_COLUMN) {
+                let idx = column * REGS_PER_COLUMN + row;
+                if column != 0 {
+                    ui.same_line(0.0);
+                    ui.text("  ");
+                    ui.same_line(0.0);
+                }
+
+                ui.text_colored(
+                    LABEL_COLOR,
+                    format!("{:>3} {:<4}", format!("x{}", idx), ABI_NAMES[idx]),
+                );
+                ui.same_line(0.0);
+                let value_text = format!("{:08x}", state.regs[idx]);
+                if self.changed[idx] {
+                    ui.text_colored(CHANGED_COLOR, value_text);
+                } else {
+                    ui.text(value_text);
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(format!("{}", state.regs[idx] as i32));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use crate::simulation::Simulation;
+use devsim::device::NUM_BREAKPOINTS;
+use devsim::disasm;
+use devsim::elf::SourceLocation;
+use imgui::{im_str, ImString, StyleColor, Ui};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const BREAKPOINT_COLOR: [f32; 4] = [1.0, 0.3, 0.3, 1.0];
+const SYMBOL_COLOR: [f32; 4] = [0.9, 0.8, 0.4, 1.0];
+const SOURCE_COLOR: [f32; 4] = [0.5, 0.7, 0.9, 1.0];
+const ERROR_COLOR: [f32; 4] = [1.0, 0.3, 0.3, 1.0];
+
+/// Kind of content displayed on a line of the listing
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum LineKind {
+    Symbol,
+    Source,
+    Instruction,
+}
+
+/// A line of the listing
+/// Every line refers to the address of the instruction it belongs to so clicking it can set a breakpoint there.
+struct Line {
+    addr: u32,
+    kind: LineKind,
+    text: String,
+}
+
+/// Reads source files on demand and remembers their contents
+struct SourceCache {
+    /// Directory of the ELF file, used to find sources with relative paths
+    elf_dir: PathBuf,
+    files: HashMap<PathBuf, Option<Vec<String>>>,
+}
+
+impl SourceCache {
+    /// Returns the text of a source line if the file can be found
+    fn line(&mut self, location: SourceLocation) -> Option<&str> {
+        let elf_dir = &self.elf_dir;
+        let lines = self
+            .files
+            .entry(location.path.to_path_buf())
+            .or_insert_with(|| {
+                // Relative paths are tried next to the ELF file first and then in the current directory
+                let text = fs::read_to_string(elf_dir.join(location.path))
+                    .or_else(|_| fs::read_to_string(location.path))
+                    .ok()?;
+                Some(
+                    text.lines()
+                        .map(|line| line.trim_end().to_owned())
+                        .collect(),
+                )
+            })
+            .as_ref()?;
+        lines
+            .get((location.line as usize).checked_sub(1)?)
+            .map(|line| line.as_str())
+    }
+}
+
+/// Builds the listing of every instruction in the simulation's current ELF file
+fn build_listing(sim: &Simulation) -> Vec<Line> {
+    let mut sources = SourceCache {
+        elf_dir: sim
+            .elf_path()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+        files: HashMap::new(),
+    };
+
+    let mut listing = Vec::new();
+    let mut prev_location = None;
+    for section in sim.code_sections() {
+        for (idx, bytes) in section.data.chunks_exact(4).enumerate() {
+            let addr = section.addr + (idx * 4) as u32;
+            let inst = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+            if let Some(symbol) = sim.symbols().lookup(addr) {
+                if symbol.addr == addr {
+                    listing.push(Line {
+                        addr,
+                        kind: LineKind::Symbol,
+                        text: format!("<{}>:", symbol.name),
+                    });
+                }
+            }
+
+            // Source lines are only shown where the line changes so runs of instructions stay grouped
+            let location = sim.lines().lookup(addr);
+            if let Some(location) = location.filter(|location| Some(*location) != prev_location) {
+                let file_name = location.path.file_name().map_or_else(
+                    || location.path.to_string_lossy(),
+                    |name| name.to_string_lossy(),
+                );
+                listing.push(Line {
+                    addr,
+                    kind: LineKind::Source,
+                    text: match sources.line(location) {
+                        Some(text) => format!("{}:{}  {}", file_name, location.line, text.trim()),
+                        None => format!("{}:{}", file_name, location.line),
+                    },
+                });
+            }
+            prev_location = location;
+
+            let mut text = format!(
+                "{:08x}:  {:08x}  {}",
+                addr,
+                inst,
+                disasm::disassemble(inst, addr)
+            );
+            if let Some(target) = disasm::branch_target(inst, addr) {
+                if let Some(symbol) = sim.symbols().lookup(target) {
+                    text.push_str(&format!(" <{}+{:#x}>", symbol.name, target - symbol.addr));
+                }
+            }
+            listing.push(Line {
+                addr,
+                kind: LineKind::Instruction,
+                text,
+            });
+        }
+    }
+
+    listing
+}
+
+/// ImGui panel that displays the disassembled program around the pc and manages breakpoints
+pub struct DisassemblyPanel {
+    pub is_open: bool,
+    is_following_pc: bool,
+    listing: Vec<Line>,
+    /// ELF generation that the listing was built from
+    listing_generation: Option<u64>,
+    /// Pc that the view last scrolled to
+    followed_pc: Option<u32>,
+    /// Line that the view should scroll to during the next draw
+    scroll_target: Option<usize>,
+    error: Option<String>,
+}
+
+impl DisassemblyPanel {
+    pub fn new() -> Self {
+        Self {
+            is_open: false,
+            is_following_pc: true,
+            listing: Vec::new(),
+            listing_generation: None,
+            followed_pc: None,
+            scroll_target: None,
+            error: None,
+        }
+    }
+
+    /// Returns the index of the listing line that holds the instruction at an address
+    fn instruction_line(&self, addr: u32) -> Option<usize> {
+        let idx = self.listing.partition_point(|line| {
+            (line.addr < addr) || (line.kind != LineKind::Instruction && line.addr == addr)
+        });
+        self.listing
+            .get(idx)
+            .filter(|line| (line.addr == addr) && (line.kind == LineKind::Instruction))
+            .map(|_| idx)
+    }
+
+    /// Draws the panel if it's open
+    /// The pc is only displayed while cpu state capture is enabled.
+    pub fn draw(&mut self, ui: &Ui, sim: &mut Simulation) {
+        if !self.is_open {
+            return;
+        }
+
+        if self.listing_generation != Some(sim.elf_generation()) {
+            self.listing = build_listing(sim);
+            self.listing_generation = Some(sim.elf_generation());
+            self.followed_pc = None;
+        }
+
+        let pc = sim.cpu_state().map(|state| state.pc);
+        if self.is_following_pc && (pc != self.followed_pc) {
+            if let Some(line_idx) = pc.and_then(|pc| self.instruction_line(pc)) {
+                self.scroll_target = Some(line_idx);
+            }
+            self.followed_pc = pc;
+        }
+
+        let mut is_open = self.is_open;
+        imgui::Window::new(im_str!("Disassembly"))
+            .size([640.0, 480.0], imgui::Condition::FirstUseEver)
+            .opened(&mut is_open)
+            .build(ui, || self.draw_contents(ui, sim, pc));
+        self.is_open = is_open;
+    }
+
+    fn draw_contents(&mut self, ui: &Ui, sim: &mut Simulation, pc: Option<u32>) {
+        if ui.checkbox(im_str!("Follow PC"), &mut self.is_following_pc) {
+            self.followed_pc = None;
+        }
+        ui.same_line(0.0);
+        ui.text(format!(
+            "Breakpoints: {}/{}",
+            sim.breakpoints().len(),
+            NUM_BREAKPOINTS
+        ));
+        if !sim.breakpoints().is_empty() {
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("Clear")) {
+                self.error = sim.clear_breakpoints().err().map(|err| err.to_string());
+            }
+        }
+
+        if let Some(err) = &self.error {
+            ui.text_colored(ERROR_COLOR, err);
+        }
+
+        if self.listing.is_empty() {
+            ui.text("No program loaded");
+            return;
+        }
+
+        let mut clicked_addr = None;
+        imgui::ChildWindow::new("listing")
+            .horizontal_scrollbar(true)
+            .build(ui, || clicked_addr = self.draw_listing(ui, sim, pc));
+
+        if let Some(addr) = clicked_addr {
+            self.error = sim.toggle_breakpoint(addr).err().map(|err| err.to_string());
+        }
+    }
+
+    /// Draws the visible lines of the listing and returns the address of a clicked line
+    fn draw_listing(&mut self, ui: &Ui, sim: &Simulation, pc: Option<u32>) -> Option<u32> {
+        let row_height = ui.text_line_height_with_spacing();
+
+        // The pc is centered in the view so the code around it is visible in both directions
+        if let Some(line_idx) = self.scroll_target.take() {
+            let scroll_y = line_idx as f32 * row_height - ui.window_size()[1] / 2.0;
+            ui.set_scroll_y(scroll_y.max(0.0));
+        }
+
+        let num_lines = self.listing.len();
+        let first_line = ((ui.scroll_y() / row_height) as usize).min(num_lines - 1);
+        let num_visible_lines = (ui.window_size()[1] / row_height) as usize + 2;
+        let end_line = (first_line + num_visible_lines).min(num_lines);
+
+        let start_pos = ui.cursor_pos();
+        ui.set_cursor_pos([start_pos[0], start_pos[1] + first_line as f32 * row_height]);
+
+        let mut clicked_addr = None;
+        for line in &self.listing[first_line..end_line] {
+            let is_instruction = line.kind == LineKind::Instruction;
+            let is_pc = is_instruction && (pc == Some(line.addr));
+            let is_breakpoint = is_instruction && sim.breakpoints().contains(&line.addr);
+
+            let marker = match (is_breakpoint, is_pc) {
+                (true, true) => "*>",
+                (true, false) => "* ",
+                (false, true) => " >",
+                (false, false) => "  ",
+            };
+            let label = ImString::new(format!("{} {}", marker, line.text));
+
+            let color = if is_breakpoint {
+                Some(BREAKPOINT_COLOR)
+            } else {
+                match line.kind {
+                    LineKind::Symbol => Some(SYMBOL_COLOR),
+                    LineKind::Source => Some(SOURCE_COLOR),
+                    LineKind::Instruction => None,
+                }
+            };
+            let color_token = color.map(|color| ui.push_style_color(StyleColor::Text, color));
+            if imgui::Selectable::new(&label).selected(is_pc).build(ui) {
+                clicked_addr = Some(line.addr);
+            }
+            if let Some(color_token) = color_token {
+                color_token.pop(ui);
+            }
+        }
+
+        // Reserve space for every line so the scroll bar covers the whole listing
+        ui.set_cursor_pos([start_pos[0], start_pos[1] + num_lines as f32 * row_height]);
+        ui.dummy([0.0, 0.0]);
+
+        clicked_addr
+    }
+}
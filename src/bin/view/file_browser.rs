@@ -0,0 +1,167 @@
+use imgui::{im_str, ImString, MouseButton, Ui};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Largest path that can be typed into the path field
+const MAX_PATH_LEN: usize = 4096;
+
+/// An entry in the current directory
+struct Entry {
+    name: String,
+    is_dir: bool,
+}
+
+/// ImGui window for selecting a file to open
+pub struct FileBrowser {
+    pub is_open: bool,
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    /// Index of the selected entry
+    selected: Option<usize>,
+    /// Contents of the editable path field
+    path_input: ImString,
+    error: Option<String>,
+}
+
+impl FileBrowser {
+    pub fn new() -> Self {
+        Self {
+            is_open: false,
+            dir: PathBuf::new(),
+            entries: Vec::new(),
+            selected: None,
+            path_input: ImString::with_capacity(MAX_PATH_LEN),
+            error: None,
+        }
+    }
+
+    /// Opens the browser in the provided directory
+    pub fn open(&mut self, dir: &Path) {
+        self.is_open = true;
+        self.set_dir(dir.to_path_buf());
+    }
+
+    /// Changes the current directory and lists its contents
+    fn set_dir(&mut self, dir: PathBuf) {
+        self.entries.clear();
+        self.selected = None;
+        self.error = None;
+
+        match fs::read_dir(&dir) {
+            Ok(read_dir) => {
+                // Entries that can't be inspected are skipped rather than hiding the whole directory
+                self.entries = read_dir
+                    .filter_map(|entry| entry.ok())
+                    .filter_map(|entry| {
+                        let is_dir = entry.file_type().ok()?.is_dir();
+                        let name = entry.file_name().into_string().ok()?;
+                        Some(Entry { name, is_dir })
+                    })
+                    .filter(|entry| !entry.name.starts_with('.'))
+                    .collect();
+
+                // Directories are listed before files
+                self.entries
+                    .sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+            }
+            Err(err) => self.error = Some(format!("Unable to read {}: {}", dir.display(), err)),
+        }
+
+        self.path_input.clear();
+        self.path_input.push_str(&dir.to_string_lossy());
+        self.dir = dir;
+    }
+
+    /// Handles a request to open a path, which either enters a directory or returns a file
+    fn activate(&mut self, path: PathBuf) -> Option<PathBuf> {
+        if path.is_dir() {
+            self.set_dir(path);
+            None
+        } else if path.is_file() {
+            self.is_open = false;
+            Some(path)
+        } else {
+            self.error = Some(format!("{} doesn't exist", path.display()));
+            None
+        }
+    }
+
+    /// Draws the browser if it's open and returns the path of a file if one was chosen
+    pub fn draw(&mut self, ui: &Ui) -> Option<PathBuf> {
+        if !self.is_open {
+            return None;
+        }
+
+        let mut chosen_path = None;
+        let mut is_open = self.is_open;
+        imgui::Window::new(im_str!("Open ELF"))
+            .size([600.0, 400.0], imgui::Condition::FirstUseEver)
+            .opened(&mut is_open)
+            .build(ui, || chosen_path = self.draw_contents(ui));
+        self.is_open &= is_open;
+
+        chosen_path
+    }
+
+    fn draw_contents(&mut self, ui: &Ui) -> Option<PathBuf> {
+        let mut activated_path = None;
+
+        if ui.button(im_str!("Up"), [0.0, 0.0]) {
+            if let Some(parent) = self.dir.parent() {
+                activated_path = Some(parent.to_path_buf());
+            }
+        }
+        ui.same_line(0.0);
+        ui.set_next_item_width(-1.0);
+        if ui
+            .input_text(im_str!("##path"), &mut self.path_input)
+            .enter_returns_true(true)
+            .build()
+        {
+            activated_path = Some(PathBuf::from(self.path_input.to_str()));
+        }
+
+        if let Some(err) = &self.error {
+            ui.text_colored([1.0, 0.3, 0.3, 1.0], err);
+        }
+
+        // Leave room for the buttons below the list
+        let footer_height = ui.frame_height_with_spacing();
+        imgui::ChildWindow::new("entries")
+            .size([0.0, -footer_height])
+            .border(true)
+            .build(ui, || {
+                for (idx, entry) in self.entries.iter().enumerate() {
+                    let label = if entry.is_dir {
+                        ImString::new(format!("{}/", entry.name))
+                    } else {
+                        ImString::new(&entry.name)
+                    };
+                    if imgui::Selectable::new(&label)
+                        .selected(self.selected == Some(idx))
+                        .allow_double_click(true)
+                        .build(ui)
+                    {
+                        self.selected = Some(idx);
+                        if ui.is_mouse_double_clicked(MouseButton::Left) {
+                            activated_path = Some(self.dir.join(&entry.name));
+                        }
+                    }
+                }
+            });
+
+        let selected_path = self
+            .selected
+            .map(|idx| self.dir.join(&self.entries[idx].name));
+        if ui.button(im_str!("Open"), [0.0, 0.0]) {
+            activated_path =
+                selected_path.or_else(|| Some(PathBuf::from(self.path_input.to_str())));
+        }
+        ui.same_line(0.0);
+        if ui.button(im_str!("Cancel"), [0.0, 0.0]) {
+            self.is_open = false;
+        }
+
+        activated_path.and_then(|path| self.activate(path))
+    }
+}
@@ -0,0 +1,220 @@
+use crate::simulation::Simulation;
+use imgui::{im_str, MouseButton, TextureId, Ui};
+
+const MIN_ZOOM: u32 = 1;
+const MAX_ZOOM: u32 = 64;
+const DEFAULT_ZOOM: u32 = 4;
+
+/// Smallest zoom level where the pixel grid is drawn since lower levels would mostly show grid lines
+const MIN_GRID_ZOOM: u32 = 8;
+
+const GRID_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 0.5];
+const HOVER_COLOR: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+const BACKGROUND_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 1.0];
+
+/// ImGui panel that displays the device framebuffer and the pixel under the mouse
+pub struct FramebufferPanel {
+    pub is_open: bool,
+    /// Number of screen pixels used for each framebuffer pixel
+    zoom: u32,
+    /// Offset of the image center from the center of the panel in screen pixels
+    pan: [f32; 2],
+    is_nearest_filtering: bool,
+    is_grid_enabled: bool,
+}
+
+impl FramebufferPanel {
+    pub fn new() -> Self {
+        Self {
+            is_open: false,
+            zoom: DEFAULT_ZOOM,
+            pan: [0.0, 0.0],
+            is_nearest_filtering: true,
+            is_grid_enabled: true,
+        }
+    }
+
+    /// Changes the zoom level while keeping the image position under the anchor point in place
+    fn set_zoom(&mut self, zoom: u32, anchor: [f32; 2]) {
+        let zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        let scale = zoom as f32 / self.zoom as f32;
+        for (pan, anchor) in self.pan.iter_mut().zip(anchor.iter()) {
+            *pan = anchor - (anchor - *pan) * scale;
+        }
+        self.zoom = zoom;
+    }
+
+    /// Draws the panel if it's open
+    /// The texture ids refer to the framebuffer image with linear and nearest filtering respectively.
+    pub fn draw(
+        &mut self,
+        ui: &Ui,
+        sim: &Simulation,
+        linear_texture_id: TextureId,
+        nearest_texture_id: TextureId,
+    ) {
+        if self.is_open {
+            let texture_id = if self.is_nearest_filtering {
+                nearest_texture_id
+            } else {
+                linear_texture_id
+            };
+
+            let mut is_open = self.is_open;
+            imgui::Window::new(im_str!("Framebuffer"))
+                .size([320.0, 360.0], imgui::Condition::FirstUseEver)
+                .opened(&mut is_open)
+                .build(ui, || self.draw_contents(ui, sim, texture_id));
+            self.is_open = is_open;
+        }
+    }
+
+    fn draw_contents(&mut self, ui: &Ui, sim: &Simulation, texture_id: TextureId) {
+        if ui.small_button(im_str!("-")) {
+            self.set_zoom(self.zoom / 2, [0.0, 0.0]);
+        }
+        ui.same_line(0.0);
+        ui.text(format!("{}x", self.zoom));
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("+")) {
+            self.set_zoom(self.zoom * 2, [0.0, 0.0]);
+        }
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Reset")) {
+            self.zoom = DEFAULT_ZOOM;
+            self.pan = [0.0, 0.0];
+        }
+        ui.same_line(0.0);
+        ui.checkbox(im_str!("Nearest"), &mut self.is_nearest_filtering);
+        ui.same_line(0.0);
+        ui.checkbox(im_str!("Grid"), &mut self.is_grid_enabled);
+
+        imgui::ChildWindow::new("image")
+            .scrollable(false)
+            .scroll_bar(false)
+            .build(ui, || self.draw_image(ui, sim, texture_id));
+    }
+
+    /// Draws the zoomed framebuffer image and handles the mouse input that pans and zooms it
+    fn draw_image(&mut self, ui: &Ui, sim: &Simulation, texture_id: TextureId) {
+        let canvas_min = ui.cursor_screen_pos();
+        let canvas_size = ui.content_region_avail();
+        if (canvas_size[0] <= 0.0) || (canvas_size[1] <= 0.0) {
+            return;
+        }
+        let canvas_max = [
+            canvas_min[0] + canvas_size[0],
+            canvas_min[1] + canvas_size[1],
+        ];
+        let canvas_center = [
+            canvas_min[0] + canvas_size[0] / 2.0,
+            canvas_min[1] + canvas_size[1] / 2.0,
+        ];
+
+        // The button covers the whole canvas so dragging it doesn't move the window
+        ui.invisible_button(im_str!("canvas"), canvas_size);
+        let is_hovered = ui.is_item_hovered();
+        let mouse_pos = ui.io().mouse_pos;
+
+        if ui.is_item_active() && ui.is_mouse_dragging(MouseButton::Left) {
+            let mouse_delta = ui.io().mouse_delta;
+            self.pan[0] += mouse_delta[0];
+            self.pan[1] += mouse_delta[1];
+        }
+
+        let mouse_wheel = ui.io().mouse_wheel;
+        if is_hovered && (mouse_wheel != 0.0) {
+            let zoom = if mouse_wheel > 0.0 {
+                self.zoom * 2
+            } else {
+                self.zoom / 2
+            };
+            let anchor = [
+                mouse_pos[0] - canvas_center[0],
+                mouse_pos[1] - canvas_center[1],
+            ];
+            self.set_zoom(zoom, anchor);
+        }
+
+        let fb_width = sim.framebuffer_width();
+        let fb_height = sim.framebuffer_height();
+        let zoom = self.zoom as f32;
+        let image_size = [fb_width as f32 * zoom, fb_height as f32 * zoom];
+
+        // The image position is rounded so pixel edges stay aligned with screen pixels
+        let image_min = [
+            (canvas_center[0] + self.pan[0] - image_size[0] / 2.0).round(),
+            (canvas_center[1] + self.pan[1] - image_size[1] / 2.0).round(),
+        ];
+        let image_max = [image_min[0] + image_size[0], image_min[1] + image_size[1]];
+
+        let draw_list = ui.get_window_draw_list();
+        draw_list
+            .add_rect(canvas_min, canvas_max, BACKGROUND_COLOR)
+            .filled(true)
+            .build();
+
+        ui.set_cursor_screen_pos(image_min);
+        imgui::Image::new(texture_id, image_size).build(ui);
+
+        draw_list.with_clip_rect_intersect(canvas_min, canvas_max, || {
+            if self.is_grid_enabled && (self.zoom >= MIN_GRID_ZOOM) {
+                for x in 1..fb_width {
+                    let line_x = image_min[0] + x as f32 * zoom;
+                    draw_list
+                        .add_line([line_x, image_min[1]], [line_x, image_max[1]], GRID_COLOR)
+                        .build();
+                }
+                for y in 1..fb_height {
+                    let line_y = image_min[1] + y as f32 * zoom;
+                    draw_list
+                        .add_line([image_min[0], line_y], [image_max[0], line_y], GRID_COLOR)
+                        .build();
+                }
+            }
+
+            if !is_hovered {
+                return;
+            }
+
+            let pixel_x = ((mouse_pos[0] - image_min[0]) / zoom).floor();
+            let pixel_y = ((mouse_pos[1] - image_min[1]) / zoom).floor();
+            if (pixel_x < 0.0)
+                || (pixel_y < 0.0)
+                || (pixel_x >= fb_width as f32)
+                || (pixel_y >= fb_height as f32)
+            {
+                return;
+            }
+            let (x, y) = (pixel_x as u32, pixel_y as u32);
+
+            let pixel_min = [image_min[0] + pixel_x * zoom, image_min[1] + pixel_y * zoom];
+            draw_list
+                .add_rect(
+                    pixel_min,
+                    [pixel_min[0] + zoom, pixel_min[1] + zoom],
+                    HOVER_COLOR,
+                )
+                .build();
+
+            let offset = ((y * fb_width + x) * 4) as usize;
+            if let Some(pixel) = sim.framebuffer_data().get(offset..(offset + 4)) {
+                ui.tooltip(|| {
+                    ui.text(format!("x: {}  y: {}", x, y));
+                    ui.text(format!(
+                        "rgba: {} {} {} {}  #{:08x}",
+                        pixel[0],
+                        pixel[1],
+                        pixel[2],
+                        pixel[3],
+                        u32::from_be_bytes([pixel[0], pixel[1], pixel[2], pixel[3]])
+                    ));
+                    ui.text(format!(
+                        "addr: {:#010x}",
+                        sim.framebuffer_addr() + offset as u32
+                    ));
+                });
+            }
+        });
+    }
+}
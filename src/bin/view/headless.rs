@@ -0,0 +1,281 @@
+use super::{capture, create_imgui_context, App, Renderer, SimOptions};
+use ash::{version::DeviceV1_0, vk};
+use devsim::vkutil::*;
+use std::slice;
+use std::sync::Weak;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Number of images that are rendered into when there's no window
+const NUM_OFFSCREEN_IMAGES: usize = 2;
+
+/// Format of the images that are rendered into when there's no window
+const OFFSCREEN_IMAGE_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+
+/// Images that are rendered into when there's no window to present them to
+/// Each image is copied into a cpu visible buffer at the end of its frame so the result can be saved.
+pub struct OffscreenTarget {
+    images: Vec<VkImage>,
+    readback_buffers: Vec<VkBuffer>,
+    extent: vk::Extent2D,
+}
+
+impl OffscreenTarget {
+    pub fn new(allocator: Weak<vk_mem::Allocator>, width: u32, height: u32) -> Result<Self> {
+        let images = (0..NUM_OFFSCREEN_IMAGES)
+            .map(|_| {
+                VkImage::new(
+                    allocator.clone(),
+                    &ash::vk::ImageCreateInfo::builder()
+                        .image_type(vk::ImageType::TYPE_2D)
+                        .extent(vk::Extent3D {
+                            width,
+                            height,
+                            depth: 1,
+                        })
+                        .mip_levels(1)
+                        .array_layers(1)
+                        .format(OFFSCREEN_IMAGE_FORMAT)
+                        .tiling(vk::ImageTiling::OPTIMAL)
+                        .initial_layout(vk::ImageLayout::UNDEFINED)
+                        .usage(
+                            vk::ImageUsageFlags::COLOR_ATTACHMENT
+                                | vk::ImageUsageFlags::TRANSFER_SRC,
+                        )
+                        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                        .samples(vk::SampleCountFlags::TYPE_1),
+                    &vk_mem::AllocationCreateInfo {
+                        usage: vk_mem::MemoryUsage::GpuOnly,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let readback_buffers = (0..NUM_OFFSCREEN_IMAGES)
+            .map(|_| {
+                VkBuffer::new(
+                    allocator.clone(),
+                    &ash::vk::BufferCreateInfo::builder()
+                        .size((width * height * 4) as u64)
+                        .usage(vk::BufferUsageFlags::TRANSFER_DST),
+                    &vk_mem::AllocationCreateInfo {
+                        usage: vk_mem::MemoryUsage::GpuToCpu,
+                        flags: vk_mem::AllocationCreateFlags::MAPPED,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            images,
+            readback_buffers,
+            extent: vk::Extent2D { width, height },
+        })
+    }
+
+    /// Records the commands that copy a rendered image into its readback buffer
+    pub fn record_readback(&self, device: &ash::Device, cmd_buffer: vk::CommandBuffer, idx: usize) {
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1)
+            .build();
+
+        unsafe {
+            // The render pass leaves the image in the transfer layout, but its writes still need to be made visible
+            device.cmd_pipeline_barrier(
+                cmd_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[vk::ImageMemoryBarrier::builder()
+                    .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                    .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+                    .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                    .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .image(self.images[idx].raw())
+                    .subresource_range(subresource_range)
+                    .build()],
+            );
+
+            device.cmd_copy_image_to_buffer(
+                cmd_buffer,
+                self.images[idx].raw(),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                self.readback_buffers[idx].raw(),
+                &[vk::BufferImageCopy::builder()
+                    .image_subresource(vk::ImageSubresourceLayers {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    })
+                    .image_extent(vk::Extent3D {
+                        width: self.extent.width,
+                        height: self.extent.height,
+                        depth: 1,
+                    })
+                    .build()],
+            );
+
+            device.cmd_pipeline_barrier(
+                cmd_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::HOST,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[vk::BufferMemoryBarrier::builder()
+                    .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(vk::AccessFlags::HOST_READ)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .buffer(self.readback_buffers[idx].raw())
+                    .offset(0)
+                    .size(vk::WHOLE_SIZE)
+                    .build()],
+                &[],
+            );
+        }
+    }
+}
+
+/// Images that the renderer draws into
+pub enum RenderTarget {
+    /// Images that are presented to a window
+    Swapchain {
+        swapchain: VkSwapchain,
+        surface: VkSurface,
+    },
+    Offscreen(OffscreenTarget),
+}
+
+impl RenderTarget {
+    pub fn images(&self) -> Vec<vk::Image> {
+        match self {
+            RenderTarget::Swapchain { swapchain, .. } => swapchain.images.clone(),
+            RenderTarget::Offscreen(target) => {
+                target.images.iter().map(|image| image.raw()).collect()
+            }
+        }
+    }
+
+    pub fn format(&self) -> vk::Format {
+        match self {
+            RenderTarget::Swapchain { swapchain, .. } => swapchain.surface_format.format,
+            RenderTarget::Offscreen(_) => OFFSCREEN_IMAGE_FORMAT,
+        }
+    }
+
+    pub fn extent(&self) -> vk::Extent2D {
+        match self {
+            RenderTarget::Swapchain { swapchain, .. } => swapchain.surface_resolution,
+            RenderTarget::Offscreen(target) => target.extent,
+        }
+    }
+
+    /// Returns the layout that images are left in once rendering is finished
+    pub fn final_layout(&self) -> vk::ImageLayout {
+        match self {
+            RenderTarget::Swapchain { .. } => vk::ImageLayout::PRESENT_SRC_KHR,
+            RenderTarget::Offscreen(_) => vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        }
+    }
+}
+
+impl Renderer {
+    /// Creates a renderer that draws into offscreen images of the provided size instead of a window
+    fn new_headless(
+        size: [u32; 2],
+        fb_width: u32,
+        fb_height: u32,
+        enable_validation: bool,
+        context: &mut imgui::Context,
+    ) -> Result<Self> {
+        Self::with_target(None, size, fb_width, fb_height, enable_validation, context)
+    }
+
+    /// Waits for the most recent frame to finish and returns its pixels if the renderer draws into offscreen images
+    /// The pixels are returned along with the size of the image.
+    fn read_offscreen_image(&self) -> Option<(vk::Extent2D, Vec<u8>)> {
+        match &self.target {
+            RenderTarget::Swapchain { .. } => None,
+            RenderTarget::Offscreen(target) => {
+                let frame_state = self.get_cur_frame_state();
+                let readback_buffer = &target.readback_buffers[self.cur_swapchain_idx];
+                let size = (target.extent.width * target.extent.height * 4) as usize;
+                unsafe {
+                    self.get_device()
+                        .wait_for_fences(&[frame_state.fence.raw()], true, u64::MAX)
+                        .unwrap();
+                    let data =
+                        slice::from_raw_parts(readback_buffer.info().get_mapped_data(), size);
+                    Some((target.extent, data.to_vec()))
+                }
+            }
+        }
+    }
+}
+
+/// Time that passes between frames in headless runs
+/// A fixed step is used so the ui looks the same no matter how long each frame takes to render.
+const HEADLESS_FRAME_TIME: f32 = 1.0 / 60.0;
+
+/// Renders the view into offscreen images without opening a window and saves the last frame as a png file
+pub fn run_headless(opts: &SimOptions) -> Result<()> {
+    if opts.frames == 0 {
+        return Err("At least one frame needs to be rendered".into());
+    }
+
+    let mut app = App::new(opts, true);
+    let size = app.settings.window_size;
+
+    let mut context = create_imgui_context();
+    // Headless runs always start from the default layout
+    context.set_ini_filename(None);
+    context.io_mut().display_size = [size[0] as f32, size[1] as f32];
+
+    let fb_width = app.sim.framebuffer_width();
+    let fb_height = app.sim.framebuffer_height();
+    let mut renderer =
+        Renderer::new_headless(size, fb_width, fb_height, !opts.no_validation, &mut context)?;
+
+    for _ in 0..opts.frames {
+        let cmd_buffer = renderer.begin_frame();
+
+        let fb_data = renderer.fb_upload_data(app.sim.framebuffer_size());
+        app.update(fb_data);
+
+        context.io_mut().delta_time = HEADLESS_FRAME_TIME;
+        let ui = context.frame();
+        app.draw(&ui, fb_data);
+        let draw_data = ui.render();
+
+        renderer.render(
+            cmd_buffer,
+            fb_width,
+            fb_height,
+            draw_data,
+            !app.framebuffer_panel.is_open,
+        );
+
+        renderer.end_frame(cmd_buffer);
+    }
+
+    let (extent, image_data) = renderer
+        .read_offscreen_image()
+        .ok_or("Headless renderer has no offscreen image")?;
+    renderer.wait_for_idle();
+
+    capture::save_png(&opts.output, extent.width, extent.height, &image_data)?;
+    println!("Saved {}", opts.output.display());
+
+    Ok(())
+}
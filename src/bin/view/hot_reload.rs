@@ -0,0 +1,307 @@
+use devsim::device::Backend;
+use devsim::hw_params;
+use imgui::{im_str, Ui};
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// How often the hardware sources are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Returns the directory that contains the hardware sources
+fn hw_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("hw")
+}
+
+/// Returns the directory that hardware builds are written to
+/// Builds are kept next to the executable so they end up in the same target directory as the rest of the build,
+/// wherever that's been configured to be.
+fn output_dir() -> Result<PathBuf> {
+    let exe_path = std::env::current_exe()?;
+    let exe_dir = exe_path
+        .parent()
+        .ok_or("Unable to find the directory that contains the executable")?;
+    Ok(exe_dir.join("hot_reload"))
+}
+
+/// Removes the libraries copied from the builds of earlier runs
+/// Libraries that are still loaded by another instance may not be removable on some platforms, so errors are ignored.
+fn remove_stale_backends(output_dir: &Path) -> Result<()> {
+    let prefix = format!("{}protobridge-", DLL_PREFIX);
+    for entry in fs::read_dir(output_dir)?.filter_map(|entry| entry.ok()) {
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        if file_name.starts_with(&prefix) && file_name.ends_with(DLL_SUFFIX) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Returns the time of the most recent modification to any of the hardware sources
+fn query_hw_modified_time() -> Option<SystemTime> {
+    fs::read_dir(hw_dir())
+        .ok()?
+        .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+        .max()
+}
+
+/// Runs a build command and appends its output to the log
+fn run_command(command: &mut Command, log: &mut String) -> Result<()> {
+    log.push_str(&format!("> {:?}\n", command));
+
+    let output = command.output()?;
+    log.push_str(&String::from_utf8_lossy(&output.stdout));
+    log.push_str(&String::from_utf8_lossy(&output.stderr));
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!("Command failed with {}", output.status).into())
+    }
+}
+
+/// Builds the hardware into a protobridge shared library and returns the path of a uniquely named copy
+/// Each build is copied to a new path since shared libraries can't be reliably reloaded from the same path.
+fn build_backend(output_dir: &Path, build_idx: u32, log: &mut String) -> Result<PathBuf> {
+    let root_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let build_dir = output_dir.join("build");
+
+    // The build directory is reused so verilator and cmake only rebuild what changed
+    run_command(
+        Command::new("cmake")
+            .arg("-S")
+            .arg(root_dir.join("protobridge"))
+            .arg("-B")
+            .arg(&build_dir)
+            .arg(format!("-DPROTOBRIDGE_HW_PATH={}", hw_dir().display()))
+            .arg(format!(
+                "-DPROTOBRIDGE_VERILATOR_ARGS={}",
+                hw_params::VERILATOR_ARGS
+            ))
+            .arg("-DPROTOBRIDGE_SHARED=ON")
+            .arg("-DPROTOBRIDGE_TRACE=ON")
+            .arg(format!(
+                "-DPROTOBRIDGE_TRACE_FORMAT={}",
+                if cfg!(feature = "trace-fst") {
+                    "FST"
+                } else {
+                    "VCD"
+                }
+            ))
+            .arg("-DCMAKE_BUILD_TYPE=Release"),
+        log,
+    )?;
+    run_command(
+        Command::new("cmake")
+            .arg("--build")
+            .arg(&build_dir)
+            .arg("--config")
+            .arg("Release"),
+        log,
+    )?;
+
+    let library_name = format!("{}protobridge{}", DLL_PREFIX, DLL_SUFFIX);
+    let library_path = find_file(&build_dir, &library_name)
+        .ok_or_else(|| format!("Unable to find {} in the build output", library_name))?;
+
+    let backend_path = output_dir.join(format!(
+        "{}protobridge-{}-{}{}",
+        DLL_PREFIX,
+        std::process::id(),
+        build_idx,
+        DLL_SUFFIX
+    ));
+    fs::copy(library_path, &backend_path)?;
+
+    Ok(backend_path)
+}
+
+/// Recursively searches a directory for a file with the provided name
+fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    for entry in fs::read_dir(dir).ok()?.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(path) = find_file(&path, name) {
+                return Some(path);
+            }
+        } else if path.file_name() == Some(name.as_ref()) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+enum BuildEvent {
+    Started,
+    Succeeded { backend: Arc<Backend>, log: String },
+    Failed { log: String },
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum BuildStatus {
+    Idle,
+    Building,
+    Succeeded,
+    Failed,
+}
+
+/// Watches the hardware sources and rebuilds the simulation backend in the background when they change
+fn watch(output_dir: PathBuf, rebuild_requests: Receiver<()>, events: Sender<BuildEvent>) {
+    let mut prev_modified_time = query_hw_modified_time();
+    let mut build_idx = 0;
+
+    loop {
+        let is_rebuild_requested = match rebuild_requests.recv_timeout(POLL_INTERVAL) {
+            Ok(()) => true,
+            Err(mpsc::RecvTimeoutError::Timeout) => false,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        };
+
+        let modified_time = query_hw_modified_time();
+        if !is_rebuild_requested && (modified_time == prev_modified_time) {
+            continue;
+        }
+        prev_modified_time = modified_time;
+
+        if events.send(BuildEvent::Started).is_err() {
+            break;
+        }
+
+        let mut log = String::new();
+        let result = build_backend(&output_dir, build_idx, &mut log).and_then(|path| {
+            let backend = Backend::load(&path);
+            // Loaded libraries stay mapped after they're removed on most platforms, and the others are removed the
+            // next time view starts
+            let _ = fs::remove_file(&path);
+            backend
+        });
+        build_idx += 1;
+
+        let event = match result {
+            Ok(backend) => BuildEvent::Succeeded { backend, log },
+            Err(err) => {
+                log.push_str(&format!("\nHardware build failed: {}\n", err));
+                BuildEvent::Failed { log }
+            }
+        };
+        if events.send(event).is_err() {
+            break;
+        }
+    }
+}
+
+/// Rebuilds the hardware when its sources change and displays the results of each build in a panel
+pub struct HotReloader {
+    rebuild_requests: Sender<()>,
+    events: Receiver<BuildEvent>,
+    status: BuildStatus,
+    build_start_time: Instant,
+    build_duration: Duration,
+    log: String,
+    pub is_open: bool,
+}
+
+impl HotReloader {
+    pub fn new() -> Result<Self> {
+        let output_dir = output_dir()?;
+        fs::create_dir_all(&output_dir)?;
+        remove_stale_backends(&output_dir)?;
+
+        let (request_sender, request_receiver) = mpsc::channel();
+        let (event_sender, event_receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("hot reload".to_string())
+            .spawn(move || watch(output_dir, request_receiver, event_sender))?;
+
+        Ok(Self {
+            rebuild_requests: request_sender,
+            events: event_receiver,
+            status: BuildStatus::Idle,
+            build_start_time: Instant::now(),
+            build_duration: Duration::default(),
+            log: String::new(),
+            is_open: false,
+        })
+    }
+
+    /// Receives results from the background build and returns a new backend if one was built
+    pub fn update(&mut self) -> Option<Arc<Backend>> {
+        let mut new_backend = None;
+        loop {
+            match self.events.try_recv() {
+                Ok(BuildEvent::Started) => {
+                    self.status = BuildStatus::Building;
+                    self.build_start_time = Instant::now();
+                }
+                Ok(BuildEvent::Succeeded { backend, log }) => {
+                    self.status = BuildStatus::Succeeded;
+                    self.build_duration = self.build_start_time.elapsed();
+                    self.log = log;
+                    new_backend = Some(backend);
+                }
+                Ok(BuildEvent::Failed { log }) => {
+                    self.status = BuildStatus::Failed;
+                    self.build_duration = self.build_start_time.elapsed();
+                    self.log = log;
+
+                    // Errors are shown immediately so they aren't missed
+                    self.is_open = true;
+                }
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => break,
+            }
+        }
+        new_backend
+    }
+
+    /// Draws the build panel if it's open
+    pub fn draw(&mut self, ui: &Ui) {
+        if !self.is_open {
+            return;
+        }
+
+        let mut is_open = self.is_open;
+        imgui::Window::new(im_str!("Hardware Build"))
+            .size([700.0, 400.0], imgui::Condition::FirstUseEver)
+            .opened(&mut is_open)
+            .build(ui, || {
+                match self.status {
+                    BuildStatus::Idle => ui.text("Watching hw/ for changes"),
+                    BuildStatus::Building => ui.text(format!(
+                        "Building... ({:.1}s)",
+                        self.build_start_time.elapsed().as_secs_f32()
+                    )),
+                    BuildStatus::Succeeded => ui.text(format!(
+                        "Build succeeded in {:.1}s, the hardware was reloaded",
+                        self.build_duration.as_secs_f32()
+                    )),
+                    BuildStatus::Failed => ui.text_colored(
+                        [1.0, 0.3, 0.3, 1.0],
+                        format!(
+                            "Build failed after {:.1}s",
+                            self.build_duration.as_secs_f32()
+                        ),
+                    ),
+                }
+
+                ui.same_line(0.0);
+                if ui.button(im_str!("Rebuild"), [0.0, 0.0]) {
+                    // The watcher only exits when this object is dropped so the request can't fail
+                    let _ = self.rebuild_requests.send(());
+                }
+
+                ui.separator();
+                imgui::ChildWindow::new("log")
+                    .horizontal_scrollbar(true)
+                    .build(ui, || ui.text(&self.log));
+            });
+        self.is_open = is_open;
+    }
+}
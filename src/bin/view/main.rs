@@ -0,0 +1,2374 @@
+use ash::{
+    version::{DeviceV1_0, InstanceV1_0},
+    vk,
+};
+use imgui::{DrawCmd, DrawCmdParams};
+use imgui_winit_support::{HiDpiMode, WinitPlatform};
+use std::sync::{Arc, Weak};
+use std::time::Instant;
+use winit::{
+    event::{
+        DeviceEvent, ElementState, Event, KeyboardInput, StartCause, VirtualKeyCode, WindowEvent,
+    },
+    event_loop::{ControlFlow, EventLoop},
+    window::WindowBuilder,
+};
+
+use clap::Clap;
+use devsim::vkutil::*;
+use imgui::internal::RawWrapper;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::slice;
+
+mod capture;
+mod cpu;
+mod disassembly;
+mod file_browser;
+mod framebuffer;
+mod headless;
+mod hot_reload;
+mod memory;
+mod settings;
+mod simulation;
+mod toast;
+mod waveform;
+use capture::FrameRecorder;
+use cpu::CpuPanel;
+use disassembly::DisassemblyPanel;
+use file_browser::FileBrowser;
+use framebuffer::FramebufferPanel;
+use headless::{OffscreenTarget, RenderTarget};
+use hot_reload::HotReloader;
+use memory::MemoryPanel;
+use settings::Settings;
+use simulation::{SimSpeed, Simulation};
+use toast::Toasts;
+use waveform::WaveformPanel;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Utility structure that simplifies the process of writing data that's constant over a single frame into GPU memory
+struct ConstantDataWriter {
+    buffer: *mut u8,
+    buffer_size: usize,
+    bytes_written: usize,
+}
+
+impl ConstantDataWriter {
+    pub fn new(buffer: *mut u8, buffer_size: usize) -> Self {
+        ConstantDataWriter {
+            buffer,
+            buffer_size,
+            bytes_written: 0,
+        }
+    }
+
+    pub fn dword_offset(&self) -> u32 {
+        (self.bytes_written / 4) as u32
+    }
+}
+
+impl io::Write for ConstantDataWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bytes_remaining = self.buffer_size - self.bytes_written;
+        let bytes_written = if buf.len() <= bytes_remaining {
+            buf.len()
+        } else {
+            bytes_remaining
+        };
+
+        let buffer = unsafe {
+            slice::from_raw_parts_mut(self.buffer.add(self.bytes_written), bytes_remaining)
+        };
+        buffer[..bytes_written].clone_from_slice(&buf[..bytes_written]);
+
+        self.bytes_written += bytes_written;
+
+        Ok(bytes_written as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Selects a physical device from the provided list
+fn select_physical_device(physical_devices: &[vk::PhysicalDevice]) -> vk::PhysicalDevice {
+    // TODO: Support proper physical device selection
+    //       For now, we just use the first device
+    physical_devices[0]
+}
+
+/// Size of the scratch memory buffer in bytes that is available to each frame
+const FRAME_MEMORY_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Number of individual texture slots available to shaders during a frame
+const NUM_TEXTURE_SLOTS: u64 = 64;
+
+/// Texture slot index associated with the imgui font
+const IMGUI_FONT_TEXTURE_SLOT_INDEX: u64 = NUM_TEXTURE_SLOTS - 1;
+
+/// Texture slot index associated with the device framebuffer
+const FRAMEBUFFER_TEXTURE_SLOT_INDEX: u64 = 0;
+
+/// Bit inside an ImGui texture id that selects nearest filtering instead of linear filtering
+const NEAREST_SAMPLER_TEXTURE_ID_BIT: usize = 1 << 8;
+
+struct FrameState {
+    #[allow(dead_code)]
+    fb_image_view: VkImageView,
+    fb_image: VkImage,
+    cmd_buffer: vk::CommandBuffer,
+    fence: VkFence,
+    descriptor_set: vk::DescriptorSet,
+    rendering_finished_semaphore: VkSemaphore,
+    /// ImGui geometry from the last time this frame was rendered, kept alive until the frame is reused
+    imgui_vtx_buffer: Option<VkBuffer>,
+    imgui_idx_buffer: Option<VkBuffer>,
+}
+
+impl FrameState {
+    fn new(
+        device: &VkDevice,
+        allocator: Weak<vk_mem::Allocator>,
+        command_pool: &VkCommandPool,
+        descriptor_pool: &VkDescriptorPool,
+        descriptor_set_layout: &VkDescriptorSetLayout,
+        frame_memory_buffer: &VkBuffer,
+        fb_width: u32,
+        fb_height: u32,
+        imgui_renderer: &ImguiRenderer,
+    ) -> Result<Self> {
+        let cmd_buffer = command_pool.allocate_command_buffer(vk::CommandBufferLevel::PRIMARY)?;
+
+        let rendering_finished_semaphore =
+            VkSemaphore::new(device.raw(), &vk::SemaphoreCreateInfo::default())?;
+        let fence = VkFence::new(
+            device.raw(),
+            &vk::FenceCreateInfo::builder().flags(vk::FenceCreateFlags::SIGNALED),
+        )?;
+
+        let descriptor_set =
+            descriptor_pool.allocate_descriptor_set(descriptor_set_layout.raw())?;
+
+        let fb_image = VkImage::new(
+            allocator,
+            &ash::vk::ImageCreateInfo::builder()
+                .image_type(vk::ImageType::TYPE_2D)
+                .extent(vk::Extent3D {
+                    width: fb_width,
+                    height: fb_height,
+                    depth: 1,
+                })
+                .mip_levels(1)
+                .array_layers(1)
+                .format(vk::Format::R8G8B8A8_UNORM)
+                .tiling(vk::ImageTiling::OPTIMAL)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .usage(vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .samples(vk::SampleCountFlags::TYPE_1),
+            &vk_mem::AllocationCreateInfo {
+                usage: vk_mem::MemoryUsage::GpuOnly,
+                ..Default::default()
+            },
+        )?;
+
+        let fb_image_view = VkImageView::new(
+            device.raw(),
+            &vk::ImageViewCreateInfo::builder()
+                .image(fb_image.raw())
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(vk::Format::R8G8B8A8_UNORM)
+                // The device doesn't output meaningful alpha values so the image is always displayed as opaque
+                .components(
+                    vk::ComponentMapping::builder()
+                        .r(vk::ComponentSwizzle::IDENTITY)
+                        .g(vk::ComponentSwizzle::IDENTITY)
+                        .b(vk::ComponentSwizzle::IDENTITY)
+                        .a(vk::ComponentSwizzle::ONE)
+                        .build(),
+                )
+                .subresource_range(
+                    vk::ImageSubresourceRange::builder()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .base_mip_level(0)
+                        .level_count(1)
+                        .base_array_layer(0)
+                        .layer_count(1)
+                        .build(),
+                ),
+        )?;
+        unsafe {
+            device.raw().upgrade().unwrap().update_descriptor_sets(
+                &[vk::WriteDescriptorSet::builder()
+                    .dst_set(descriptor_set)
+                    .dst_binding(0)
+                    .dst_array_element(0)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER_DYNAMIC)
+                    .buffer_info(&[vk::DescriptorBufferInfo::builder()
+                        .buffer(frame_memory_buffer.raw())
+                        .offset(0)
+                        .range(FRAME_MEMORY_SIZE)
+                        .build()])
+                    .build()],
+                &[],
+            );
+
+            let mut image_infos = (0..(NUM_TEXTURE_SLOTS - 1))
+                .map(|_| {
+                    vk::DescriptorImageInfo::builder()
+                        .image_view(fb_image_view.raw())
+                        .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                        .build()
+                })
+                .collect::<Vec<_>>();
+            image_infos.push(
+                vk::DescriptorImageInfo::builder()
+                    .image_view(imgui_renderer.font_atlas_image_view.raw())
+                    .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    .build(),
+            );
+            device.raw().upgrade().unwrap().update_descriptor_sets(
+                &[vk::WriteDescriptorSet::builder()
+                    .dst_set(descriptor_set)
+                    .dst_binding(2)
+                    .dst_array_element(0)
+                    .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+                    .image_info(&image_infos)
+                    .build()],
+                &[],
+            );
+        }
+
+        Ok(FrameState {
+            fb_image_view,
+            fb_image,
+            cmd_buffer,
+            fence,
+            descriptor_set,
+            rendering_finished_semaphore,
+            imgui_vtx_buffer: None,
+            imgui_idx_buffer: None,
+        })
+    }
+}
+
+/// Creates a view of each render target image
+fn create_target_image_views(
+    device: &VkDevice,
+    images: &[vk::Image],
+    format: vk::Format,
+) -> Result<Vec<VkImageView>> {
+    images
+        .iter()
+        .map(|image| {
+            VkImageView::new(
+                device.raw(),
+                &vk::ImageViewCreateInfo::builder()
+                    .image(*image)
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(format)
+                    .components(
+                        vk::ComponentMapping::builder()
+                            .r(vk::ComponentSwizzle::IDENTITY)
+                            .g(vk::ComponentSwizzle::IDENTITY)
+                            .b(vk::ComponentSwizzle::IDENTITY)
+                            .a(vk::ComponentSwizzle::IDENTITY)
+                            .build(),
+                    )
+                    .subresource_range(
+                        vk::ImageSubresourceRange::builder()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .base_mip_level(0)
+                            .level_count(1)
+                            .base_array_layer(0)
+                            .layer_count(1)
+                            .build(),
+                    ),
+            )
+        })
+        .collect()
+}
+
+/// Creates a framebuffer for each render target image view
+fn create_target_framebuffers(
+    device: &VkDevice,
+    renderpass: &VkRenderPass,
+    image_views: &[VkImageView],
+    extent: vk::Extent2D,
+) -> Result<Vec<VkFramebuffer>> {
+    image_views
+        .iter()
+        .map(|image_view| {
+            VkFramebuffer::new(
+                device.raw(),
+                &vk::FramebufferCreateInfo::builder()
+                    .render_pass(renderpass.raw())
+                    .attachments(&[image_view.raw()])
+                    .width(extent.width)
+                    .height(extent.height)
+                    .layers(1),
+            )
+        })
+        .collect()
+}
+
+struct Renderer {
+    #[allow(dead_code)]
+    imgui_renderer: ImguiRenderer,
+    frame_states: Vec<FrameState>,
+    fb_upload_buffer: VkBuffer,
+    frame_memory_buffer: VkBuffer,
+    image_available_semaphores: Vec<VkSemaphore>,
+    framebuffers: Vec<VkFramebuffer>,
+    renderpass: VkRenderPass,
+    #[allow(dead_code)]
+    cmd_pool: VkCommandPool,
+    #[allow(dead_code)]
+    linear_sampler: VkSampler,
+    #[allow(dead_code)]
+    nearest_sampler: VkSampler,
+    pipeline_layout: VkPipelineLayout,
+    #[allow(dead_code)]
+    descriptor_set_layout: VkDescriptorSetLayout,
+    #[allow(dead_code)]
+    descriptor_pool: VkDescriptorPool,
+    gfx_pipeline: VkPipeline,
+    imgui_pipeline: VkPipeline,
+    #[allow(dead_code)]
+    pipeline_cache: VkPipelineCache,
+    cur_frame_idx: usize,
+    /// Index of the target image used by the current frame
+    /// Offscreen images are used in order, so this only differs from the frame index when presenting to a window.
+    cur_swapchain_idx: usize,
+    target_image_views: Vec<VkImageView>,
+    target: RenderTarget,
+    allocator: Arc<vk_mem::Allocator>,
+    device: VkDevice,
+    _debug_messenger: Option<VkDebugMessenger>,
+    instance: VkInstance,
+}
+
+impl Renderer {
+    /// Creates a renderer that presents to the provided window
+    fn new(
+        window: &winit::window::Window,
+        fb_width: u32,
+        fb_height: u32,
+        enable_validation: bool,
+        context: &mut imgui::Context,
+    ) -> Result<Self> {
+        let window_size = window.inner_size();
+        Self::with_target(
+            Some(window),
+            [window_size.width, window_size.height],
+            fb_width,
+            fb_height,
+            enable_validation,
+            context,
+        )
+    }
+
+    fn with_target(
+        window: Option<&winit::window::Window>,
+        size: [u32; 2],
+        fb_width: u32,
+        fb_height: u32,
+        enable_validation: bool,
+        context: &mut imgui::Context,
+    ) -> Result<Self> {
+        let instance = match window {
+            Some(window) => VkInstance::new(window, enable_validation)?,
+            None => VkInstance::new_headless(enable_validation)?,
+        };
+
+        let _debug_messenger = if enable_validation {
+            Some(VkDebugMessenger::new(&instance)?)
+        } else {
+            None
+        };
+
+        let physical_devices = unsafe { instance.raw().enumerate_physical_devices()? };
+        let physical_device = select_physical_device(&physical_devices);
+
+        let surface = match window {
+            Some(window) => Some(VkSurface::new(&instance, window)?),
+            None => None,
+        };
+
+        let device = VkDevice::new(&instance, physical_device, surface.as_ref())?;
+
+        let allocator = Arc::new(vk_mem::Allocator::new(&vk_mem::AllocatorCreateInfo {
+            physical_device,
+            device: (*device.raw().upgrade().unwrap()).clone(),
+            instance: instance.raw().clone(),
+            flags: vk_mem::AllocatorCreateFlags::NONE,
+            preferred_large_heap_block_size: 0,
+            frame_in_use_count: 0,
+            heap_size_limits: None,
+        })?);
+
+        let pipeline_cache =
+            VkPipelineCache::new(device.raw(), &vk::PipelineCacheCreateInfo::default())?;
+
+        let target = match surface {
+            Some(surface) => RenderTarget::Swapchain {
+                swapchain: VkSwapchain::new(&instance, &surface, &device, size[0], size[1], None)?,
+                surface,
+            },
+            None => RenderTarget::Offscreen(OffscreenTarget::new(
+                Arc::downgrade(&allocator),
+                size[0],
+                size[1],
+            )?),
+        };
+
+        let target_images = target.images();
+        let desired_image_count = target_images.len() as u32;
+        let queue_family_index = 0;
+
+        let target_image_views =
+            create_target_image_views(&device, &target_images, target.format())?;
+
+        let renderpass = VkRenderPass::new(
+            device.raw(),
+            &vk::RenderPassCreateInfo::builder()
+                .attachments(&[vk::AttachmentDescription::builder()
+                    .format(target.format())
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .load_op(vk::AttachmentLoadOp::CLEAR)
+                    .store_op(vk::AttachmentStoreOp::STORE)
+                    .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                    .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                    .initial_layout(vk::ImageLayout::UNDEFINED)
+                    .final_layout(target.final_layout())
+                    .build()])
+                .subpasses(&[vk::SubpassDescription::builder()
+                    .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+                    .color_attachments(&[vk::AttachmentReference::builder()
+                        .attachment(0)
+                        .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                        .build()])
+                    .build()]),
+        )?;
+
+        let framebuffers =
+            create_target_framebuffers(&device, &renderpass, &target_image_views, target.extent())?;
+
+        let cmd_pool = VkCommandPool::new(
+            device.raw(),
+            &vk::CommandPoolCreateInfo::builder()
+                .queue_family_index(queue_family_index)
+                .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER),
+        )?;
+
+        let linear_sampler = VkSampler::new(
+            device.raw(),
+            &vk::SamplerCreateInfo::builder()
+                .mag_filter(vk::Filter::LINEAR)
+                .min_filter(vk::Filter::LINEAR)
+                .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+                .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                .min_lod(0.0)
+                .max_lod(10000.0)
+                .border_color(vk::BorderColor::FLOAT_TRANSPARENT_BLACK),
+        )?;
+
+        let nearest_sampler = VkSampler::new(
+            device.raw(),
+            &vk::SamplerCreateInfo::builder()
+                .mag_filter(vk::Filter::NEAREST)
+                .min_filter(vk::Filter::NEAREST)
+                .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+                .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                .min_lod(0.0)
+                .max_lod(10000.0)
+                .border_color(vk::BorderColor::FLOAT_TRANSPARENT_BLACK),
+        )?;
+
+        let descriptor_set_layout = VkDescriptorSetLayout::new(
+            device.raw(),
+            &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(0)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER_DYNAMIC)
+                    .descriptor_count(1)
+                    .stage_flags(
+                        vk::ShaderStageFlags::VERTEX
+                            | vk::ShaderStageFlags::FRAGMENT
+                            | vk::ShaderStageFlags::COMPUTE,
+                    )
+                    .build(),
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(1)
+                    .descriptor_type(vk::DescriptorType::SAMPLER)
+                    .descriptor_count(2)
+                    .stage_flags(vk::ShaderStageFlags::FRAGMENT | vk::ShaderStageFlags::COMPUTE)
+                    // The order needs to match the sampler indices in GraphicsBindings.glsl
+                    .immutable_samplers(&[linear_sampler.raw(), nearest_sampler.raw()])
+                    .build(),
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(2)
+                    .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+                    .descriptor_count(NUM_TEXTURE_SLOTS as u32)
+                    .stage_flags(vk::ShaderStageFlags::FRAGMENT | vk::ShaderStageFlags::COMPUTE)
+                    .build(),
+            ]),
+        )?;
+
+        let pipeline_layout = VkPipelineLayout::new(
+            device.raw(),
+            &vk::PipelineLayoutCreateInfo::builder()
+                .set_layouts(&[descriptor_set_layout.raw()])
+                .push_constant_ranges(&[vk::PushConstantRange::builder()
+                    .offset(0)
+                    .size((4 * std::mem::size_of::<u32>()) as u32)
+                    .stage_flags(
+                        vk::ShaderStageFlags::VERTEX
+                            | vk::ShaderStageFlags::FRAGMENT
+                            | vk::ShaderStageFlags::COMPUTE,
+                    )
+                    .build()]),
+        )?;
+
+        let descriptor_pool = VkDescriptorPool::new(
+            device.raw(),
+            &vk::DescriptorPoolCreateInfo::builder()
+                .max_sets(desired_image_count)
+                .pool_sizes(&[
+                    vk::DescriptorPoolSize::builder()
+                        .ty(vk::DescriptorType::STORAGE_BUFFER_DYNAMIC)
+                        .descriptor_count(desired_image_count)
+                        .build(),
+                    vk::DescriptorPoolSize::builder()
+                        .ty(vk::DescriptorType::SAMPLER)
+                        .descriptor_count(desired_image_count)
+                        .build(),
+                    vk::DescriptorPoolSize::builder()
+                        .ty(vk::DescriptorType::SAMPLED_IMAGE)
+                        .descriptor_count(desired_image_count * (NUM_TEXTURE_SLOTS as u32))
+                        .build(),
+                ]),
+        )?;
+
+        let mut compiler = shaderc::Compiler::new().expect("Failed to create compiler");
+
+        let vert_source = include_str!("../shaders/FullscreenPass.vert");
+        let frag_source = include_str!("../shaders/CopyTexture.frag");
+        let mut compile_options = shaderc::CompileOptions::new().unwrap();
+        let shader_dir = std::env::current_dir().unwrap().join("src/shaders");
+        compile_options.set_include_callback(move |name, _inc_type, _parent_name, _depth| {
+            let path = shader_dir.join(name);
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                Ok(shaderc::ResolvedInclude {
+                    resolved_name: String::from(name),
+                    content,
+                })
+            } else {
+                Err(format!(
+                    "Failed to load included shader code from {}.",
+                    name
+                ))
+            }
+        });
+
+        let vert_result = compiler.compile_into_spirv(
+            vert_source,
+            shaderc::ShaderKind::Vertex,
+            "FullscreenPass.vert",
+            "main",
+            Some(&compile_options),
+        )?;
+
+        let vert_module = VkShaderModule::new(
+            device.raw(),
+            &vk::ShaderModuleCreateInfo::builder().code(vert_result.as_binary()),
+        )?;
+
+        let frag_result = compiler.compile_into_spirv(
+            frag_source,
+            shaderc::ShaderKind::Fragment,
+            "CopyTexture.frag",
+            "main",
+            Some(&compile_options),
+        )?;
+
+        let frag_module = VkShaderModule::new(
+            device.raw(),
+            &vk::ShaderModuleCreateInfo::builder().code(frag_result.as_binary()),
+        )?;
+
+        let entry_point_c_string = std::ffi::CString::new("main").unwrap();
+        let gfx_pipeline = pipeline_cache.create_graphics_pipeline(
+            &vk::GraphicsPipelineCreateInfo::builder()
+                .stages(&[
+                    vk::PipelineShaderStageCreateInfo::builder()
+                        .stage(vk::ShaderStageFlags::VERTEX)
+                        .module(vert_module.raw())
+                        .name(entry_point_c_string.as_c_str())
+                        .build(),
+                    vk::PipelineShaderStageCreateInfo::builder()
+                        .stage(vk::ShaderStageFlags::FRAGMENT)
+                        .module(frag_module.raw())
+                        .name(entry_point_c_string.as_c_str())
+                        .build(),
+                ])
+                .input_assembly_state(
+                    &vk::PipelineInputAssemblyStateCreateInfo::builder()
+                        .topology(vk::PrimitiveTopology::TRIANGLE_LIST),
+                )
+                .vertex_input_state(&vk::PipelineVertexInputStateCreateInfo::builder().build())
+                .viewport_state(
+                    &vk::PipelineViewportStateCreateInfo::builder()
+                        .viewports(&[vk::Viewport::default()])
+                        .scissors(&[vk::Rect2D::default()]),
+                )
+                .rasterization_state(
+                    &vk::PipelineRasterizationStateCreateInfo::builder()
+                        .polygon_mode(vk::PolygonMode::FILL)
+                        .cull_mode(vk::CullModeFlags::BACK)
+                        .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+                        .line_width(1.0),
+                )
+                .multisample_state(
+                    &vk::PipelineMultisampleStateCreateInfo::builder()
+                        .rasterization_samples(vk::SampleCountFlags::TYPE_1),
+                )
+                // Don't need depth state
+                .color_blend_state(
+                    &vk::PipelineColorBlendStateCreateInfo::builder().attachments(&[
+                        vk::PipelineColorBlendAttachmentState::builder()
+                            .color_write_mask(
+                                vk::ColorComponentFlags::R
+                                    | vk::ColorComponentFlags::G
+                                    | vk::ColorComponentFlags::B
+                                    | vk::ColorComponentFlags::A,
+                            )
+                            .build(),
+                    ]),
+                )
+                .dynamic_state(
+                    &vk::PipelineDynamicStateCreateInfo::builder()
+                        .dynamic_states(&[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR]),
+                )
+                .layout(pipeline_layout.raw())
+                .render_pass(renderpass.raw())
+                .subpass(0),
+        )?;
+
+        let imgui_vert_source = include_str!("../shaders/ImguiTriangle.vert");
+        let imgui_frag_source = include_str!("../shaders/ImguiTriangle.frag");
+
+        let imgui_vert_result = compiler.compile_into_spirv(
+            imgui_vert_source,
+            shaderc::ShaderKind::Vertex,
+            "ImguiTriangle.vert",
+            "main",
+            Some(&compile_options),
+        )?;
+
+        let imgui_vert_module = VkShaderModule::new(
+            device.raw(),
+            &vk::ShaderModuleCreateInfo::builder().code(imgui_vert_result.as_binary()),
+        )?;
+
+        let imgui_frag_result = compiler.compile_into_spirv(
+            imgui_frag_source,
+            shaderc::ShaderKind::Fragment,
+            "ImguiTriangle.frag",
+            "main",
+            Some(&compile_options),
+        )?;
+
+        let imgui_frag_module = VkShaderModule::new(
+            device.raw(),
+            &vk::ShaderModuleCreateInfo::builder().code(imgui_frag_result.as_binary()),
+        )?;
+
+        let imgui_entry_point_c_string = std::ffi::CString::new("main").unwrap();
+        let imgui_pipeline = pipeline_cache.create_graphics_pipeline(
+            &vk::GraphicsPipelineCreateInfo::builder()
+                .stages(&[
+                    vk::PipelineShaderStageCreateInfo::builder()
+                        .stage(vk::ShaderStageFlags::VERTEX)
+                        .module(imgui_vert_module.raw())
+                        .name(imgui_entry_point_c_string.as_c_str())
+                        .build(),
+                    vk::PipelineShaderStageCreateInfo::builder()
+                        .stage(vk::ShaderStageFlags::FRAGMENT)
+                        .module(imgui_frag_module.raw())
+                        .name(imgui_entry_point_c_string.as_c_str())
+                        .build(),
+                ])
+                .input_assembly_state(
+                    &vk::PipelineInputAssemblyStateCreateInfo::builder()
+                        .topology(vk::PrimitiveTopology::TRIANGLE_LIST),
+                )
+                .vertex_input_state(
+                    &vk::PipelineVertexInputStateCreateInfo::builder()
+                        .vertex_binding_descriptions(&[vk::VertexInputBindingDescription::builder(
+                        )
+                        .binding(0)
+                        .stride(std::mem::size_of::<imgui::DrawVert>() as u32)
+                        .input_rate(vk::VertexInputRate::VERTEX)
+                        .build()])
+                        .vertex_attribute_descriptions(&[
+                            vk::VertexInputAttributeDescription::builder()
+                                .location(0)
+                                .binding(0)
+                                .format(vk::Format::R32G32_SFLOAT)
+                                .offset(0)
+                                .build(),
+                            vk::VertexInputAttributeDescription::builder()
+                                .location(1)
+                                .binding(0)
+                                .format(vk::Format::R32G32_SFLOAT)
+                                .offset(8)
+                                .build(),
+                            vk::VertexInputAttributeDescription::builder()
+                                .location(2)
+                                .binding(0)
+                                .format(vk::Format::R32_UINT)
+                                .offset(16)
+                                .build(),
+                        ]),
+                )
+                .viewport_state(
+                    &vk::PipelineViewportStateCreateInfo::builder()
+                        .viewports(&[vk::Viewport::default()])
+                        .scissors(&[vk::Rect2D::default()]),
+                )
+                .rasterization_state(
+                    &vk::PipelineRasterizationStateCreateInfo::builder()
+                        .polygon_mode(vk::PolygonMode::FILL)
+                        .cull_mode(vk::CullModeFlags::NONE)
+                        .line_width(1.0),
+                )
+                .multisample_state(
+                    &vk::PipelineMultisampleStateCreateInfo::builder()
+                        .rasterization_samples(vk::SampleCountFlags::TYPE_1),
+                )
+                // Don't need depth state
+                .color_blend_state(
+                    &vk::PipelineColorBlendStateCreateInfo::builder().attachments(&[
+                        vk::PipelineColorBlendAttachmentState::builder()
+                            .color_write_mask(
+                                vk::ColorComponentFlags::R
+                                    | vk::ColorComponentFlags::G
+                                    | vk::ColorComponentFlags::B
+                                    | vk::ColorComponentFlags::A,
+                            )
+                            .blend_enable(true)
+                            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                            .color_blend_op(vk::BlendOp::ADD)
+                            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                            .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+                            .alpha_blend_op(vk::BlendOp::ADD)
+                            .build(),
+                    ]),
+                )
+                .dynamic_state(
+                    &vk::PipelineDynamicStateCreateInfo::builder()
+                        .dynamic_states(&[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR]),
+                )
+                .layout(pipeline_layout.raw())
+                .render_pass(renderpass.raw())
+                .subpass(0),
+        )?;
+
+        let image_available_semaphores = target_images
+            .iter()
+            .map(|_| VkSemaphore::new(device.raw(), &vk::SemaphoreCreateInfo::default()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let image_size_bytes = fb_width * fb_height * 4;
+
+        let fb_upload_buffer = VkBuffer::new(
+            Arc::downgrade(&allocator),
+            &ash::vk::BufferCreateInfo::builder()
+                .size((((image_size_bytes + 255) & !255) * desired_image_count) as u64)
+                .usage(vk::BufferUsageFlags::TRANSFER_SRC),
+            &vk_mem::AllocationCreateInfo {
+                usage: vk_mem::MemoryUsage::CpuOnly,
+                flags: vk_mem::AllocationCreateFlags::MAPPED,
+                ..Default::default()
+            },
+        )?;
+
+        let frame_memory_buffer = VkBuffer::new(
+            Arc::downgrade(&allocator),
+            &ash::vk::BufferCreateInfo::builder()
+                .size(FRAME_MEMORY_SIZE * (desired_image_count as u64))
+                .usage(vk::BufferUsageFlags::STORAGE_BUFFER),
+            &vk_mem::AllocationCreateInfo {
+                usage: vk_mem::MemoryUsage::CpuToGpu,
+                flags: vk_mem::AllocationCreateFlags::MAPPED,
+                ..Default::default()
+            },
+        )?;
+
+        let imgui_renderer = ImguiRenderer::new(&device, Arc::downgrade(&allocator), context)?;
+
+        let frame_states = target_image_views
+            .iter()
+            .map(|_image_view| {
+                FrameState::new(
+                    &device,
+                    Arc::downgrade(&allocator),
+                    &cmd_pool,
+                    &descriptor_pool,
+                    &descriptor_set_layout,
+                    &frame_memory_buffer,
+                    fb_width,
+                    fb_height,
+                    &imgui_renderer,
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Renderer {
+            imgui_renderer,
+            frame_states,
+            frame_memory_buffer,
+            fb_upload_buffer,
+            framebuffers,
+            image_available_semaphores,
+            renderpass,
+            cmd_pool,
+            linear_sampler,
+            nearest_sampler,
+            pipeline_layout,
+            descriptor_set_layout,
+            descriptor_pool,
+            gfx_pipeline,
+            imgui_pipeline,
+            pipeline_cache,
+            cur_frame_idx: 0,
+            cur_swapchain_idx: 0,
+            target_image_views,
+            target,
+            allocator,
+            device,
+            _debug_messenger,
+            instance,
+        })
+    }
+
+    fn get_cur_frame_state(&self) -> &FrameState {
+        &self.frame_states[self.cur_swapchain_idx]
+    }
+
+    fn recreate_swapchain(&mut self, window: &winit::window::Window) -> Result<()> {
+        println!(
+            "Recreating {}x{} swapchain!",
+            window.inner_size().width,
+            window.inner_size().height
+        );
+
+        // Make sure all previous rendering work is completed before we destroy the old swapchain resources
+        self.wait_for_idle();
+
+        let swapchain = match &self.target {
+            RenderTarget::Swapchain { swapchain, surface } => VkSwapchain::new(
+                &self.instance,
+                surface,
+                &self.device,
+                window.inner_size().width,
+                window.inner_size().height,
+                Some(swapchain),
+            )?,
+            RenderTarget::Offscreen(_) => return Err("Offscreen targets have no swapchain".into()),
+        };
+
+        let target_image_views = create_target_image_views(
+            &self.device,
+            &swapchain.images,
+            swapchain.surface_format.format,
+        )?;
+
+        let framebuffers = create_target_framebuffers(
+            &self.device,
+            &self.renderpass,
+            &target_image_views,
+            swapchain.surface_resolution,
+        )?;
+
+        self.target_image_views = target_image_views;
+        self.framebuffers = framebuffers;
+        if let RenderTarget::Swapchain {
+            swapchain: cur_swapchain,
+            ..
+        } = &mut self.target
+        {
+            *cur_swapchain = swapchain;
+        }
+
+        Ok(())
+    }
+
+    fn begin_frame(&mut self) -> vk::CommandBuffer {
+        unsafe {
+            self.cur_swapchain_idx = match &self.target {
+                RenderTarget::Swapchain { swapchain, .. } => {
+                    // Acquire the current swapchain image index
+                    // TODO: Handle suboptimal swapchains
+                    let (image_index, _is_suboptimal) = swapchain
+                        .acquire_next_image(
+                            u64::MAX,
+                            Some(self.image_available_semaphores[self.cur_frame_idx].raw()),
+                            None,
+                        )
+                        .unwrap();
+                    // TODO: This should never happen since we're already handling window resize events, but this could be handled
+                    // more robustly in the future.
+                    assert!(!_is_suboptimal);
+                    image_index as usize
+                }
+                RenderTarget::Offscreen(_) => self.cur_frame_idx,
+            };
+
+            let frame_state = self.get_cur_frame_state();
+
+            // Wait for the resources for this frame to become available
+            self.device
+                .raw()
+                .upgrade()
+                .unwrap()
+                .wait_for_fences(&[frame_state.fence.raw()], true, u64::MAX)
+                .unwrap();
+
+            let cmd_buffer = frame_state.cmd_buffer;
+
+            self.device
+                .raw()
+                .upgrade()
+                .unwrap()
+                .begin_command_buffer(cmd_buffer, &vk::CommandBufferBeginInfo::default())
+                .unwrap();
+
+            cmd_buffer
+        }
+    }
+
+    fn begin_render(&self) {
+        let frame_state = self.get_cur_frame_state();
+        let framebuffer = &self.framebuffers[self.cur_swapchain_idx];
+        unsafe {
+            self.device.raw().upgrade().unwrap().cmd_begin_render_pass(
+                frame_state.cmd_buffer,
+                &vk::RenderPassBeginInfo::builder()
+                    .render_pass(self.renderpass.raw())
+                    .framebuffer(framebuffer.raw())
+                    .render_area(vk::Rect2D::builder().extent(self.target.extent()).build())
+                    .clear_values(&[vk::ClearValue {
+                        color: vk::ClearColorValue {
+                            float32: [0.0, 0.0, 0.0, 1.0],
+                        },
+                    }]),
+                vk::SubpassContents::INLINE,
+            );
+        }
+    }
+
+    fn end_render(&self) {
+        let frame_state = self.get_cur_frame_state();
+        unsafe {
+            self.device
+                .raw()
+                .upgrade()
+                .unwrap()
+                .cmd_end_render_pass(frame_state.cmd_buffer);
+        }
+    }
+
+    fn end_frame(&mut self, cmd_buffer: vk::CommandBuffer) {
+        let frame_state = self.get_cur_frame_state();
+        unsafe {
+            if let RenderTarget::Offscreen(target) = &self.target {
+                target.record_readback(&self.get_device(), cmd_buffer, self.cur_swapchain_idx);
+            }
+
+            self.device
+                .raw()
+                .upgrade()
+                .unwrap()
+                .end_command_buffer(cmd_buffer)
+                .unwrap();
+
+            // The user should always pass the same cmdbuffer back to us after a frame
+            assert_eq!(frame_state.cmd_buffer, cmd_buffer);
+
+            // Offscreen images don't need to be acquired or presented so there's nothing to synchronize with
+            let is_presenting = matches!(self.target, RenderTarget::Swapchain { .. });
+            let (wait_semaphores, signal_semaphores) = if is_presenting {
+                (
+                    vec![self.image_available_semaphores[self.cur_frame_idx].raw()],
+                    vec![frame_state.rendering_finished_semaphore.raw()],
+                )
+            } else {
+                (Vec::new(), Vec::new())
+            };
+            let wait_dst_stage_mask =
+                vec![vk::PipelineStageFlags::TOP_OF_PIPE; wait_semaphores.len()];
+            let command_buffers = [cmd_buffer];
+            let submit_info = vk::SubmitInfo::builder()
+                .wait_semaphores(&wait_semaphores)
+                .wait_dst_stage_mask(&wait_dst_stage_mask)
+                .command_buffers(&command_buffers)
+                .signal_semaphores(&signal_semaphores)
+                .build();
+
+            let fence = &frame_state.fence;
+            self.device
+                .raw()
+                .upgrade()
+                .unwrap()
+                .reset_fences(&[fence.raw()])
+                .unwrap();
+            self.device
+                .raw()
+                .upgrade()
+                .unwrap()
+                .queue_submit(self.device.present_queue(), &[submit_info], fence.raw())
+                .unwrap();
+
+            if let RenderTarget::Swapchain { swapchain, .. } = &self.target {
+                let _is_suboptimal = swapchain
+                    .present_image(
+                        self.cur_swapchain_idx as u32,
+                        &signal_semaphores,
+                        self.device.present_queue(),
+                    )
+                    .unwrap();
+                // TODO: This should never happen since we're already handling window resize events, but this could be handled
+                // more robustly in the future.
+                assert!(!_is_suboptimal);
+            }
+
+            self.cur_frame_idx = (self.cur_frame_idx + 1) % self.get_num_swapchain_images();
+        }
+    }
+
+    /// Returns the data in the upload buffer that's used to send the framebuffer to the gpu during the current frame
+    fn fb_upload_data(&mut self, fb_size: usize) -> &mut [u8] {
+        unsafe {
+            let p_fb_upload_buf_mem = self.fb_upload_buffer.info().get_mapped_data();
+            slice::from_raw_parts_mut(
+                p_fb_upload_buf_mem.add(fb_size * self.cur_swapchain_idx),
+                fb_size,
+            )
+        }
+    }
+
+    /// Records the commands that draw the view during the current frame
+    /// The framebuffer is drawn behind the ui across the whole target when is_framebuffer_fullscreen is true.
+    fn render(
+        &mut self,
+        cmd_buffer: vk::CommandBuffer,
+        fb_width: u32,
+        fb_height: u32,
+        draw_data: &imgui::DrawData,
+        is_framebuffer_fullscreen: bool,
+    ) {
+        let device = self.get_device();
+        let fb_size = (fb_width * fb_height * 4) as usize;
+        let target_extent = self.target.extent();
+
+        unsafe {
+            let cur_fb_image = &self.get_cur_frame_state().fb_image;
+
+            // Initialize the current framebuffer image
+            device.cmd_pipeline_barrier(
+                cmd_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[vk::ImageMemoryBarrier::builder()
+                    .src_access_mask(vk::AccessFlags::empty())
+                    .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                    .old_layout(vk::ImageLayout::UNDEFINED)
+                    .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .image(cur_fb_image.raw())
+                    .subresource_range(
+                        vk::ImageSubresourceRange::builder()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .base_mip_level(0)
+                            .level_count(1)
+                            .base_array_layer(0)
+                            .layer_count(1)
+                            .build(),
+                    )
+                    .build()],
+            );
+
+            // Copy the latest device output to the framebuffer image
+            let buffer_offset = self.cur_swapchain_idx * fb_size;
+            device.cmd_copy_buffer_to_image(
+                cmd_buffer,
+                self.fb_upload_buffer.raw(),
+                cur_fb_image.raw(),
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[vk::BufferImageCopy::builder()
+                    .buffer_offset(buffer_offset as u64)
+                    .image_subresource(vk::ImageSubresourceLayers {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    })
+                    .image_extent(vk::Extent3D {
+                        width: fb_width,
+                        height: fb_height,
+                        depth: 1,
+                    })
+                    .build()],
+            );
+
+            // Make sure the fb image is ready to be read by shaders
+            device.cmd_pipeline_barrier(
+                cmd_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[vk::ImageMemoryBarrier::builder()
+                    .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                    .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                    .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .image(cur_fb_image.raw())
+                    .subresource_range(
+                        vk::ImageSubresourceRange::builder()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .base_mip_level(0)
+                            .level_count(1)
+                            .base_array_layer(0)
+                            .layer_count(1)
+                            .build(),
+                    )
+                    .build()],
+            );
+
+            let descriptor_set = self.get_cur_frame_state().descriptor_set;
+
+            self.begin_render();
+
+            device.cmd_bind_pipeline(
+                cmd_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.gfx_pipeline.raw(),
+            );
+
+            device.cmd_set_viewport(
+                cmd_buffer,
+                0,
+                &[vk::Viewport::builder()
+                    .x(0.0)
+                    .y(0.0)
+                    .width(target_extent.width as f32)
+                    .height(target_extent.height as f32)
+                    .build()],
+            );
+
+            device.cmd_set_scissor(
+                cmd_buffer,
+                0,
+                &[vk::Rect2D::builder()
+                    .offset(vk::Offset2D::builder().x(0).y(0).build())
+                    .extent(target_extent)
+                    .build()],
+            );
+
+            let constant_data_offset = self.cur_swapchain_idx * (FRAME_MEMORY_SIZE as usize);
+
+            device.cmd_bind_descriptor_sets(
+                cmd_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_layout.raw(),
+                0,
+                &[descriptor_set],
+                &[constant_data_offset as u32],
+            );
+
+            let mut constant_writer = ConstantDataWriter::new(
+                self.frame_memory_buffer
+                    .info()
+                    .get_mapped_data()
+                    .add(constant_data_offset),
+                FRAME_MEMORY_SIZE as usize,
+            );
+
+            if is_framebuffer_fullscreen {
+                device.cmd_draw(cmd_buffer, 3, 1, 0, 0);
+            }
+
+            let draw_width = draw_data.display_size[0] * draw_data.framebuffer_scale[0];
+            let draw_height = draw_data.display_size[1] * draw_data.framebuffer_scale[1];
+            if (draw_width > 0.0) && (draw_height > 0.0) && draw_data.total_idx_count > 0 {
+                let total_vtx_count = draw_data.total_vtx_count as usize;
+                let vtx_buffer_size = total_vtx_count * std::mem::size_of::<imgui::DrawVert>();
+                let vtx_buffer = VkBuffer::new(
+                    self.get_allocator(),
+                    &ash::vk::BufferCreateInfo::builder()
+                        .size(vtx_buffer_size as u64)
+                        .usage(vk::BufferUsageFlags::VERTEX_BUFFER),
+                    &vk_mem::AllocationCreateInfo {
+                        usage: vk_mem::MemoryUsage::CpuToGpu,
+                        flags: vk_mem::AllocationCreateFlags::MAPPED,
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+                let vtx_buffer_slice =
+                    slice::from_raw_parts_mut(vtx_buffer.info().get_mapped_data(), vtx_buffer_size);
+                let vtx_buffer_raw = vtx_buffer.raw();
+
+                let total_idx_count = draw_data.total_idx_count as usize;
+                let idx_buffer_size = total_idx_count * std::mem::size_of::<imgui::DrawIdx>();
+                let idx_buffer = VkBuffer::new(
+                    self.get_allocator(),
+                    &ash::vk::BufferCreateInfo::builder()
+                        .size(idx_buffer_size as u64)
+                        .usage(vk::BufferUsageFlags::INDEX_BUFFER),
+                    &vk_mem::AllocationCreateInfo {
+                        usage: vk_mem::MemoryUsage::CpuToGpu,
+                        flags: vk_mem::AllocationCreateFlags::MAPPED,
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+                let idx_buffer_slice =
+                    slice::from_raw_parts_mut(idx_buffer.info().get_mapped_data(), idx_buffer_size);
+                let idx_buffer_raw = idx_buffer.raw();
+
+                let mut vtx_bytes_written: usize = 0;
+                let mut vtx_buffer_offsets = Vec::new();
+
+                let mut idx_bytes_written: usize = 0;
+                let mut idx_buffer_offsets = Vec::new();
+
+                for draw_list in draw_data.draw_lists() {
+                    let vtx_data_src = draw_list.vtx_buffer().as_ptr() as *const u8;
+                    let vtx_data_dst =
+                        (vtx_buffer_slice.as_mut_ptr() as *mut u8).add(vtx_bytes_written);
+                    let vtx_data_size =
+                        draw_list.vtx_buffer().len() * std::mem::size_of::<imgui::DrawVert>();
+                    core::ptr::copy_nonoverlapping(vtx_data_src, vtx_data_dst, vtx_data_size);
+                    vtx_buffer_offsets.push(vtx_bytes_written);
+                    vtx_bytes_written += vtx_data_size;
+
+                    let idx_data_src = draw_list.idx_buffer().as_ptr() as *const u8;
+                    let idx_data_dst =
+                        (idx_buffer_slice.as_mut_ptr() as *mut u8).add(idx_bytes_written);
+                    let idx_data_size =
+                        draw_list.idx_buffer().len() * std::mem::size_of::<imgui::DrawIdx>();
+                    core::ptr::copy_nonoverlapping(idx_data_src, idx_data_dst, idx_data_size);
+                    idx_buffer_offsets.push(idx_bytes_written);
+                    idx_bytes_written += idx_data_size;
+                }
+
+                let frame_state = &mut self.frame_states[self.cur_swapchain_idx];
+                frame_state.imgui_vtx_buffer = Some(vtx_buffer);
+                frame_state.imgui_idx_buffer = Some(idx_buffer);
+
+                device.cmd_bind_pipeline(
+                    cmd_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    self.imgui_pipeline.raw(),
+                );
+
+                let fb_scale = draw_data.framebuffer_scale;
+                device.cmd_set_viewport(
+                    cmd_buffer,
+                    0,
+                    &[vk::Viewport::builder()
+                        .x(draw_data.display_pos[0] * fb_scale[0])
+                        .y(draw_data.display_pos[1] * fb_scale[1])
+                        .width(draw_data.display_size[0] * fb_scale[0])
+                        .height(draw_data.display_size[1] * fb_scale[1])
+                        .build()],
+                );
+
+                let clip_off = draw_data.display_pos;
+                let clip_scale = draw_data.framebuffer_scale;
+
+                let left = draw_data.display_pos[0];
+                let right = draw_data.display_pos[0] + draw_data.display_size[0];
+                let top = draw_data.display_pos[1];
+                let bottom = draw_data.display_pos[1] + draw_data.display_size[1];
+                let matrix = [
+                    [(2.0 / (right - left)), 0.0, 0.0, 0.0],
+                    [0.0, (2.0 / (top - bottom)), 0.0, 0.0],
+                    [0.0, 0.0, -1.0, 0.0],
+                    [
+                        (right + left) / (left - right),
+                        (top + bottom) / (bottom - top),
+                        0.0,
+                        1.0,
+                    ],
+                ];
+
+                // Identify the current constant buffer offset before we write any new data into it
+                let dword_offset = constant_writer.dword_offset();
+
+                // Write the imgui matrix into the buffer
+                for row in &matrix {
+                    for val in row {
+                        constant_writer.write_all(&val.to_le_bytes()).unwrap();
+                    }
+                }
+
+                for (idx, draw_list) in draw_data.draw_lists().enumerate() {
+                    device.cmd_bind_vertex_buffers(
+                        cmd_buffer,
+                        0,
+                        &[vtx_buffer_raw],
+                        &[vtx_buffer_offsets[idx] as u64],
+                    );
+
+                    device.cmd_bind_index_buffer(
+                        cmd_buffer,
+                        idx_buffer_raw,
+                        idx_buffer_offsets[idx] as u64,
+                        vk::IndexType::UINT16,
+                    );
+
+                    for cmd in draw_list.commands() {
+                        match cmd {
+                            DrawCmd::Elements {
+                                count,
+                                cmd_params:
+                                    DrawCmdParams {
+                                        clip_rect,
+                                        texture_id,
+                                        vtx_offset,
+                                        idx_offset,
+                                    },
+                            } => {
+                                let clip_rect = [
+                                    (clip_rect[0] - clip_off[0]) * clip_scale[0],
+                                    (clip_rect[1] - clip_off[1]) * clip_scale[1],
+                                    (clip_rect[2] - clip_off[0]) * clip_scale[0],
+                                    (clip_rect[3] - clip_off[1]) * clip_scale[1],
+                                ];
+
+                                if clip_rect[0] < draw_width
+                                    && clip_rect[1] < draw_height
+                                    && clip_rect[2] >= 0.0
+                                    && clip_rect[3] >= 0.0
+                                {
+                                    let scissor_x = f32::max(0.0, clip_rect[0]).floor() as i32;
+                                    let scissor_y = f32::max(0.0, clip_rect[1]).floor() as i32;
+                                    let scissor_w =
+                                        (clip_rect[2] - clip_rect[0]).abs().ceil() as u32;
+                                    let scissor_h =
+                                        (clip_rect[3] - clip_rect[1]).abs().ceil() as u32;
+
+                                    device.cmd_set_scissor(
+                                        cmd_buffer,
+                                        0,
+                                        &[vk::Rect2D::builder()
+                                            .offset(
+                                                vk::Offset2D::builder()
+                                                    .x(scissor_x)
+                                                    .y(scissor_y)
+                                                    .build(),
+                                            )
+                                            .extent(
+                                                vk::Extent2D::builder()
+                                                    .width(scissor_w)
+                                                    .height(scissor_h)
+                                                    .build(),
+                                            )
+                                            .build()],
+                                    );
+
+                                    // The texture slot index and sampler are stored inside the ImGui texture id
+                                    let texture_index: u32 = texture_id.id() as u32;
+                                    let sampler_index = u32::from(
+                                        (texture_id.id() & NEAREST_SAMPLER_TEXTURE_ID_BIT) != 0,
+                                    );
+                                    let push_constant_0 = ((texture_index & 0xff) << 24)
+                                        | (dword_offset & 0x00ffffff);
+                                    let mut push_constants = [0; 8];
+                                    push_constants[0..4]
+                                        .copy_from_slice(&push_constant_0.to_le_bytes());
+                                    push_constants[4..8]
+                                        .copy_from_slice(&sampler_index.to_le_bytes());
+                                    device.cmd_push_constants(
+                                        cmd_buffer,
+                                        self.pipeline_layout.raw(),
+                                        vk::ShaderStageFlags::VERTEX
+                                            | vk::ShaderStageFlags::FRAGMENT
+                                            | vk::ShaderStageFlags::COMPUTE,
+                                        0,
+                                        &push_constants,
+                                    );
+
+                                    device.cmd_draw_indexed(
+                                        cmd_buffer,
+                                        count as u32,
+                                        1,
+                                        idx_offset as u32,
+                                        vtx_offset as i32,
+                                        0,
+                                    );
+                                }
+                            }
+                            DrawCmd::ResetRenderState => (), // NOTE: This doesn't seem necessary given how pipelines work?
+                            DrawCmd::RawCallback { callback, raw_cmd } => {
+                                callback(draw_list.raw(), raw_cmd)
+                            }
+                        }
+                    }
+                }
+            }
+
+            self.end_render();
+        }
+    }
+
+    fn wait_for_idle(&self) {
+        unsafe { self.get_device().device_wait_idle().unwrap() };
+    }
+
+    fn get_device(&self) -> Arc<ash::Device> {
+        self.device.raw().upgrade().unwrap()
+    }
+    fn get_allocator(&self) -> Weak<vk_mem::Allocator> {
+        Arc::downgrade(&self.allocator)
+    }
+
+    fn get_num_swapchain_images(&self) -> usize {
+        self.frame_states.len()
+    }
+}
+
+struct ImguiRenderer {
+    #[allow(dead_code)]
+    font_atlas_image: VkImage,
+    font_atlas_image_view: VkImageView,
+}
+
+impl ImguiRenderer {
+    fn new(
+        device: &VkDevice,
+        allocator: Weak<vk_mem::Allocator>,
+        context: &mut imgui::Context,
+    ) -> Result<Self> {
+        let font_atlas_image;
+        let font_atlas_image_view;
+        {
+            let mut context_fonts = context.fonts();
+            let font_atlas = context_fonts.build_alpha8_texture();
+
+            font_atlas_image = VkImage::new(
+                allocator.clone(),
+                &ash::vk::ImageCreateInfo::builder()
+                    .image_type(vk::ImageType::TYPE_2D)
+                    .extent(vk::Extent3D {
+                        width: font_atlas.width,
+                        height: font_atlas.height,
+                        depth: 1,
+                    })
+                    .mip_levels(1)
+                    .array_layers(1)
+                    .format(vk::Format::R8_UNORM)
+                    .tiling(vk::ImageTiling::OPTIMAL)
+                    .initial_layout(vk::ImageLayout::UNDEFINED)
+                    .usage(vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                    .samples(vk::SampleCountFlags::TYPE_1),
+                &vk_mem::AllocationCreateInfo {
+                    usage: vk_mem::MemoryUsage::GpuOnly,
+                    ..Default::default()
+                },
+            )?;
+            font_atlas_image_view = VkImageView::new(
+                device.raw(),
+                &vk::ImageViewCreateInfo::builder()
+                    .image(font_atlas_image.raw())
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(vk::Format::R8_UNORM)
+                    // The font atlas only stores coverage so it's expanded into white texels with alpha
+                    .components(
+                        vk::ComponentMapping::builder()
+                            .r(vk::ComponentSwizzle::ONE)
+                            .g(vk::ComponentSwizzle::ONE)
+                            .b(vk::ComponentSwizzle::ONE)
+                            .a(vk::ComponentSwizzle::R)
+                            .build(),
+                    )
+                    .subresource_range(
+                        vk::ImageSubresourceRange::builder()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .base_mip_level(0)
+                            .level_count(1)
+                            .base_array_layer(0)
+                            .layer_count(1)
+                            .build(),
+                    ),
+            )?;
+
+            let cmd_pool = VkCommandPool::new(
+                device.raw(),
+                &vk::CommandPoolCreateInfo::builder()
+                    .queue_family_index(device.graphics_queue_family_index() as u32),
+            )?;
+            let cmd_buffer = cmd_pool.allocate_command_buffer(vk::CommandBufferLevel::PRIMARY)?;
+            unsafe {
+                let raw_device = device.raw().upgrade().unwrap();
+                raw_device.begin_command_buffer(
+                    cmd_buffer,
+                    &vk::CommandBufferBeginInfo::builder()
+                        .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+                        .build(),
+                )?;
+
+                // TODO: It would be faster to upload this with the transfer queue, but it would significantly increase
+                //       the complexity of the upload process here. Replace this with a more standardized resource
+                //       upload process when it becomes available.
+                raw_device.cmd_pipeline_barrier(
+                    cmd_buffer,
+                    vk::PipelineStageFlags::TOP_OF_PIPE,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[vk::ImageMemoryBarrier::builder()
+                        .src_access_mask(vk::AccessFlags::empty())
+                        .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                        .old_layout(vk::ImageLayout::UNDEFINED)
+                        .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .image(font_atlas_image.raw())
+                        .subresource_range(
+                            vk::ImageSubresourceRange::builder()
+                                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                                .base_mip_level(0)
+                                .level_count(1)
+                                .base_array_layer(0)
+                                .layer_count(1)
+                                .build(),
+                        )
+                        .build()],
+                );
+
+                let atlas_buffer_size =
+                    ((font_atlas.width * font_atlas.height) as usize) * std::mem::size_of::<u8>();
+                let atlas_buffer = VkBuffer::new(
+                    allocator,
+                    &ash::vk::BufferCreateInfo::builder()
+                        .size(atlas_buffer_size as u64)
+                        .usage(vk::BufferUsageFlags::TRANSFER_SRC),
+                    &vk_mem::AllocationCreateInfo {
+                        usage: vk_mem::MemoryUsage::CpuToGpu,
+                        flags: vk_mem::AllocationCreateFlags::MAPPED,
+                        ..Default::default()
+                    },
+                )?;
+
+                let atlas_data_src = font_atlas.data.as_ptr();
+                let atlas_data_dst = atlas_buffer.info().get_mapped_data();
+                core::ptr::copy_nonoverlapping(atlas_data_src, atlas_data_dst, atlas_buffer_size);
+
+                raw_device.cmd_copy_buffer_to_image(
+                    cmd_buffer,
+                    atlas_buffer.raw(),
+                    font_atlas_image.raw(),
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[vk::BufferImageCopy::builder()
+                        .buffer_offset(0)
+                        .image_subresource(vk::ImageSubresourceLayers {
+                            aspect_mask: vk::ImageAspectFlags::COLOR,
+                            mip_level: 0,
+                            base_array_layer: 0,
+                            layer_count: 1,
+                        })
+                        .image_extent(vk::Extent3D {
+                            width: font_atlas.width,
+                            height: font_atlas.height,
+                            depth: 1,
+                        })
+                        .build()],
+                );
+
+                raw_device.cmd_pipeline_barrier(
+                    cmd_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::FRAGMENT_SHADER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[vk::ImageMemoryBarrier::builder()
+                        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                        .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                        .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                        .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .image(font_atlas_image.raw())
+                        .subresource_range(
+                            vk::ImageSubresourceRange::builder()
+                                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                                .base_mip_level(0)
+                                .level_count(1)
+                                .base_array_layer(0)
+                                .layer_count(1)
+                                .build(),
+                        )
+                        .build()],
+                );
+
+                raw_device.end_command_buffer(cmd_buffer)?;
+                raw_device.queue_submit(
+                    device.graphics_queue(),
+                    &[vk::SubmitInfo::builder()
+                        .command_buffers(&[cmd_buffer])
+                        .build()],
+                    vk::Fence::null(),
+                )?;
+                raw_device.queue_wait_idle(device.graphics_queue())?;
+            }
+        }
+
+        context.fonts().tex_id = imgui::TextureId::from(IMGUI_FONT_TEXTURE_SLOT_INDEX as usize);
+
+        Ok(ImguiRenderer {
+            font_atlas_image,
+            font_atlas_image_view,
+        })
+    }
+}
+
+/// Initial speed used when switching to a fixed number of cycles per frame
+const DEFAULT_CYCLES_PER_FRAME: u32 = 10_000;
+
+/// Largest speed selectable when executing a fixed number of cycles per frame
+const MAX_CYCLES_PER_FRAME: u32 = 10_000_000;
+
+/// Initial speed used when switching to a target clock rate
+const DEFAULT_TARGET_HZ: u32 = 1_000_000;
+
+/// Largest speed selectable when executing at a target clock rate
+const MAX_TARGET_HZ: u32 = 100_000_000;
+
+/// State of the view that's shared between windowed and headless runs
+struct App {
+    sim: Simulation,
+    settings: Settings,
+    /// Headless runs neither read nor write the saved settings so they aren't affected by interactive sessions
+    is_headless: bool,
+    capture_dir: PathBuf,
+    frame_recorder: Option<FrameRecorder>,
+    waveform_panel: WaveformPanel,
+    memory_panel: MemoryPanel,
+    cpu_panel: CpuPanel,
+    disassembly_panel: DisassemblyPanel,
+    framebuffer_panel: FramebufferPanel,
+    file_browser: FileBrowser,
+    toasts: Toasts,
+    waveform_capture_cycles: Option<u64>,
+    is_capturing_cpu_state: bool,
+    hot_reloader: Option<HotReloader>,
+    is_exit_requested: bool,
+}
+
+/// Displays the error of a failed request as a toast instead of stopping the view
+fn report_error(toasts: &mut Toasts, action: &str, result: Result<()>) {
+    if let Err(err) = result {
+        toasts.push(format!("{}: {}", action, err));
+    }
+}
+
+impl App {
+    fn new(opts: &SimOptions, is_headless: bool) -> Self {
+        let mut sim = Simulation::new().expect("Failed to create simulation");
+
+        let mut settings = Settings::new(DEFAULT_CYCLES_PER_FRAME, DEFAULT_TARGET_HZ);
+        if !is_headless {
+            if let Err(err) = settings.load() {
+                println!("Failed to load settings: {}", err);
+            }
+        }
+
+        // Options from the command line take priority over the saved ones
+        if let Some(cycles_per_frame) = opts.cycles_per_frame {
+            settings.cycles_per_frame = cycles_per_frame;
+            settings.speed = SimSpeed::CyclesPerFrame(cycles_per_frame);
+        } else if let Some(target_hz) = opts.target_hz {
+            settings.target_hz = target_hz;
+            settings.speed = SimSpeed::TargetHz(target_hz);
+        }
+        if let Some(width) = opts.width {
+            settings.window_size[0] = width;
+        }
+        if let Some(height) = opts.height {
+            settings.window_size[1] = height;
+        }
+        sim.set_speed(settings.speed)
+            .expect("Failed to configure simulation");
+        sim.set_elf_reload_enabled(settings.is_elf_reload_enabled);
+        sim.set_live_framebuffer(settings.is_live_framebuffer)
+            .expect("Failed to configure simulation");
+
+        let mut waveform_panel = WaveformPanel::new();
+        waveform_panel.is_open = settings.is_waveform_open;
+        let mut memory_panel = MemoryPanel::new();
+        memory_panel.is_open = settings.is_memory_open;
+        let mut cpu_panel = CpuPanel::new();
+        cpu_panel.is_open = settings.is_cpu_open;
+        let mut disassembly_panel = DisassemblyPanel::new();
+        disassembly_panel.is_open = settings.is_disassembly_open;
+        let mut framebuffer_panel = FramebufferPanel::new();
+        framebuffer_panel.is_open = settings.is_framebuffer_open;
+        // Without the verilator feature the software model is used instead of the hardware, so there's nothing to
+        // rebuild and verilator may not even be installed
+        let hot_reloader = if opts.no_hot_reload || is_headless || !cfg!(feature = "verilator") {
+            None
+        } else {
+            let mut hot_reloader = HotReloader::new().expect("Failed to start hardware hot reload");
+            hot_reloader.is_open = settings.is_hardware_build_open;
+            Some(hot_reloader)
+        };
+
+        let mut app = Self {
+            sim,
+            settings,
+            is_headless,
+            capture_dir: opts.capture_dir.clone(),
+            frame_recorder: None,
+            waveform_panel,
+            memory_panel,
+            cpu_panel,
+            disassembly_panel,
+            framebuffer_panel,
+            file_browser: FileBrowser::new(),
+            toasts: Toasts::new(),
+            waveform_capture_cycles: None,
+            is_capturing_cpu_state: false,
+            hot_reloader,
+            is_exit_requested: false,
+        };
+
+        // The last file is only reopened if it still exists since it may have been a temporary build
+        let elf_path = match &opts.elf_path {
+            Some(elf_path) => Some(PathBuf::from(elf_path)),
+            None => app
+                .settings
+                .last_elf()
+                .filter(|path| path.exists())
+                .cloned(),
+        };
+        if let Some(elf_path) = elf_path {
+            app.open_elf(&elf_path);
+        }
+
+        app
+    }
+
+    /// Loads an ELF file into the simulation and records it in the recent files list
+    fn open_elf(&mut self, path: &Path) {
+        if let Err(err) = self.sim.load_elf(&path) {
+            // Headless runs have nobody watching the toasts
+            println!("Failed to load {}: {}", path.display(), err);
+            self.toasts
+                .push(format!("Failed to load {}: {}", path.display(), err));
+            return;
+        }
+
+        if !self.is_headless {
+            self.settings.add_recent_file(path.to_path_buf());
+            // Settings are saved immediately so the recent files list survives crashes
+            if let Err(err) = self.settings.save() {
+                println!("Failed to save settings: {}", err);
+            }
+        }
+    }
+
+    /// Receives the latest results from the simulation and writes the framebuffer into the provided slice
+    /// This should be called once per rendered frame.
+    fn update(&mut self, fb_data: &mut [u8]) {
+        let sim = &mut self.sim;
+        let toasts = &mut self.toasts;
+
+        sim.update(fb_data);
+
+        if let Some(recorder) = &mut self.frame_recorder {
+            let result =
+                recorder.record(sim.framebuffer_width(), sim.framebuffer_height(), fb_data);
+            if let Err(err) = result {
+                toasts.push(format!("Stopped recording frames: {}", err));
+                self.frame_recorder = None;
+            }
+        }
+
+        match sim.reload_elf_if_changed() {
+            Ok(true) => {
+                if let Some(path) = sim.elf_path() {
+                    toasts.push(format!("Reloaded {}", path.display()));
+                }
+            }
+            Ok(false) => {}
+            Err(err) => toasts.push(format!("Failed to reload ELF: {}", err)),
+        }
+        if let Some(err) = sim.take_error() {
+            toasts.push(format!("Simulation error: {}", err));
+        }
+        if let Some(addr) = sim.take_breakpoint_hit() {
+            let text = match sim.symbols().lookup(addr) {
+                Some(symbol) => format!(
+                    "Breakpoint hit at {:#x} <{}+{:#x}>",
+                    addr,
+                    symbol.name,
+                    addr - symbol.addr
+                ),
+                None => format!("Breakpoint hit at {:#x}", addr),
+            };
+            toasts.push(text);
+        }
+    }
+
+    /// Draws the menus and panels of the view
+    /// The provided framebuffer data is the same data that's displayed during this frame.
+    fn draw(&mut self, ui: &imgui::Ui, fb_data: &[u8]) {
+        if let Some(main_menu_bar) = ui.begin_main_menu_bar() {
+            self.draw_file_menu(ui, fb_data);
+            self.draw_simulation_menu(ui);
+            if let Some(windows_menu) = ui.begin_menu(imgui::im_str!("Windows"), true) {
+                imgui::MenuItem::new(imgui::im_str!("Framebuffer"))
+                    .build_with_ref(ui, &mut self.framebuffer_panel.is_open);
+                imgui::MenuItem::new(imgui::im_str!("Waveform"))
+                    .build_with_ref(ui, &mut self.waveform_panel.is_open);
+                imgui::MenuItem::new(imgui::im_str!("Memory"))
+                    .build_with_ref(ui, &mut self.memory_panel.is_open);
+                imgui::MenuItem::new(imgui::im_str!("CPU"))
+                    .build_with_ref(ui, &mut self.cpu_panel.is_open);
+                imgui::MenuItem::new(imgui::im_str!("Disassembly"))
+                    .build_with_ref(ui, &mut self.disassembly_panel.is_open);
+                if let Some(hot_reloader) = &mut self.hot_reloader {
+                    imgui::MenuItem::new(imgui::im_str!("Hardware Build"))
+                        .build_with_ref(ui, &mut hot_reloader.is_open);
+                }
+
+                windows_menu.end(ui);
+            }
+            main_menu_bar.end(ui);
+        }
+
+        let sim = &mut self.sim;
+
+        let framebuffer_texture_id = FRAMEBUFFER_TEXTURE_SLOT_INDEX as usize;
+        self.framebuffer_panel.draw(
+            ui,
+            sim,
+            imgui::TextureId::from(framebuffer_texture_id),
+            imgui::TextureId::from(framebuffer_texture_id | NEAREST_SAMPLER_TEXTURE_ID_BIT),
+        );
+        self.waveform_panel.draw(ui, sim.waveform());
+        report_error(
+            &mut self.toasts,
+            "Failed to update memory view",
+            self.memory_panel.draw(ui, sim),
+        );
+        self.cpu_panel.draw(ui, sim);
+        self.disassembly_panel.draw(ui, sim);
+
+        if let Some(hot_reloader) = &mut self.hot_reloader {
+            if let Some(backend) = hot_reloader.update() {
+                self.toasts.push(match sim.set_backend(backend) {
+                    Ok(()) => "Reloaded hardware".to_string(),
+                    Err(err) => format!("Failed to reload hardware: {}", err),
+                });
+            }
+            hot_reloader.draw(ui);
+        }
+
+        self.toasts.draw(ui);
+
+        if let Some(path) = self.file_browser.draw(ui) {
+            self.open_elf(&path);
+        }
+
+        let sim = &mut self.sim;
+
+        // Waveforms are only captured while something is displaying them since tracing is expensive
+        let capture_cycles = self.waveform_panel.capture_cycles();
+        if capture_cycles != self.waveform_capture_cycles {
+            report_error(
+                &mut self.toasts,
+                "Failed to configure waveform capture",
+                sim.set_waveform_capture(capture_cycles),
+            );
+            self.waveform_capture_cycles = capture_cycles;
+        }
+
+        // The cpu state is only read while it's visible since each read costs time on the simulation thread
+        let is_cpu_state_needed = self.cpu_panel.is_open || self.disassembly_panel.is_open;
+        if is_cpu_state_needed != self.is_capturing_cpu_state {
+            report_error(
+                &mut self.toasts,
+                "Failed to configure cpu state capture",
+                sim.set_cpu_state_capture(is_cpu_state_needed),
+            );
+            self.is_capturing_cpu_state = is_cpu_state_needed;
+        }
+    }
+
+    fn draw_file_menu(&mut self, ui: &imgui::Ui, fb_data: &[u8]) {
+        let file_menu = match ui.begin_menu(imgui::im_str!("File"), true) {
+            Some(file_menu) => file_menu,
+            None => return,
+        };
+
+        if imgui::MenuItem::new(imgui::im_str!("Open...")).build(ui) {
+            // Browsing starts next to the current file since related files are usually nearby
+            let dir = self
+                .sim
+                .elf_path()
+                .and_then(|path| path.parent())
+                .filter(|dir| dir.is_dir())
+                .map(Path::to_path_buf)
+                .or_else(|| std::env::current_dir().ok())
+                .unwrap_or_default();
+            self.file_browser.open(&dir);
+        }
+        if let Some(recent_menu) = ui.begin_menu(
+            imgui::im_str!("Open Recent"),
+            !self.settings.recent_files.is_empty(),
+        ) {
+            let mut recent_path = None;
+            for path in &self.settings.recent_files {
+                let label = imgui::ImString::new(path.to_string_lossy());
+                if imgui::MenuItem::new(&label).build(ui) {
+                    recent_path = Some(path.clone());
+                }
+            }
+            ui.separator();
+            if imgui::MenuItem::new(imgui::im_str!("Clear")).build(ui) {
+                self.settings.recent_files.clear();
+            }
+            if let Some(path) = recent_path {
+                self.open_elf(&path);
+            }
+
+            recent_menu.end(ui);
+        }
+
+        ui.separator();
+
+        let sim = &self.sim;
+        let toasts = &mut self.toasts;
+
+        if imgui::MenuItem::new(imgui::im_str!("Save Framebuffer PNG")).build(ui) {
+            let path = self
+                .capture_dir
+                .join(capture::capture_name("framebuffer"))
+                .with_extension("png");
+            let result = capture::save_png(
+                &path,
+                sim.framebuffer_width(),
+                sim.framebuffer_height(),
+                fb_data,
+            );
+            toasts.push(match result {
+                Ok(()) => format!("Saved {}", path.display()),
+                Err(err) => format!("Failed to save framebuffer: {}", err),
+            });
+        }
+        if imgui::MenuItem::new(imgui::im_str!("Record Frames"))
+            .selected(self.frame_recorder.is_some())
+            .build(ui)
+        {
+            match self.frame_recorder.take() {
+                Some(recorder) => toasts.push(format!(
+                    "Recorded {} frames to {}",
+                    recorder.num_frames(),
+                    recorder.dir().display()
+                )),
+                None => {
+                    let dir = self.capture_dir.join(capture::capture_name("frames"));
+                    match FrameRecorder::new(dir) {
+                        Ok(recorder) => self.frame_recorder = Some(recorder),
+                        Err(err) => {
+                            toasts.push(format!("Failed to start recording frames: {}", err))
+                        }
+                    }
+                }
+            }
+        }
+
+        ui.separator();
+
+        let mut is_elf_reload_enabled = self.sim.is_elf_reload_enabled();
+        if imgui::MenuItem::new(imgui::im_str!("Reload ELF on change"))
+            .build_with_ref(ui, &mut is_elf_reload_enabled)
+        {
+            self.sim.set_elf_reload_enabled(is_elf_reload_enabled);
+        }
+
+        ui.separator();
+
+        if imgui::MenuItem::new(imgui::im_str!("Exit")).build(ui) {
+            self.is_exit_requested = true;
+        }
+
+        file_menu.end(ui);
+    }
+
+    fn draw_simulation_menu(&mut self, ui: &imgui::Ui) {
+        let simulation_menu = match ui.begin_menu(imgui::im_str!("Simulation"), true) {
+            Some(simulation_menu) => simulation_menu,
+            None => return,
+        };
+
+        let sim = &mut self.sim;
+        let settings = &mut self.settings;
+        let toasts = &mut self.toasts;
+
+        if imgui::MenuItem::new(imgui::im_str!("Reset")).build(ui) {
+            report_error(toasts, "Failed to reset simulation", sim.reset());
+        }
+
+        let toggle_string = if sim.is_running() {
+            imgui::im_str!("Pause")
+        } else {
+            imgui::im_str!("Resume")
+        };
+        if imgui::MenuItem::new(toggle_string).build(ui) {
+            report_error(toasts, "Failed to toggle simulation", sim.toggle());
+        }
+
+        if imgui::MenuItem::new(imgui::im_str!("Step"))
+            .enabled(!sim.is_running())
+            .build(ui)
+        {
+            report_error(toasts, "Failed to step simulation", sim.step());
+        }
+
+        ui.separator();
+
+        let speed = sim.speed();
+        if imgui::MenuItem::new(imgui::im_str!("Cycles per frame"))
+            .selected(matches!(speed, SimSpeed::CyclesPerFrame(_)))
+            .build(ui)
+        {
+            report_error(
+                toasts,
+                "Failed to set simulation speed",
+                sim.set_speed(SimSpeed::CyclesPerFrame(settings.cycles_per_frame)),
+            );
+        }
+        if imgui::MenuItem::new(imgui::im_str!("Target clock rate"))
+            .selected(matches!(speed, SimSpeed::TargetHz(_)))
+            .build(ui)
+        {
+            report_error(
+                toasts,
+                "Failed to set simulation speed",
+                sim.set_speed(SimSpeed::TargetHz(settings.target_hz)),
+            );
+        }
+        if imgui::MenuItem::new(imgui::im_str!("Max speed"))
+            .selected(speed == SimSpeed::Max)
+            .build(ui)
+        {
+            report_error(
+                toasts,
+                "Failed to set simulation speed",
+                sim.set_speed(SimSpeed::Max),
+            );
+        }
+
+        match speed {
+            SimSpeed::CyclesPerFrame(_) => {
+                if imgui::Slider::new(imgui::im_str!("Cycles"))
+                    .range(1..=MAX_CYCLES_PER_FRAME)
+                    .flags(imgui::SliderFlags::LOGARITHMIC)
+                    .build(ui, &mut settings.cycles_per_frame)
+                {
+                    report_error(
+                        toasts,
+                        "Failed to set simulation speed",
+                        sim.set_speed(SimSpeed::CyclesPerFrame(settings.cycles_per_frame)),
+                    );
+                }
+            }
+            SimSpeed::TargetHz(_) => {
+                if imgui::Slider::new(imgui::im_str!("Hz"))
+                    .range(1..=MAX_TARGET_HZ)
+                    .flags(imgui::SliderFlags::LOGARITHMIC)
+                    .build(ui, &mut settings.target_hz)
+                {
+                    report_error(
+                        toasts,
+                        "Failed to set simulation speed",
+                        sim.set_speed(SimSpeed::TargetHz(settings.target_hz)),
+                    );
+                }
+            }
+            SimSpeed::Max => {}
+        }
+
+        ui.separator();
+
+        // Only frames the firmware has presented are shown unless the live framebuffer is requested
+        let mut is_live_framebuffer = sim.is_live_framebuffer();
+        if imgui::MenuItem::new(imgui::im_str!("Show live framebuffer"))
+            .build_with_ref(ui, &mut is_live_framebuffer)
+        {
+            report_error(
+                toasts,
+                "Failed to configure simulation",
+                sim.set_live_framebuffer(is_live_framebuffer),
+            );
+        }
+
+        ui.separator();
+        ui.text(format!("Cycles: {}", sim.clocks()));
+        ui.text(format!("Frames presented: {}", sim.frame_count()));
+        ui.text(format!(
+            "Clock rate: {:.3} MHz",
+            sim.clock_rate() / 1_000_000.0
+        ));
+        ui.text(if sim.is_halted() {
+            "Device: Halted"
+        } else {
+            "Device: Executing"
+        });
+
+        simulation_menu.end(ui);
+    }
+
+    /// Saves the current settings so they're restored by the next run
+    fn save_settings(&mut self) {
+        if self.is_headless {
+            return;
+        }
+
+        let settings = &mut self.settings;
+        settings.speed = self.sim.speed();
+        settings.is_elf_reload_enabled = self.sim.is_elf_reload_enabled();
+        settings.is_live_framebuffer = self.sim.is_live_framebuffer();
+        settings.is_waveform_open = self.waveform_panel.is_open;
+        settings.is_memory_open = self.memory_panel.is_open;
+        settings.is_cpu_open = self.cpu_panel.is_open;
+        settings.is_disassembly_open = self.disassembly_panel.is_open;
+        settings.is_framebuffer_open = self.framebuffer_panel.is_open;
+        if let Some(hot_reloader) = &self.hot_reloader {
+            settings.is_hardware_build_open = hot_reloader.is_open;
+        }
+        if let Err(err) = settings.save() {
+            println!("Failed to save settings: {}", err);
+        }
+    }
+}
+
+/// Creates the ImGui context used to draw the view
+fn create_imgui_context() -> imgui::Context {
+    let mut context = imgui::Context::create();
+    context.set_renderer_name(Some(imgui::ImString::from(String::from("DevSim"))));
+    context
+        .io_mut()
+        .backend_flags
+        .insert(imgui::BackendFlags::RENDERER_HAS_VTX_OFFSET);
+    context
+}
+
+/// Shows the simulation window with the provided options
+fn show(opts: &SimOptions) -> ! {
+    let mut app = App::new(opts, false);
+
+    let window_width = app.settings.window_size[0];
+    let window_height = app.settings.window_size[1];
+
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title("DevSim View")
+        .with_inner_size(winit::dpi::PhysicalSize::new(window_width, window_height))
+        .build(&event_loop)
+        .expect("Failed to create window");
+
+    let mut context = create_imgui_context();
+    if let Err(err) = settings::load_layout(&mut context) {
+        println!("Failed to load window layout: {}", err);
+    }
+
+    let mut platform = WinitPlatform::init(&mut context);
+    platform.attach_window(context.io_mut(), &window, HiDpiMode::Default);
+
+    let fb_width = app.sim.framebuffer_width();
+    let fb_height = app.sim.framebuffer_height();
+    let mut renderer = Renderer::new(
+        &window,
+        fb_width,
+        fb_height,
+        !opts.no_validation,
+        &mut context,
+    )
+    .expect("Failed to create renderer");
+
+    let mut last_frame = Instant::now();
+    event_loop.run(move |event, _, control_flow| {
+        platform.handle_event(context.io_mut(), &window, &event);
+        match event {
+            Event::NewEvents(StartCause::Init) => {
+                *control_flow = ControlFlow::Poll;
+            }
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                WindowEvent::Resized(new_size) => {
+                    // Minimized windows report a size of zero, which shouldn't be restored later
+                    if (new_size.width > 0) && (new_size.height > 0) {
+                        app.settings.window_size = [new_size.width, new_size.height];
+                    }
+
+                    // TODO: This code needs to be updated to properly handle minimized windows
+                    //       When a window is minimized, it resizes to 0x0 which causes all sorts of problems
+                    //       inside the graphics api. This basically results in crashes on minimize. :/
+                    //       This will be fixed in a future change.
+                    renderer.recreate_swapchain(&window).unwrap();
+                }
+                WindowEvent::DroppedFile(path) => {
+                    app.open_elf(&path);
+                }
+                _ => {}
+            },
+            Event::MainEventsCleared => {
+                let cmd_buffer = renderer.begin_frame();
+
+                let fb_data = renderer.fb_upload_data(app.sim.framebuffer_size());
+                app.update(fb_data);
+
+                let now = Instant::now();
+                context.io_mut().update_delta_time(now - last_frame);
+                last_frame = now;
+
+                platform
+                    .prepare_frame(context.io_mut(), &window)
+                    .expect("Failed to prepare frame");
+
+                let ui = context.frame();
+                app.draw(&ui, fb_data);
+                if app.is_exit_requested {
+                    *control_flow = ControlFlow::Exit;
+                }
+
+                platform.prepare_render(&ui, &window);
+                let draw_data = ui.render();
+
+                // The framebuffer fills the window whenever its panel is closed
+                renderer.render(
+                    cmd_buffer,
+                    fb_width,
+                    fb_height,
+                    draw_data,
+                    !app.framebuffer_panel.is_open,
+                );
+
+                renderer.end_frame(cmd_buffer);
+            }
+            Event::LoopDestroyed => {
+                renderer.wait_for_idle();
+
+                app.save_settings();
+                if let Err(err) = settings::save_layout(&mut context) {
+                    println!("Failed to save window layout: {}", err);
+                }
+            }
+            event => match event {
+                Event::DeviceEvent { event, .. } => match event {
+                    DeviceEvent::Key(KeyboardInput {
+                        virtual_keycode: Some(keycode),
+                        state,
+                        ..
+                    }) => match (keycode, state) {
+                        (VirtualKeyCode::Escape, ElementState::Released) => {
+                            *control_flow = ControlFlow::Exit
+                        }
+                        _ => (),
+                    },
+                    _ => (),
+                },
+                _ => {}
+            },
+        }
+    });
+}
+
+#[derive(Debug, Clap)]
+#[clap(version)]
+struct SimOptions {
+    /// Path to a RISC-V elf to execute
+    elf_path: Option<String>,
+
+    /// Number of device cycles executed for each rendered frame
+    /// Takes priority over the target clock rate when both are specified.
+    #[clap(long)]
+    cycles_per_frame: Option<u32>,
+
+    /// Simulated clock rate in hz
+    /// The device runs as fast as possible when no speed is specified.
+    #[clap(long)]
+    target_hz: Option<u32>,
+
+    /// Disables rebuilding the hardware when its sources change
+    #[clap(long)]
+    no_hot_reload: bool,
+
+    /// Directory that framebuffer screenshots and recordings are written into
+    #[clap(long, default_value = ".")]
+    capture_dir: PathBuf,
+
+    /// Width of the window, or of the rendered image when running headless
+    #[clap(long)]
+    width: Option<u32>,
+
+    /// Height of the window, or of the rendered image when running headless
+    #[clap(long)]
+    height: Option<u32>,
+
+    /// Disables the Vulkan validation layers
+    #[clap(long)]
+    no_validation: bool,
+
+    /// Renders without opening a window and saves the last frame into the output file
+    #[clap(long)]
+    headless: bool,
+
+    /// Number of frames rendered when running headless
+    #[clap(long, default_value = "60")]
+    frames: u32,
+
+    /// Png file that the last frame is written into when running headless
+    #[clap(short, long, default_value = "view.png")]
+    output: PathBuf,
+}
+
+fn main() {
+    let opts = SimOptions::parse();
+    if opts.headless {
+        if let Err(err) = headless::run_headless(&opts) {
+            println!("Headless rendering failed: {}", err);
+            std::process::exit(1);
+        }
+    } else {
+        show(&opts);
+    }
+}
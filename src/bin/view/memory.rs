@@ -0,0 +1,338 @@
+use crate::simulation::Simulation;
+use imgui::{im_str, FocusedWidget, ImString, MouseButton, Ui};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const BYTES_PER_ROW: usize = 16;
+
+/// Extra rows read above and below the visible ones so small scrolls don't show missing data
+const PREFETCH_ROWS: usize = 8;
+
+const CHANGED_COLOR: [f32; 4] = [1.0, 0.4, 0.3, 1.0];
+const MISSING_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+const ERROR_COLOR: [f32; 4] = [1.0, 0.3, 0.3, 1.0];
+
+/// Size of the values displayed in each cell
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum CellSize {
+    Byte = 1,
+    Half = 2,
+    Word = 4,
+}
+
+/// A cell that's being edited
+struct Edit {
+    addr: u32,
+    input: ImString,
+    /// Keyboard focus is only moved to the input on the first frame so clicking away ends the edit
+    is_focus_requested: bool,
+}
+
+/// Parses an address that was typed by the user
+/// Symbol names take priority over numbers, which are read as hex with an optional 0x prefix.
+fn parse_address(sim: &Simulation, text: &str) -> Option<u32> {
+    let text = text.trim();
+    if let Some(symbol) = sim.symbols().find(text) {
+        return Some(symbol.addr);
+    }
+
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    u32::from_str_radix(digits, 16).ok()
+}
+
+/// ImGui panel that displays device memory and allows it to be edited
+pub struct MemoryPanel {
+    pub is_open: bool,
+    cell_size: CellSize,
+    jump_input: ImString,
+    jump_error: Option<String>,
+    /// Row that the view should scroll to during the next draw
+    scroll_target: Option<usize>,
+    /// Range of memory currently requested from the simulation
+    view: Option<(u32, usize)>,
+    /// Memory contents that are currently displayed
+    data: Option<(u32, Vec<u8>)>,
+    /// Marks the bytes in the displayed data that changed since the previous read
+    changed: Vec<bool>,
+    edit: Option<Edit>,
+    /// Address and new contents of an edited cell that needs to be written to the device
+    committed_edit: Option<(u32, Vec<u8>)>,
+}
+
+impl MemoryPanel {
+    pub fn new() -> Self {
+        Self {
+            is_open: false,
+            cell_size: CellSize::Byte,
+            jump_input: ImString::with_capacity(256),
+            jump_error: None,
+            scroll_target: None,
+            view: None,
+            data: None,
+            changed: Vec::new(),
+            edit: None,
+            committed_edit: None,
+        }
+    }
+
+    /// Compares the latest memory from the simulation with the displayed data to find which bytes changed
+    fn update_data(&mut self, sim: &Simulation) {
+        let (addr, data) = match sim.memory() {
+            Some(memory) => memory,
+            None => {
+                self.data = None;
+                return;
+            }
+        };
+
+        if let Some((prev_addr, prev_data)) = &self.data {
+            if (*prev_addr == addr) && (prev_data.as_slice() == data) {
+                return;
+            }
+
+            // Bytes that weren't displayed before have nothing to compare against so they aren't marked
+            self.changed = data
+                .iter()
+                .enumerate()
+                .map(|(idx, byte)| {
+                    let prev_idx = (addr as usize + idx).wrapping_sub(*prev_addr as usize);
+                    matches!(prev_data.get(prev_idx), Some(prev) if prev != byte)
+                })
+                .collect();
+        } else {
+            self.changed = vec![false; data.len()];
+        }
+
+        self.data = Some((addr, data.to_vec()));
+    }
+
+    /// Returns the displayed byte at an address along with whether it changed
+    fn byte(&self, addr: u32) -> Option<(u8, bool)> {
+        let (data_addr, data) = self.data.as_ref()?;
+        let idx = (addr as usize).checked_sub(*data_addr as usize)?;
+        Some((*data.get(idx)?, self.changed[idx]))
+    }
+
+    /// Returns the displayed value of the cell at an address along with whether any of its bytes changed
+    fn cell(&self, addr: u32) -> Option<(u32, bool)> {
+        let mut value = 0;
+        let mut is_changed = false;
+        for byte_idx in 0..(self.cell_size as u32) {
+            let (byte, is_byte_changed) = self.byte(addr + byte_idx)?;
+            value |= (byte as u32) << (byte_idx * 8);
+            is_changed |= is_byte_changed;
+        }
+        Some((value, is_changed))
+    }
+
+    /// Draws the panel if it's open and keeps the simulation's memory view in sync with it
+    pub fn draw(&mut self, ui: &Ui, sim: &mut Simulation) -> Result<()> {
+        let mut view = None;
+        if self.is_open {
+            self.update_data(sim);
+
+            let mut is_open = self.is_open;
+            imgui::Window::new(im_str!("Memory"))
+                .size([720.0, 400.0], imgui::Condition::FirstUseEver)
+                .opened(&mut is_open)
+                .build(ui, || view = self.draw_contents(ui, sim));
+            self.is_open = is_open;
+        }
+
+        // Memory is only read while it's visible since each read costs time on the simulation thread
+        let view = view.filter(|_| self.is_open);
+        if view != self.view {
+            sim.set_memory_view(view)?;
+            self.view = view;
+        }
+
+        if let Some((addr, data)) = self.committed_edit.take() {
+            sim.write_memory(addr, &data)?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_contents(&mut self, ui: &Ui, sim: &Simulation) -> Option<(u32, usize)> {
+        ui.set_next_item_width(200.0);
+        let is_jump_requested = ui
+            .input_text(im_str!("Address"), &mut self.jump_input)
+            .enter_returns_true(true)
+            .build();
+        ui.same_line(0.0);
+        if ui.button(im_str!("Go"), [0.0, 0.0]) || is_jump_requested {
+            match parse_address(sim, self.jump_input.to_str()) {
+                Some(addr) if (addr as usize) < sim.memory_size() => {
+                    self.scroll_target = Some(addr as usize / BYTES_PER_ROW);
+                    self.jump_error = None;
+                }
+                Some(addr) => {
+                    self.jump_error = Some(format!("{:#x} is outside of device memory", addr))
+                }
+                None => {
+                    self.jump_error = Some(format!(
+                        "{} isn't an address or symbol",
+                        self.jump_input.to_str()
+                    ))
+                }
+            }
+        }
+
+        for (label, cell_size) in [
+            (im_str!("Byte"), CellSize::Byte),
+            (im_str!("Half"), CellSize::Half),
+            (im_str!("Word"), CellSize::Word),
+        ]
+        .iter()
+        {
+            ui.same_line(0.0);
+            if ui.radio_button_bool(label, self.cell_size == *cell_size) {
+                self.cell_size = *cell_size;
+                self.edit = None;
+            }
+        }
+
+        if let Some(err) = &self.jump_error {
+            ui.text_colored(ERROR_COLOR, err);
+        }
+
+        let mut view = None;
+        imgui::ChildWindow::new("rows")
+            .horizontal_scrollbar(true)
+            .build(ui, || view = Some(self.draw_rows(ui, sim)));
+        view
+    }
+
+    /// Draws the visible rows of memory and returns the range of memory they need
+    /// Only the visible rows are drawn so the whole address space can be scrolled through cheaply.
+    fn draw_rows(&mut self, ui: &Ui, sim: &Simulation) -> (u32, usize) {
+        let row_height = ui.text_line_height_with_spacing();
+        let num_rows = sim.memory_size() / BYTES_PER_ROW;
+
+        if let Some(row) = self.scroll_target.take() {
+            ui.set_scroll_y(row as f32 * row_height);
+        }
+
+        let first_row = ((ui.scroll_y() / row_height) as usize).min(num_rows - 1);
+        let num_visible_rows = (ui.window_size()[1] / row_height) as usize + 2;
+        let end_row = (first_row + num_visible_rows).min(num_rows);
+
+        let start_pos = ui.cursor_pos();
+        ui.set_cursor_pos([start_pos[0], start_pos[1] + first_row as f32 * row_height]);
+
+        for row in first_row..end_row {
+            let row_addr = (row * BYTES_PER_ROW) as u32;
+            let symbol = sim
+                .symbols()
+                .lookup(row_addr)
+                .filter(|symbol| symbol.addr == row_addr);
+
+            ui.text(format!("{:05x}", row_addr));
+            for cell_idx in 0..(BYTES_PER_ROW / self.cell_size as usize) {
+                let cell_addr = row_addr + (cell_idx * self.cell_size as usize) as u32;
+                ui.same_line(0.0);
+
+                if self.edit.as_ref().map(|edit| edit.addr) == Some(cell_addr) {
+                    self.draw_edit(ui);
+                    continue;
+                }
+
+                let num_digits = self.cell_size as usize * 2;
+                match self.cell(cell_addr) {
+                    Some((value, true)) => {
+                        ui.text_colored(CHANGED_COLOR, format!("{:01$x}", value, num_digits))
+                    }
+                    Some((value, false)) => ui.text(format!("{:01$x}", value, num_digits)),
+                    None => ui.text_colored(MISSING_COLOR, "?".repeat(num_digits)),
+                }
+
+                if ui.is_item_hovered() {
+                    let symbol = sim.symbols().lookup(cell_addr);
+                    ui.tooltip_text(match symbol {
+                        Some(symbol) => format!(
+                            "{:#07x} ({}+{:#x})",
+                            cell_addr,
+                            symbol.name,
+                            cell_addr - symbol.addr
+                        ),
+                        None => format!("{:#07x}", cell_addr),
+                    });
+
+                    if ui.is_mouse_double_clicked(MouseButton::Left) {
+                        if let Some((value, _)) = self.cell(cell_addr) {
+                            let mut input = ImString::with_capacity(16);
+                            input.push_str(&format!("{:01$x}", value, num_digits));
+                            self.edit = Some(Edit {
+                                addr: cell_addr,
+                                input,
+                                is_focus_requested: true,
+                            });
+                        }
+                    }
+                }
+            }
+
+            let ascii = (0..BYTES_PER_ROW as u32)
+                .map(|idx| match self.byte(row_addr + idx) {
+                    Some((byte, _)) if byte.is_ascii_graphic() || (byte == b' ') => byte as char,
+                    Some(_) => '.',
+                    None => ' ',
+                })
+                .collect::<String>();
+            ui.same_line(0.0);
+            ui.text(format!(" {}", ascii));
+
+            if let Some(symbol) = symbol {
+                ui.same_line(0.0);
+                ui.text_colored(MISSING_COLOR, format!("<{}>", symbol.name));
+            }
+        }
+
+        // Reserve space for every row so the scroll bar covers the whole address space
+        ui.set_cursor_pos([start_pos[0], start_pos[1] + num_rows as f32 * row_height]);
+        ui.dummy([0.0, 0.0]);
+
+        let view_start_row = first_row.saturating_sub(PREFETCH_ROWS);
+        let view_end_row = (end_row + PREFETCH_ROWS).min(num_rows);
+        (
+            (view_start_row * BYTES_PER_ROW) as u32,
+            (view_end_row - view_start_row) * BYTES_PER_ROW,
+        )
+    }
+
+    /// Draws the input for the cell that's being edited and finishes the edit once it's committed or abandoned
+    fn draw_edit(&mut self, ui: &Ui) {
+        let cell_size = self.cell_size as usize;
+        let edit = match &mut self.edit {
+            Some(edit) => edit,
+            None => return,
+        };
+
+        if edit.is_focus_requested {
+            ui.set_keyboard_focus_here(FocusedWidget::Next);
+            edit.is_focus_requested = false;
+        }
+
+        let text_size = ui.calc_text_size(&ImString::new("0".repeat(cell_size * 2)), false, -1.0);
+        ui.set_next_item_width(text_size[0] + 2.0 * ui.clone_style().frame_padding[0]);
+        let is_committed = ui
+            .input_text(im_str!("##edit"), &mut edit.input)
+            .chars_hexadecimal(true)
+            .enter_returns_true(true)
+            .auto_select_all(true)
+            .build();
+
+        if is_committed {
+            // Invalid input abandons the edit rather than writing a guess
+            if let Ok(value) = u32::from_str_radix(edit.input.to_str(), 16) {
+                self.committed_edit = Some((edit.addr, value.to_le_bytes()[..cell_size].to_vec()));
+            }
+            self.edit = None;
+        } else if ui.is_item_deactivated() {
+            self.edit = None;
+        }
+    }
+}
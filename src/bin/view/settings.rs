@@ -0,0 +1,263 @@
+use crate::simulation::SimSpeed;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Largest number of files kept in the recent files list
+const MAX_RECENT_FILES: usize = 10;
+
+/// Returns the directory that view stores its configuration in
+/// This follows the XDG base directory specification and returns None if no suitable directory exists.
+fn config_dir() -> Option<PathBuf> {
+    let base_dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base_dir.join("devsim"))
+}
+
+fn layout_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("imgui.ini"))
+}
+
+fn settings_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("view.cfg"))
+}
+
+/// Restores the positions and sizes of ImGui windows from the config directory
+pub fn load_layout(context: &mut imgui::Context) -> Result<()> {
+    // Layouts are saved explicitly since ImGui only writes its file when the context is destroyed or periodically
+    context.set_ini_filename(None);
+
+    if let Some(path) = layout_path().filter(|path| path.exists()) {
+        context.load_ini_settings(&fs::read_to_string(path)?);
+    }
+    Ok(())
+}
+
+/// Writes the positions and sizes of ImGui windows to the config directory
+pub fn save_layout(context: &mut imgui::Context) -> Result<()> {
+    let path = layout_path().ok_or("Unable to find a config directory")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut layout = String::new();
+    context.save_ini_settings(&mut layout);
+    fs::write(path, layout)?;
+    Ok(())
+}
+
+/// Settings that are preserved between runs of view
+/// The positions and sizes of windows are stored separately by ImGui.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub window_size: [u32; 2],
+    /// Most recently loaded files, starting with the newest
+    pub recent_files: Vec<PathBuf>,
+    pub speed: SimSpeed,
+    /// Speed used when switching to a fixed number of cycles per frame
+    pub cycles_per_frame: u32,
+    /// Speed used when switching to a target clock rate
+    pub target_hz: u32,
+    pub is_elf_reload_enabled: bool,
+    pub is_live_framebuffer: bool,
+    pub is_waveform_open: bool,
+    pub is_memory_open: bool,
+    pub is_cpu_open: bool,
+    pub is_disassembly_open: bool,
+    pub is_framebuffer_open: bool,
+    pub is_hardware_build_open: bool,
+}
+
+impl Settings {
+    pub fn new(cycles_per_frame: u32, target_hz: u32) -> Self {
+        Self {
+            window_size: [1280, 720],
+            recent_files: Vec::new(),
+            speed: SimSpeed::Max,
+            cycles_per_frame,
+            target_hz,
+            is_elf_reload_enabled: true,
+            is_live_framebuffer: false,
+            is_waveform_open: false,
+            is_memory_open: false,
+            is_cpu_open: false,
+            is_disassembly_open: false,
+            is_framebuffer_open: true,
+            is_hardware_build_open: false,
+        }
+    }
+
+    /// Returns the most recently loaded file
+    pub fn last_elf(&self) -> Option<&PathBuf> {
+        self.recent_files.first()
+    }
+
+    /// Moves a file to the front of the recent files list
+    pub fn add_recent_file(&mut self, path: PathBuf) {
+        // Relative paths would refer to a different file when view is started from another directory
+        let path = fs::canonicalize(&path).unwrap_or(path);
+        self.recent_files.retain(|recent_path| *recent_path != path);
+        self.recent_files.insert(0, path);
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+
+    /// Loads the saved settings on top of the current ones
+    /// Missing files and unrecognized entries are ignored so older or newer versions of the file still load.
+    pub fn load(&mut self) -> Result<()> {
+        let path = match settings_path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(()),
+        };
+
+        self.parse(&fs::read_to_string(path)?);
+        Ok(())
+    }
+
+    /// Reads settings from the contents of a settings file on top of the current ones
+    fn parse(&mut self, text: &str) {
+        let mut recent_files = Vec::new();
+        for line in text.lines() {
+            // Keys never contain an equals sign, but paths can
+            let (key, raw_value) = match line.find('=') {
+                Some(idx) => (line[..idx].trim(), &line[(idx + 1)..]),
+                None => continue,
+            };
+            let value = raw_value.trim();
+            match key {
+                "window_size" => {
+                    let mut dims = value.split('x').filter_map(|dim| dim.parse().ok());
+                    if let (Some(width), Some(height)) = (dims.next(), dims.next()) {
+                        self.window_size = [width, height];
+                    }
+                }
+                // Paths can begin or end with whitespace, so only the space written after the separator is removed
+                "recent_file" => recent_files.push(PathBuf::from(
+                    raw_value.strip_prefix(' ').unwrap_or(raw_value),
+                )),
+                "speed" => match value {
+                    "cycles_per_frame" => self.speed = SimSpeed::CyclesPerFrame(0),
+                    "target_hz" => self.speed = SimSpeed::TargetHz(0),
+                    "max" => self.speed = SimSpeed::Max,
+                    _ => {}
+                },
+                "cycles_per_frame" => {
+                    self.cycles_per_frame = value.parse().unwrap_or(self.cycles_per_frame)
+                }
+                "target_hz" => self.target_hz = value.parse().unwrap_or(self.target_hz),
+                "reload_elf" => self.is_elf_reload_enabled = value == "true",
+                "live_framebuffer" => self.is_live_framebuffer = value == "true",
+                "waveform_open" => self.is_waveform_open = value == "true",
+                "memory_open" => self.is_memory_open = value == "true",
+                "cpu_open" => self.is_cpu_open = value == "true",
+                "disassembly_open" => self.is_disassembly_open = value == "true",
+                "framebuffer_open" => self.is_framebuffer_open = value == "true",
+                "hardware_build_open" => self.is_hardware_build_open = value == "true",
+                _ => {}
+            }
+        }
+        if !recent_files.is_empty() {
+            self.recent_files = recent_files;
+        }
+
+        // The speed values are stored separately so they're preserved while another mode is selected
+        self.speed = match self.speed {
+            SimSpeed::CyclesPerFrame(_) => SimSpeed::CyclesPerFrame(self.cycles_per_frame),
+            SimSpeed::TargetHz(_) => SimSpeed::TargetHz(self.target_hz),
+            SimSpeed::Max => SimSpeed::Max,
+        };
+    }
+
+    /// Writes the settings to the config directory
+    pub fn save(&self) -> Result<()> {
+        let path = settings_path().ok_or("Unable to find a config directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, self.to_text())?;
+        Ok(())
+    }
+
+    /// Returns the contents of a settings file that contains the current settings
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        text.push_str(&format!(
+            "window_size = {}x{}\n",
+            self.window_size[0], self.window_size[1]
+        ));
+        for recent_file in &self.recent_files {
+            text.push_str(&format!("recent_file = {}\n", recent_file.display()));
+        }
+        text.push_str(&format!(
+            "speed = {}\n",
+            match self.speed {
+                SimSpeed::CyclesPerFrame(_) => "cycles_per_frame",
+                SimSpeed::TargetHz(_) => "target_hz",
+                SimSpeed::Max => "max",
+            }
+        ));
+        text.push_str(&format!("cycles_per_frame = {}\n", self.cycles_per_frame));
+        text.push_str(&format!("target_hz = {}\n", self.target_hz));
+        text.push_str(&format!("reload_elf = {}\n", self.is_elf_reload_enabled));
+        text.push_str(&format!(
+            "live_framebuffer = {}\n",
+            self.is_live_framebuffer
+        ));
+        text.push_str(&format!("waveform_open = {}\n", self.is_waveform_open));
+        text.push_str(&format!("memory_open = {}\n", self.is_memory_open));
+        text.push_str(&format!("cpu_open = {}\n", self.is_cpu_open));
+        text.push_str(&format!(
+            "disassembly_open = {}\n",
+            self.is_disassembly_open
+        ));
+        text.push_str(&format!(
+            "framebuffer_open = {}\n",
+            self.is_framebuffer_open
+        ));
+        text.push_str(&format!(
+            "hardware_build_open = {}\n",
+            self.is_hardware_build_open
+        ));
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_round_trip() {
+        let mut settings = Settings::new(1000, 50_000);
+        settings.window_size = [800, 600];
+        settings.recent_files = vec![
+            PathBuf::from("/tmp/firmware.elf"),
+            PathBuf::from("/tmp/key=value/a b.elf"),
+            PathBuf::from(" /tmp/padded.elf "),
+        ];
+        settings.speed = SimSpeed::TargetHz(50_000);
+        settings.is_elf_reload_enabled = false;
+        settings.is_live_framebuffer = true;
+        settings.is_memory_open = true;
+        settings.is_framebuffer_open = false;
+
+        let mut loaded = Settings::new(1, 1);
+        loaded.parse(&settings.to_text());
+        assert_eq!(loaded, settings);
+    }
+
+    #[test]
+    fn parse_ignores_unknown_entries() {
+        let mut settings = Settings::new(1000, 50_000);
+        settings
+            .parse("unknown = 1\nno separator\nspeed = cycles_per_frame\ncycles_per_frame = 20\n");
+
+        assert_eq!(settings.speed, SimSpeed::CyclesPerFrame(20));
+        assert_eq!(settings.target_hz, 50_000);
+        assert!(settings.recent_files.is_empty());
+    }
+}
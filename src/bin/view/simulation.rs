@@ -0,0 +1,982 @@
+use devsim::device::{Backend, CpuDebugState, Device, Ticket, TraceFormat, NUM_BREAKPOINTS};
+use devsim::elf::{self, CodeSection, LineTable, SymbolTable};
+use devsim::vcd::{self, Vcd};
+use std::cmp;
+use std::fs::{self, File};
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Largest number of device cycles that are simulated between each framebuffer snapshot
+/// This also bounds how long the simulation thread goes without checking for new commands.
+const MAX_CYCLES_PER_UPDATE: usize = 100_000;
+
+/// How long the simulation thread waits for commands while it's waiting for more cycles at a target clock rate
+const IDLE_WAIT_DURATION: Duration = Duration::from_millis(1);
+
+/// Longest period of time the simulation can fall behind a target clock rate before cycles are skipped
+/// This avoids bursts of execution after the device is unable to keep up with the target rate.
+const MAX_BUDGET_DURATION: Duration = Duration::from_millis(100);
+
+/// Period of time used to measure the simulated clock rate
+const CLOCK_RATE_SAMPLE_PERIOD: Duration = Duration::from_millis(500);
+
+/// How often the current ELF file is checked for changes
+const ELF_CHECK_PERIOD: Duration = Duration::from_millis(250);
+
+/// Size in bytes that the waveform file can grow to before the trace is started over
+const MAX_WAVEFORM_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Controls how quickly the device executes
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SimSpeed {
+    /// Executes a fixed number of cycles for each rendered frame
+    CyclesPerFrame(u32),
+    /// Executes cycles at a fixed simulated clock rate
+    TargetHz(u32),
+    /// Executes cycles as fast as the simulation allows
+    Max,
+}
+
+impl SimSpeed {
+    /// Returns the number of cycles executed by a single step while paused
+    fn cycles_per_step(self) -> usize {
+        match self {
+            SimSpeed::CyclesPerFrame(cycles) => cycles as usize,
+            // Steps are sized so they're similar to a single frame of a 60hz display
+            SimSpeed::TargetHz(hz) => cmp::max(hz as usize / 60, 1),
+            SimSpeed::Max => MAX_CYCLES_PER_UPDATE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum SimulationState {
+    Running,
+    Paused,
+}
+
+/// Commands sent from the render loop to the simulation thread
+#[derive(Debug)]
+enum SimCommand {
+    /// Loads an ELF file into a fresh device
+    LoadElf(PathBuf),
+    /// Resets the device and reloads the current ELF file if there is one
+    Reset,
+    Pause,
+    Resume,
+    /// Executes a single step while paused
+    Step,
+    SetSpeed(SimSpeed),
+    /// Notifies the simulation that the render loop started a new frame
+    Frame,
+    /// Captures a waveform of the specified number of cycles at the end of each update, or stops capturing
+    SetWaveformCapture(Option<u64>),
+    /// Replaces the hardware backend and resets the device with it
+    SetBackend(Arc<Backend>),
+    /// Reads the specified range of memory along with each snapshot, or stops reading memory
+    SetMemoryView(Option<(u32, usize)>),
+    WriteMemory {
+        addr: u32,
+        data: Vec<u8>,
+    },
+    /// Reads the cpu state along with each snapshot when enabled
+    SetCpuStateCapture(bool),
+    /// Replaces the addresses that pause execution when the cpu reaches them
+    SetBreakpoints(Vec<u32>),
+    /// Shows the framebuffer as it's being drawn instead of the most recently presented frame when enabled
+    SetLiveFramebuffer(bool),
+}
+
+/// State of the device at the end of an update
+#[derive(Debug)]
+struct Snapshot {
+    fb_data: Vec<u8>,
+    fb_addr: u32,
+    frame_count: u32,
+    clocks: u64,
+    is_halted: bool,
+}
+
+/// Latest result of each kind that the simulation thread produced for the render loop
+/// Each result replaces the previous one of the same kind, so results are never lost to results of other kinds while
+/// the render loop is catching up.
+#[derive(Debug, Default)]
+struct SimResults {
+    snapshot: Option<Snapshot>,
+    waveform: Option<Vcd>,
+    memory: Option<(u32, Vec<u8>)>,
+    cpu_state: Option<CpuDebugState>,
+}
+
+/// Events sent from the simulation thread to the render loop
+/// Unlike results, every event is delivered.
+#[derive(Debug)]
+enum SimEvent {
+    /// The cpu halted at the breakpoint with the provided address and execution was paused
+    BreakpointHit(u32),
+    /// The backend was replaced by hardware with the provided memory size
+    MemorySizeChanged(usize),
+    Error(String),
+}
+
+/// Returns the path of the file that waveforms are captured into
+fn waveform_path() -> PathBuf {
+    std::env::temp_dir().join(format!("devsim_waveform_{}.vcd", std::process::id()))
+}
+
+/// A trace of the device that's parsed while it's being written
+struct WaveformCapture {
+    file: File,
+    parser: vcd::Parser,
+}
+
+/// A frame that was read from the device as soon as the firmware presented it
+struct PresentedFrame {
+    fb_data: Vec<u8>,
+    fb_addr: u32,
+}
+
+/// Owns the device and executes it on the simulation thread
+struct SimulationWorker {
+    backend: Arc<Backend>,
+    /// Size of the framebuffer the render loop was created with
+    fb_size: (u32, u32),
+    device: Option<Device>,
+    elf_path: Option<PathBuf>,
+    state: SimulationState,
+    speed: SimSpeed,
+    /// Number of cycles the device may execute before the current speed grants more
+    cycle_budget: f64,
+    last_budget_update: Instant,
+    fb_ticket: Option<Ticket>,
+    waveform_cycles: Option<u64>,
+    waveform: Option<WaveformCapture>,
+    memory_view: Option<(u32, usize)>,
+    is_cpu_state_enabled: bool,
+    breakpoints: Vec<u32>,
+    is_live_framebuffer: bool,
+    presented_frame: Option<PresentedFrame>,
+    frame_count: u32,
+    results: Arc<Mutex<SimResults>>,
+    events: Sender<SimEvent>,
+}
+
+impl SimulationWorker {
+    /// Processes commands and executes the device until the render loop disconnects
+    fn run(mut self, commands: Receiver<SimCommand>) {
+        loop {
+            self.update_budget();
+            let num_cycles = self.available_cycles();
+
+            // Block while there's nothing to execute so an idle simulation doesn't occupy a core
+            let cmd = if num_cycles > 0 {
+                match commands.try_recv() {
+                    Ok(cmd) => Some(cmd),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => break,
+                }
+            } else if self.is_active() && matches!(self.speed, SimSpeed::TargetHz(_)) {
+                match commands.recv_timeout(IDLE_WAIT_DURATION) {
+                    Ok(cmd) => Some(cmd),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            } else {
+                match commands.recv() {
+                    Ok(cmd) => Some(cmd),
+                    Err(_) => break,
+                }
+            };
+
+            let result = match cmd {
+                Some(cmd) => self.handle_command(cmd),
+                None if num_cycles > 0 => self.update(num_cycles),
+                None => Ok(()),
+            };
+
+            if let Err(err) = result {
+                // Execution stops on errors so the same error isn't reported repeatedly
+                self.state = SimulationState::Paused;
+                let _ = self.events.send(SimEvent::Error(err.to_string()));
+            }
+        }
+    }
+
+    fn handle_command(&mut self, cmd: SimCommand) -> Result<()> {
+        match cmd {
+            SimCommand::LoadElf(path) => {
+                self.elf_path = Some(path);
+                self.reset()?;
+            }
+            SimCommand::Reset => self.reset()?,
+            SimCommand::Pause => self.state = SimulationState::Paused,
+            SimCommand::Resume => {
+                self.resume_from_breakpoint()?;
+                self.state = SimulationState::Running;
+                self.cycle_budget = 0.0;
+            }
+            SimCommand::Step => {
+                if self.state == SimulationState::Paused {
+                    self.resume_from_breakpoint()?;
+                    self.execute(self.speed.cycles_per_step())?;
+                    self.update_snapshot(true)?;
+                }
+            }
+            SimCommand::SetSpeed(speed) => {
+                self.speed = speed;
+                self.cycle_budget = 0.0;
+            }
+            SimCommand::SetWaveformCapture(num_cycles) => {
+                let was_capturing = self.waveform_cycles.is_some();
+                self.waveform_cycles = num_cycles;
+                if num_cycles.is_none() {
+                    self.waveform = None;
+                    if let Some(device) = &mut self.device {
+                        device.stop_trace()?;
+                    }
+                    // The file may not exist if nothing was captured
+                    let _ = fs::remove_file(waveform_path());
+                } else if !was_capturing {
+                    self.start_waveform()?;
+                }
+            }
+            SimCommand::SetBackend(backend) => {
+                // The new hardware may have been built with different sizes, but the render loop's framebuffer
+                // resources can't be resized
+                let mut device = Device::with_backend(backend.clone());
+                let fb_size = device.query_framebuffer_size()?;
+                if fb_size != self.fb_size {
+                    return Err(format!(
+                        "The new hardware has a {}x{} framebuffer instead of {}x{}, restart to use it",
+                        fb_size.0, fb_size.1, self.fb_size.0, self.fb_size.1
+                    )
+                    .into());
+                }
+                let memory_size = device.query_memory_size()?;
+                let _ = self.events.send(SimEvent::MemorySizeChanged(memory_size));
+
+                self.backend = backend;
+                self.reset()?;
+            }
+            SimCommand::SetMemoryView(memory_view) => {
+                self.memory_view = memory_view;
+                self.update_memory()?;
+            }
+            SimCommand::WriteMemory { addr, data } => {
+                if let Some(device) = &mut self.device {
+                    device.write_memory(addr, &data)?;
+                    self.update_memory()?;
+                    self.update_cpu_state()?;
+                }
+            }
+            SimCommand::SetCpuStateCapture(is_enabled) => {
+                self.is_cpu_state_enabled = is_enabled;
+                self.update_cpu_state()?;
+            }
+            SimCommand::SetBreakpoints(breakpoints) => {
+                self.breakpoints = breakpoints;
+                if let Some(device) = &mut self.device {
+                    device.set_breakpoints(&self.breakpoints)?;
+                }
+            }
+            SimCommand::SetLiveFramebuffer(is_enabled) => {
+                self.is_live_framebuffer = is_enabled;
+                self.update_snapshot(true)?;
+            }
+            SimCommand::Frame => {
+                // Unused cycles from earlier frames are discarded so a slow device doesn't build up a backlog
+                if let SimSpeed::CyclesPerFrame(cycles) = self.speed {
+                    self.cycle_budget = cycles as f64;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the device should currently be executing
+    fn is_active(&self) -> bool {
+        self.device.is_some() && (self.state == SimulationState::Running)
+    }
+
+    /// Grants cycles to the device based on the time that has elapsed at the current speed
+    fn update_budget(&mut self) {
+        let now = Instant::now();
+        if let SimSpeed::TargetHz(hz) = self.speed {
+            let elapsed = now - self.last_budget_update;
+            let max_budget = (hz as f64 * MAX_BUDGET_DURATION.as_secs_f64()).max(1.0);
+            self.cycle_budget =
+                (self.cycle_budget + (hz as f64 * elapsed.as_secs_f64())).min(max_budget);
+        }
+        self.last_budget_update = now;
+    }
+
+    /// Returns the number of cycles the device should execute during the next update
+    fn available_cycles(&self) -> usize {
+        if !self.is_active() {
+            0
+        } else if self.speed == SimSpeed::Max {
+            MAX_CYCLES_PER_UPDATE
+        } else {
+            cmp::min(self.cycle_budget as usize, MAX_CYCLES_PER_UPDATE)
+        }
+    }
+
+    /// Recreates the device and reloads the current ELF file if there is one
+    fn reset(&mut self) -> Result<()> {
+        if let Some(path) = &self.elf_path {
+            // The old device is released first so a failed load doesn't leave stale state behind
+            self.device = None;
+            self.fb_ticket = None;
+            self.presented_frame = None;
+            self.frame_count = 0;
+
+            let mut device = Device::with_backend(self.backend.clone());
+            device.load_elf(path)?;
+            device.set_breakpoints(&self.breakpoints)?;
+            self.device = Some(device);
+
+            self.start_waveform()?;
+            self.update_snapshot(true)?;
+        }
+
+        Ok(())
+    }
+
+    /// Executes the device for a single update and forwards any completed framebuffer snapshot
+    fn update(&mut self, num_cycles: usize) -> Result<()> {
+        self.execute(num_cycles)?;
+        self.cycle_budget = (self.cycle_budget - num_cycles as f64).max(0.0);
+
+        // Breakpoints pause execution, and the snapshot is completed right away so it shows where the cpu stopped
+        let is_at_breakpoint = self.state == SimulationState::Paused;
+        self.update_snapshot(is_at_breakpoint)
+    }
+
+    /// Lets the cpu continue past the breakpoint it's halted at, if any
+    fn resume_from_breakpoint(&mut self) -> Result<()> {
+        if let Some(device) = &mut self.device {
+            if !self.breakpoints.is_empty() && device.query_is_at_breakpoint()? {
+                device.resume_from_breakpoint()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Executes the device for the specified number of cycles
+    fn execute(&mut self, num_cycles: usize) -> Result<()> {
+        if let Some(device) = &mut self.device {
+            // Halted programs are resumed so they can continue past each wfi
+            if device.query_is_halted()? {
+                device.enable();
+            }
+
+            // Waiting for a present polls the frame count every few cycles, so firmware that has never presented is
+            // simply run since it would pay for the polling without ever producing a frame
+            if self.is_live_framebuffer || (device.query_frame_count()? == 0) {
+                device.run(num_cycles);
+            } else {
+                // Frames are read as soon as they're presented so the render loop never sees a partially drawn frame
+                let end_clocks = device.clocks() + num_cycles as u64;
+                while device.clocks() < end_clocks {
+                    // Nothing else is presented during this update once the firmware halts
+                    let frame_count = match device.wait_for_present(end_clocks - device.clocks())? {
+                        Some(frame_count) => frame_count,
+                        None => break,
+                    };
+
+                    let (fb_width, fb_height) = device.query_framebuffer_size()?;
+                    let mut fb_data = vec![0; (fb_width * fb_height * 4) as usize];
+                    device.dump_framebuffer(&mut fb_data)?;
+
+                    self.presented_frame = Some(PresentedFrame {
+                        fb_data,
+                        fb_addr: device.query_framebuffer_addr()?,
+                    });
+                    self.frame_count = frame_count;
+                }
+            }
+
+            // The cpu stays halted at a breakpoint until it's resumed so the rest of the cycles are harmless
+            if !self.breakpoints.is_empty() && device.query_is_at_breakpoint()? {
+                self.state = SimulationState::Paused;
+                let pc = device.read_cpu_state()?.pc;
+                let _ = self.events.send(SimEvent::BreakpointHit(pc));
+            }
+        }
+
+        self.update_waveform()
+    }
+
+    /// Begins tracing the device into the waveform file if waveforms are being captured
+    fn start_waveform(&mut self) -> Result<()> {
+        self.waveform = None;
+        if let (Some(device), Some(_)) = (&mut self.device, self.waveform_cycles) {
+            device.start_trace(waveform_path(), TraceFormat::Vcd)?;
+            self.waveform = Some(WaveformCapture {
+                file: File::open(waveform_path())?,
+                parser: vcd::Parser::new(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Parses the part of the trace written since the last update and sends the captured cycles to the render loop
+    fn update_waveform(&mut self) -> Result<()> {
+        if let (Some(device), Some(waveform), Some(waveform_cycles)) =
+            (&mut self.device, &mut self.waveform, self.waveform_cycles)
+        {
+            device.flush_trace();
+            let mut text = String::new();
+            waveform.file.read_to_string(&mut text)?;
+            waveform.parser.feed(&text)?;
+
+            // Older changes are dropped as the trace is parsed so only the captured cycles are kept in memory
+            let vcd = waveform.parser.vcd_mut();
+            vcd.discard_before(device.clocks().saturating_sub(waveform_cycles));
+            self.results.lock().unwrap().waveform = Some(vcd.clone());
+
+            // The trace file is only read from the end, so it's started over once it grows large
+            if waveform.file.stream_position()? > MAX_WAVEFORM_FILE_SIZE {
+                self.start_waveform()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads back the framebuffer and sends it to the render loop once the read completes
+    /// The read is pipelined with execution unless blocking is requested.
+    fn update_snapshot(&mut self, is_blocking: bool) -> Result<()> {
+        if let Some(device) = &mut self.device {
+            let ticket = match self.fb_ticket {
+                Some(ticket) => ticket,
+                None => device.submit_read_framebuffer()?,
+            };
+
+            if is_blocking {
+                device.clock_until_idle()?;
+            }
+
+            match device.poll(ticket) {
+                Some(fb_data) => {
+                    self.fb_ticket = None;
+
+                    // Firmware that never presents is always shown live so older programs keep working
+                    let (fb_data, fb_addr) = match &self.presented_frame {
+                        Some(frame) if !self.is_live_framebuffer => {
+                            (frame.fb_data.clone(), frame.fb_addr)
+                        }
+                        _ => (fb_data, device.query_framebuffer_addr()?),
+                    };
+
+                    let snapshot = Snapshot {
+                        fb_data,
+                        fb_addr,
+                        frame_count: self.frame_count,
+                        clocks: device.clocks(),
+                        is_halted: device.query_is_halted()?,
+                    };
+                    self.results.lock().unwrap().snapshot = Some(snapshot);
+
+                    self.update_memory()?;
+                    self.update_cpu_state()?;
+                }
+                None => self.fb_ticket = Some(ticket),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the current memory view and sends it to the render loop
+    fn update_memory(&mut self) -> Result<()> {
+        if let (Some(device), Some((addr, len))) = (&mut self.device, self.memory_view) {
+            let mut data = vec![0; len];
+            device.read_memory(addr, &mut data)?;
+            self.results.lock().unwrap().memory = Some((addr, data));
+        }
+
+        Ok(())
+    }
+
+    /// Reads the cpu state and sends it to the render loop
+    fn update_cpu_state(&mut self) -> Result<()> {
+        if let (Some(device), true) = (&mut self.device, self.is_cpu_state_enabled) {
+            let cpu_state = device.read_cpu_state()?;
+            self.results.lock().unwrap().cpu_state = Some(cpu_state);
+        }
+
+        Ok(())
+    }
+}
+
+/// Simulation control object
+/// The device is executed on a dedicated thread so slow simulations never stall the render loop.
+pub struct Simulation {
+    commands: Sender<SimCommand>,
+    results: Arc<Mutex<SimResults>>,
+    events: Receiver<SimEvent>,
+    state: SimulationState,
+    speed: SimSpeed,
+    memory_size: usize,
+    fb_width: u32,
+    fb_height: u32,
+    fb_data: Vec<u8>,
+    fb_addr: u32,
+    is_live_framebuffer: bool,
+    frame_count: u32,
+    clocks: u64,
+    is_halted: bool,
+    waveform: Option<Vcd>,
+    clock_rate: f64,
+    rate_sample_time: Instant,
+    rate_sample_clocks: u64,
+    elf_path: Option<PathBuf>,
+    /// Modification time of the ELF file when it was last loaded
+    elf_modified_time: Option<SystemTime>,
+    /// Modification time observed during the previous check, used to wait until the file stops changing
+    elf_pending_time: Option<SystemTime>,
+    last_elf_check: Instant,
+    is_elf_reload_enabled: bool,
+    error: Option<String>,
+    symbols: SymbolTable,
+    memory: Option<(u32, Vec<u8>)>,
+    cpu_state: Option<CpuDebugState>,
+    lines: LineTable,
+    code_sections: Vec<CodeSection>,
+    /// Incremented whenever an ELF file is loaded so views of its contents can be rebuilt
+    elf_generation: u64,
+    breakpoints: Vec<u32>,
+    breakpoint_hit: Option<u32>,
+}
+
+/// Returns the modification time of a file if it's available
+fn query_modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).ok()?.modified().ok()
+}
+
+impl Simulation {
+    pub fn new() -> Result<Self> {
+        // Create a device so we can query the memory and framebuffer sizes
+        let mut device = Device::new();
+        let memory_size = device.query_memory_size()?;
+        let (fb_width, fb_height) = device.query_framebuffer_size()?;
+
+        let (command_sender, command_receiver) = mpsc::channel();
+        let (event_sender, event_receiver) = mpsc::channel();
+        let results = Arc::new(Mutex::new(SimResults::default()));
+
+        let worker = SimulationWorker {
+            backend: Backend::builtin(),
+            fb_size: (fb_width, fb_height),
+            device: None,
+            elf_path: None,
+            state: SimulationState::Running,
+            speed: SimSpeed::Max,
+            cycle_budget: 0.0,
+            last_budget_update: Instant::now(),
+            fb_ticket: None,
+            waveform_cycles: None,
+            waveform: None,
+            memory_view: None,
+            is_cpu_state_enabled: false,
+            breakpoints: Vec::new(),
+            is_live_framebuffer: false,
+            presented_frame: None,
+            frame_count: 0,
+            results: results.clone(),
+            events: event_sender,
+        };
+        thread::Builder::new()
+            .name("simulation".to_string())
+            .spawn(move || worker.run(command_receiver))?;
+
+        Ok(Self {
+            commands: command_sender,
+            results,
+            events: event_receiver,
+            state: SimulationState::Running,
+            speed: SimSpeed::Max,
+            memory_size,
+            fb_width,
+            fb_height,
+            fb_data: vec![0; (fb_width * fb_height * 4) as usize],
+            fb_addr: 0,
+            is_live_framebuffer: false,
+            frame_count: 0,
+            clocks: 0,
+            is_halted: false,
+            waveform: None,
+            clock_rate: 0.0,
+            rate_sample_time: Instant::now(),
+            rate_sample_clocks: 0,
+            elf_path: None,
+            elf_modified_time: None,
+            elf_pending_time: None,
+            last_elf_check: Instant::now(),
+            is_elf_reload_enabled: true,
+            error: None,
+            symbols: SymbolTable::default(),
+            memory: None,
+            cpu_state: None,
+            lines: LineTable::default(),
+            code_sections: Vec::new(),
+            elf_generation: 0,
+            breakpoints: Vec::new(),
+            breakpoint_hit: None,
+        })
+    }
+
+    fn send(&self, cmd: SimCommand) -> Result<()> {
+        self.commands.send(cmd)?;
+        Ok(())
+    }
+
+    /// Loads an ELF file from the provided path into the simulator
+    pub fn load_elf(&mut self, path: &impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        self.elf_modified_time = query_modified_time(path);
+        self.elf_pending_time = self.elf_modified_time;
+        self.elf_path = Some(path.to_path_buf());
+
+        // Invalid files are reported by the simulation thread when it fails to load them
+        let buffer = fs::read(path).unwrap_or_default();
+        self.symbols = SymbolTable::parse(&buffer).unwrap_or_default();
+        self.lines = LineTable::parse(&buffer).unwrap_or_default();
+        self.code_sections = elf::parse_code_sections(&buffer).unwrap_or_default();
+        self.elf_generation += 1;
+
+        self.send(SimCommand::LoadElf(path.to_path_buf()))
+    }
+
+    /// Returns the symbols defined by the current ELF file
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Returns the source lines that the current ELF file's instructions were compiled from
+    pub fn lines(&self) -> &LineTable {
+        &self.lines
+    }
+
+    /// Returns the executable sections of the current ELF file
+    pub fn code_sections(&self) -> &[CodeSection] {
+        &self.code_sections
+    }
+
+    /// Returns a number that changes whenever an ELF file is loaded
+    pub fn elf_generation(&self) -> u64 {
+        self.elf_generation
+    }
+
+    /// Returns the addresses of the active breakpoints
+    pub fn breakpoints(&self) -> &[u32] {
+        &self.breakpoints
+    }
+
+    /// Adds a breakpoint at an address, or removes it if there's already one there
+    /// Breakpoints are implemented by the device so only a limited number can be active at once.
+    pub fn toggle_breakpoint(&mut self, addr: u32) -> Result<()> {
+        if let Some(idx) = self.breakpoints.iter().position(|bp_addr| *bp_addr == addr) {
+            self.breakpoints.remove(idx);
+        } else if self.breakpoints.len() < NUM_BREAKPOINTS {
+            self.breakpoints.push(addr);
+        } else {
+            return Err(format!("Only {} breakpoints are supported", NUM_BREAKPOINTS).into());
+        }
+
+        self.send(SimCommand::SetBreakpoints(self.breakpoints.clone()))
+    }
+
+    /// Removes every breakpoint
+    pub fn clear_breakpoints(&mut self) -> Result<()> {
+        self.breakpoints.clear();
+        self.send(SimCommand::SetBreakpoints(Vec::new()))
+    }
+
+    /// Returns the address of the most recently hit breakpoint and clears it
+    pub fn take_breakpoint_hit(&mut self) -> Option<u32> {
+        self.breakpoint_hit.take()
+    }
+
+    /// Reads the provided range of device memory along with each snapshot, or stops reading if None is provided
+    pub fn set_memory_view(&mut self, view: Option<(u32, usize)>) -> Result<()> {
+        if view.is_none() {
+            self.memory = None;
+        }
+        self.send(SimCommand::SetMemoryView(view))
+    }
+
+    /// Returns the address and contents of the most recent memory view read
+    pub fn memory(&self) -> Option<(u32, &[u8])> {
+        self.memory
+            .as_ref()
+            .map(|(addr, data)| (*addr, data.as_slice()))
+    }
+
+    /// Writes data into device memory at the provided address
+    pub fn write_memory(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        self.send(SimCommand::WriteMemory {
+            addr,
+            data: data.to_vec(),
+        })
+    }
+
+    /// Reads the cpu state along with each snapshot when enabled
+    pub fn set_cpu_state_capture(&mut self, is_enabled: bool) -> Result<()> {
+        if !is_enabled {
+            self.cpu_state = None;
+        }
+        self.send(SimCommand::SetCpuStateCapture(is_enabled))
+    }
+
+    /// Returns the most recently read cpu state
+    pub fn cpu_state(&self) -> Option<&CpuDebugState> {
+        self.cpu_state.as_ref()
+    }
+
+    /// Returns the path of the current ELF file if there is one
+    pub fn elf_path(&self) -> Option<&Path> {
+        self.elf_path.as_deref()
+    }
+
+    /// Returns true if the current ELF file is reloaded automatically when it changes on disk
+    pub fn is_elf_reload_enabled(&self) -> bool {
+        self.is_elf_reload_enabled
+    }
+
+    /// Enables or disables automatically reloading the current ELF file when it changes on disk
+    pub fn set_elf_reload_enabled(&mut self, is_enabled: bool) {
+        self.is_elf_reload_enabled = is_enabled;
+    }
+
+    /// Resets the simulator and reloads the current ELF file if it changed on disk since it was loaded
+    /// Returns true if the file was reloaded. Files are only reloaded once they stop changing between checks so
+    /// partially written files aren't loaded while a build is still writing them.
+    pub fn reload_elf_if_changed(&mut self) -> Result<bool> {
+        let now = Instant::now();
+        if !self.is_elf_reload_enabled || (now - self.last_elf_check) < ELF_CHECK_PERIOD {
+            return Ok(false);
+        }
+        self.last_elf_check = now;
+
+        let path = match &self.elf_path {
+            Some(path) => path.clone(),
+            None => return Ok(false),
+        };
+        let modified_time = query_modified_time(&path);
+        let is_stable = modified_time == self.elf_pending_time;
+        self.elf_pending_time = modified_time;
+
+        // Missing files are ignored since some builds delete the output before writing it again
+        if modified_time.is_none() || !is_stable || (modified_time == self.elf_modified_time) {
+            return Ok(false);
+        }
+
+        self.load_elf(&path)?;
+        Ok(true)
+    }
+
+    /// Returns the most recent error reported by the simulation thread and clears it
+    pub fn take_error(&mut self) -> Option<String> {
+        self.error.take()
+    }
+
+    /// Resets the simulator and reloads the current ELF file if there is one
+    pub fn reset(&mut self) -> Result<()> {
+        self.send(SimCommand::Reset)
+    }
+
+    /// Executes a single step of the simulator while it's paused
+    /// The number of cycles in a step is based on the current speed.
+    pub fn step(&mut self) -> Result<()> {
+        self.send(SimCommand::Step)
+    }
+
+    /// Replaces the hardware backend used by the device and resets it
+    /// The framebuffer size is only queried at startup so hardware with a different size is rejected with an error.
+    pub fn set_backend(&mut self, backend: Arc<Backend>) -> Result<()> {
+        self.send(SimCommand::SetBackend(backend))
+    }
+
+    /// Returns the speed the device is executing at
+    pub fn speed(&self) -> SimSpeed {
+        self.speed
+    }
+
+    /// Sets the speed the device executes at
+    pub fn set_speed(&mut self, speed: SimSpeed) -> Result<()> {
+        self.speed = speed;
+        self.send(SimCommand::SetSpeed(speed))
+    }
+
+    /// Returns the size in bytes of the device memory
+    pub fn memory_size(&self) -> usize {
+        self.memory_size
+    }
+
+    /// Returns the width of the framebuffer image inside the device
+    pub fn framebuffer_width(&self) -> u32 {
+        self.fb_width
+    }
+
+    /// Returns the height of the framebuffer image inside the device
+    pub fn framebuffer_height(&self) -> u32 {
+        self.fb_height
+    }
+
+    /// Returns the size in bytes of the framebuffer image inside the device
+    pub fn framebuffer_size(&self) -> usize {
+        (self.fb_width * self.fb_height * 4) as usize
+    }
+
+    /// Returns the framebuffer data from the most recent snapshot
+    pub fn framebuffer_data(&self) -> &[u8] {
+        &self.fb_data
+    }
+
+    /// Returns the device memory address that the most recent framebuffer snapshot was read from
+    pub fn framebuffer_addr(&self) -> u32 {
+        self.fb_addr
+    }
+
+    /// Returns true if the framebuffer is shown as it's being drawn instead of at present boundaries
+    pub fn is_live_framebuffer(&self) -> bool {
+        self.is_live_framebuffer
+    }
+
+    /// Shows the framebuffer as it's being drawn, or only the frames the firmware has presented
+    /// Firmware that never presents a frame is always shown live.
+    pub fn set_live_framebuffer(&mut self, is_enabled: bool) -> Result<()> {
+        self.is_live_framebuffer = is_enabled;
+        self.send(SimCommand::SetLiveFramebuffer(is_enabled))
+    }
+
+    /// Returns the number of frames the firmware has presented in the most recent snapshot
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// Returns the number of device cycles in the most recent snapshot
+    pub fn clocks(&self) -> u64 {
+        self.clocks
+    }
+
+    /// Captures a waveform of the last num_cycles of each update, or stops capturing if None is provided
+    pub fn set_waveform_capture(&mut self, num_cycles: Option<u64>) -> Result<()> {
+        if num_cycles.is_none() {
+            self.waveform = None;
+        }
+        self.send(SimCommand::SetWaveformCapture(num_cycles))
+    }
+
+    /// Returns the most recently captured waveform
+    pub fn waveform(&self) -> Option<&Vcd> {
+        self.waveform.as_ref()
+    }
+
+    /// Returns the measured number of device cycles executed per second
+    pub fn clock_rate(&self) -> f64 {
+        self.clock_rate
+    }
+
+    /// Returns true if the device was halted in the most recent snapshot
+    pub fn is_halted(&self) -> bool {
+        self.is_halted
+    }
+
+    /// Pauses the simulator so that the device stops executing
+    pub fn pause(&mut self) -> Result<()> {
+        self.state = SimulationState::Paused;
+        self.send(SimCommand::Pause)
+    }
+
+    /// Resumes the simulator so that the device continues executing
+    pub fn resume(&mut self) -> Result<()> {
+        self.state = SimulationState::Running;
+        self.send(SimCommand::Resume)
+    }
+
+    /// If the simulator is running, pause it. Otherwise, resume it
+    pub fn toggle(&mut self) -> Result<()> {
+        if self.is_running() {
+            self.pause()
+        } else {
+            self.resume()
+        }
+    }
+
+    /// Returns true if the simulator is running
+    pub fn is_running(&self) -> bool {
+        self.state == SimulationState::Running
+    }
+
+    /// Receives any results from the simulation thread and returns the latest framebuffer data via the provided
+    /// slice. The slice should be large enough to hold the framebuffer data from the device.
+    /// This should be called once per rendered frame.
+    pub fn update(&mut self, fb_data: &mut [u8]) {
+        if self.is_running() && matches!(self.speed, SimSpeed::CyclesPerFrame(_)) {
+            // This only fails if the simulation thread has exited, which is reported by the other commands
+            let _ = self.send(SimCommand::Frame);
+        }
+
+        {
+            let mut results = self.results.lock().unwrap();
+            if let Some(snapshot) = results.snapshot.take() {
+                self.fb_data = snapshot.fb_data;
+                self.fb_addr = snapshot.fb_addr;
+                self.frame_count = snapshot.frame_count;
+                self.clocks = snapshot.clocks;
+                self.is_halted = snapshot.is_halted;
+            }
+            if let Some(waveform) = results.waveform.take() {
+                self.waveform = Some(waveform);
+            }
+            if let Some(memory) = results.memory.take() {
+                self.memory = Some(memory);
+            }
+            if let Some(cpu_state) = results.cpu_state.take() {
+                self.cpu_state = Some(cpu_state);
+            }
+        }
+
+        loop {
+            match self.events.try_recv() {
+                Ok(SimEvent::BreakpointHit(addr)) => {
+                    // Breakpoints pause the simulation thread so the local state needs to match
+                    self.state = SimulationState::Paused;
+                    self.breakpoint_hit = Some(addr);
+                }
+                Ok(SimEvent::MemorySizeChanged(memory_size)) => self.memory_size = memory_size,
+                Ok(SimEvent::Error(err)) => {
+                    // Errors pause the simulation thread so the local state needs to match
+                    self.state = SimulationState::Paused;
+                    println!("Simulation error: {}", err);
+                    self.error = Some(err);
+                }
+                Err(_) => break,
+            }
+        }
+
+        let now = Instant::now();
+        let elapsed = now - self.rate_sample_time;
+        if elapsed >= CLOCK_RATE_SAMPLE_PERIOD {
+            // Resets restart the clock count so they're treated as if no cycles were executed
+            let num_cycles = self.clocks.saturating_sub(self.rate_sample_clocks);
+            self.clock_rate = num_cycles as f64 / elapsed.as_secs_f64();
+            self.rate_sample_time = now;
+            self.rate_sample_clocks = self.clocks;
+        }
+
+        fb_data.copy_from_slice(&self.fb_data);
+    }
+}
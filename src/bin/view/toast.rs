@@ -0,0 +1,75 @@
+use imgui::{im_str, Condition, Ui, WindowFlags};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How long each message stays on screen
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+
+/// Largest number of messages displayed at once
+/// The oldest messages are removed early when new ones arrive.
+const MAX_TOASTS: usize = 4;
+
+/// Distance between the messages and the corner of the window
+const TOAST_MARGIN: f32 = 10.0;
+
+/// Short status messages that are briefly displayed in the corner of the window
+pub struct Toasts {
+    messages: VecDeque<(String, Instant)>,
+}
+
+impl Toasts {
+    pub fn new() -> Self {
+        Self {
+            messages: VecDeque::new(),
+        }
+    }
+
+    /// Displays a new message
+    pub fn push(&mut self, message: impl Into<String>) {
+        if self.messages.len() == MAX_TOASTS {
+            self.messages.pop_front();
+        }
+        self.messages.push_back((message.into(), Instant::now()));
+    }
+
+    /// Draws any messages that haven't expired yet
+    pub fn draw(&mut self, ui: &Ui) {
+        let now = Instant::now();
+        while let Some((_, time)) = self.messages.front() {
+            if (now - *time) < TOAST_DURATION {
+                break;
+            }
+            self.messages.pop_front();
+        }
+
+        if self.messages.is_empty() {
+            return;
+        }
+
+        let display_size = ui.io().display_size;
+        imgui::Window::new(im_str!("toasts"))
+            .position(
+                [
+                    display_size[0] - TOAST_MARGIN,
+                    display_size[1] - TOAST_MARGIN,
+                ],
+                Condition::Always,
+            )
+            .position_pivot([1.0, 1.0])
+            .bg_alpha(0.6)
+            .flags(
+                WindowFlags::NO_DECORATION
+                    | WindowFlags::ALWAYS_AUTO_RESIZE
+                    | WindowFlags::NO_MOVE
+                    | WindowFlags::NO_SAVED_SETTINGS
+                    | WindowFlags::NO_FOCUS_ON_APPEARING
+                    | WindowFlags::NO_NAV
+                    | WindowFlags::NO_INPUTS,
+            )
+            .build(ui, || {
+                for (message, _) in &self.messages {
+                    ui.text(message);
+                }
+            });
+    }
+}
@@ -0,0 +1,276 @@
+use devsim::vcd::{Signal, Vcd};
+use imgui::{im_str, ImString, MouseButton, Ui};
+use std::collections::HashSet;
+
+/// Names of the signals that are displayed before the user selects any
+/// These cover the cpu and command state machines, the cpu memory bus and the host interface fifos.
+const DEFAULT_SIGNALS: [&str; 9] = [
+    "r_state",
+    "r_pc",
+    "o_input_full",
+    "o_output_empty",
+    "w_cpu_mem_write_en",
+    "w_cpu_mem_req_size_out",
+    "w_cpu_mem_addr_out",
+    "w_cpu_mem_data_out",
+    "r_cpu_mem_data_in",
+];
+
+/// Waveforms are read from vcd traces, so they can't be captured when the hardware writes fst traces instead
+const IS_CAPTURE_SUPPORTED: bool = !cfg!(feature = "trace-fst");
+
+const DEFAULT_CAPTURE_CYCLES: u32 = 256;
+const MAX_CAPTURE_CYCLES: u32 = 65536;
+
+const NAME_COLUMN_WIDTH: f32 = 280.0;
+const ROW_HEIGHT: f32 = 20.0;
+const ROW_PADDING: f32 = 3.0;
+
+/// Zoom factor applied for each step of the mouse wheel
+const ZOOM_STEP: f64 = 1.25;
+
+const WAVE_COLOR: [f32; 4] = [0.3, 0.9, 0.3, 1.0];
+const CURSOR_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 1.0];
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+/// Returns the name of a signal without the scopes that contain it
+fn short_name(name: &str) -> &str {
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+/// Returns the name of a signal along with the scope that directly contains it
+/// This keeps signals with the same name in different modules distinguishable without taking up much space.
+fn display_name(name: &str) -> &str {
+    match name.rmatch_indices('.').nth(1) {
+        Some((idx, _)) => &name[(idx + 1)..],
+        None => name,
+    }
+}
+
+fn format_value(signal: &Signal, value: u64) -> String {
+    if signal.width == 1 {
+        format!("{}", value)
+    } else {
+        format!("{:#x}", value)
+    }
+}
+
+/// ImGui panel that displays signals from the most recently captured waveform
+pub struct WaveformPanel {
+    pub is_open: bool,
+    capture_cycles: u32,
+    /// Full names of the displayed signals, or None if the defaults haven't been selected yet
+    selected: Option<HashSet<String>>,
+    /// Horizontal scale in pixels per unit of trace time
+    zoom: f64,
+    /// Trace time displayed at the left edge of the waveform
+    scroll: f64,
+    /// Fits the entire waveform into the panel whenever a new one is captured
+    is_fit_enabled: bool,
+    cursor: Option<u64>,
+}
+
+impl WaveformPanel {
+    pub fn new() -> Self {
+        Self {
+            is_open: false,
+            capture_cycles: DEFAULT_CAPTURE_CYCLES,
+            selected: None,
+            zoom: 1.0,
+            scroll: 0.0,
+            is_fit_enabled: true,
+            cursor: None,
+        }
+    }
+
+    /// Returns the number of cycles that should be captured for the panel, or None if nothing needs to be captured
+    pub fn capture_cycles(&self) -> Option<u64> {
+        if self.is_open && IS_CAPTURE_SUPPORTED {
+            Some(self.capture_cycles as u64)
+        } else {
+            None
+        }
+    }
+
+    /// Draws the panel if it's open
+    pub fn draw(&mut self, ui: &Ui, waveform: Option<&Vcd>) {
+        if !self.is_open {
+            return;
+        }
+
+        let mut is_open = self.is_open;
+        imgui::Window::new(im_str!("Waveform"))
+            .size([900.0, 320.0], imgui::Condition::FirstUseEver)
+            .opened(&mut is_open)
+            .build(ui, || self.draw_contents(ui, waveform));
+        self.is_open = is_open;
+    }
+
+    fn draw_contents(&mut self, ui: &Ui, waveform: Option<&Vcd>) {
+        if !IS_CAPTURE_SUPPORTED {
+            ui.text("Waveforms can't be displayed when built with the trace-fst feature.");
+            return;
+        }
+
+        ui.set_next_item_width(200.0);
+        imgui::Slider::new(im_str!("Cycles"))
+            .range(1..=MAX_CAPTURE_CYCLES)
+            .flags(imgui::SliderFlags::LOGARITHMIC)
+            .build(ui, &mut self.capture_cycles);
+        ui.same_line(0.0);
+        if ui.button(im_str!("Fit"), [0.0, 0.0]) {
+            self.is_fit_enabled = true;
+        }
+        ui.same_line(0.0);
+        if ui.button(im_str!("Clear Cursor"), [0.0, 0.0]) {
+            self.cursor = None;
+        }
+
+        let waveform = match waveform {
+            Some(waveform) => waveform,
+            None => {
+                ui.text("Waiting for the simulation to capture a waveform...");
+                return;
+            }
+        };
+
+        let selected = self.selected.get_or_insert_with(|| {
+            waveform
+                .signals
+                .iter()
+                .filter(|signal| DEFAULT_SIGNALS.contains(&short_name(&signal.name)))
+                .map(|signal| signal.name.clone())
+                .collect()
+        });
+
+        if imgui::CollapsingHeader::new(im_str!("Signals")).build(ui) {
+            for signal in &waveform.signals {
+                let mut is_selected = selected.contains(&signal.name);
+                if ui.checkbox(&ImString::new(&signal.name), &mut is_selected) {
+                    if is_selected {
+                        selected.insert(signal.name.clone());
+                    } else {
+                        selected.remove(&signal.name);
+                    }
+                }
+            }
+        }
+
+        let rows = waveform
+            .signals
+            .iter()
+            .filter(|signal| selected.contains(&signal.name))
+            .collect::<Vec<_>>();
+
+        let origin = ui.cursor_screen_pos();
+        let avail = ui.content_region_avail();
+        let wave_x = origin[0] + NAME_COLUMN_WIDTH;
+        let wave_width = (avail[0] - NAME_COLUMN_WIDTH).max(1.0);
+        let height = (rows.len() as f32 * ROW_HEIGHT).max(ROW_HEIGHT);
+
+        if self.is_fit_enabled {
+            let duration = (waveform.end_time - waveform.start_time).max(1);
+            self.zoom = wave_width as f64 / duration as f64;
+            self.scroll = waveform.start_time as f64;
+        }
+
+        ui.invisible_button(im_str!("waveform"), [avail[0].max(1.0), height]);
+        let is_hovered = ui.is_item_hovered();
+        let mouse_pos = ui.io().mouse_pos;
+        let mouse_time = self.scroll + (mouse_pos[0] - wave_x) as f64 / self.zoom;
+
+        if is_hovered {
+            let wheel = ui.io().mouse_wheel;
+            if wheel != 0.0 {
+                // Zoom around the mouse so the time under it stays in place
+                self.zoom *= ZOOM_STEP.powf(wheel as f64);
+                self.scroll = mouse_time - (mouse_pos[0] - wave_x) as f64 / self.zoom;
+                self.is_fit_enabled = false;
+            }
+
+            if ui.is_item_clicked(MouseButton::Left) && (mouse_pos[0] >= wave_x) {
+                self.cursor = Some(mouse_time.max(0.0).round() as u64);
+            }
+        }
+
+        // Dragging pans the waveform while a click without dragging only moves the cursor
+        if ui.is_item_active() && ui.is_mouse_dragging(MouseButton::Left) {
+            self.scroll -= ui.io().mouse_delta[0] as f64 / self.zoom;
+            self.is_fit_enabled = false;
+        }
+
+        let time_to_x = |time: u64| wave_x + ((time as f64 - self.scroll) * self.zoom) as f32;
+        let wave_end_x = wave_x + wave_width;
+
+        let draw_list = ui.get_window_draw_list();
+        for (row_idx, signal) in rows.iter().enumerate() {
+            let y_top = origin[1] + row_idx as f32 * ROW_HEIGHT + ROW_PADDING;
+            let y_bottom = origin[1] + (row_idx + 1) as f32 * ROW_HEIGHT - ROW_PADDING;
+
+            let name = display_name(&signal.name);
+            let label = match self.cursor.and_then(|cursor| signal.value_at(cursor)) {
+                Some(value) => format!("{} = {}", name, format_value(signal, value)),
+                None => name.to_owned(),
+            };
+            draw_list.add_text([origin[0], y_top], TEXT_COLOR, &label);
+
+            for (change_idx, (time, value)) in signal.changes.iter().enumerate() {
+                let next_time = signal
+                    .changes
+                    .get(change_idx + 1)
+                    .map(|(time, _)| *time)
+                    .unwrap_or(waveform.end_time);
+
+                let x_start = time_to_x(*time).max(wave_x);
+                let x_end = time_to_x(next_time).min(wave_end_x);
+                if x_end < x_start {
+                    continue;
+                }
+
+                if signal.width == 1 {
+                    let y = if *value != 0 { y_top } else { y_bottom };
+                    draw_list
+                        .add_line([x_start, y], [x_end, y], WAVE_COLOR)
+                        .build();
+                    if change_idx > 0 && time_to_x(*time) >= wave_x {
+                        draw_list
+                            .add_line([x_start, y_top], [x_start, y_bottom], WAVE_COLOR)
+                            .build();
+                    }
+                } else {
+                    draw_list
+                        .add_line([x_start, y_top], [x_end, y_top], WAVE_COLOR)
+                        .build();
+                    draw_list
+                        .add_line([x_start, y_bottom], [x_end, y_bottom], WAVE_COLOR)
+                        .build();
+                    if time_to_x(*time) >= wave_x {
+                        draw_list
+                            .add_line([x_start, y_top], [x_start, y_bottom], WAVE_COLOR)
+                            .build();
+                    }
+
+                    // Values are only labeled when there's enough room to read them
+                    let text = format_value(signal, *value);
+                    let text_size = ui.calc_text_size(&ImString::new(&text), false, -1.0);
+                    if (x_end - x_start) > (text_size[0] + 2.0 * ROW_PADDING) {
+                        draw_list.add_text([x_start + ROW_PADDING, y_top], TEXT_COLOR, &text);
+                    }
+                }
+            }
+        }
+
+        if let Some(cursor) = self.cursor {
+            let x = time_to_x(cursor);
+            if (x >= wave_x) && (x <= wave_end_x) {
+                draw_list
+                    .add_line([x, origin[1]], [x, origin[1] + height], CURSOR_COLOR)
+                    .build();
+            }
+        }
+
+        if is_hovered && (mouse_pos[0] >= wave_x) {
+            ui.tooltip_text(format!("Time: {}", mouse_time.max(0.0).round() as u64));
+        }
+    }
+}
@@ -1,19 +1,52 @@
+use crate::hw_params;
 use crate::protobridge::{
-    ProtoBridge, REG_IDX_DEV_EN, REG_IDX_FB_ADDR, REG_IDX_FB_CONFIG, WAIT_INFINITE_CYCLES,
+    reg_idx_dbg_breakpoint, reg_idx_dbg_x, reg_idx_fb_buffer, ProtoBridge, RegIdx, NUM_CPU_REGS,
+    REG_IDX_DBG_ACTIVE_CYCLES, REG_IDX_DBG_BREAK, REG_IDX_DBG_CPU_STATE, REG_IDX_DBG_CYCLE_HI,
+    REG_IDX_DBG_CYCLE_LO, REG_IDX_DBG_DATA_STALL_CYCLES, REG_IDX_DBG_FETCH_STALL_CYCLES,
+    REG_IDX_DBG_HALT, REG_IDX_DBG_INST, REG_IDX_DBG_INSTRET_HI, REG_IDX_DBG_INSTRET_LO,
+    REG_IDX_DBG_INST_COUNT, REG_IDX_DBG_PC, REG_IDX_DEV_EN, REG_IDX_DEV_FIFO_DEPTH,
+    REG_IDX_DEV_MEM_SIZE, REG_IDX_FB_ADDR, REG_IDX_FB_BUFFER_BASE, REG_IDX_FB_CONFIG,
+    REG_IDX_FB_FRAME_COUNT, REG_IDX_FB_FRONT, REG_IDX_FB_PRESENT, REG_IDX_MEM_WAIT_STATES,
+    WAIT_INFINITE_CYCLES,
+};
+pub use crate::protobridge::{
+    Backend, ProtoBridgeError, ProtoBridgeErrorKind, Ticket, TraceFormat, NUM_BREAKPOINTS,
+    NUM_FB_BUFFERS,
 };
 use goblin::Object;
 use std::error;
 use std::fmt;
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+/// Size in bytes of the memory that's exposed by the device this library was built with
+/// Use Device::query_memory_size when the hardware may have been built separately, like after a hot reload.
+pub const MEMORY_SIZE: usize = hw_params::MEM_SIZE as usize;
+
+/// Address that firmware writes to when it finishes drawing a frame
+/// The cpu accesses the device registers through the addresses directly after memory.
+pub const FB_PRESENT_ADDR: u32 = MEMORY_SIZE as u32 + REG_IDX_FB_PRESENT.offset();
+
+/// Address that firmware reads the number of presented frames from
+pub const FB_FRAME_COUNT_ADDR: u32 = MEMORY_SIZE as u32 + REG_IDX_FB_FRAME_COUNT.offset();
+
+/// Address that firmware writes a buffer index to in order to display that buffer
+pub const FB_FRONT_ADDR: u32 = MEMORY_SIZE as u32 + REG_IDX_FB_FRONT.offset();
+
+/// Address of the register that holds the address of the first framebuffer
+/// The registers for the other buffers follow it.
+pub const FB_BUFFER_ADDR: u32 = MEMORY_SIZE as u32 + REG_IDX_FB_BUFFER_BASE.offset();
+
 /// Enumeration of possible device error types
 #[derive(Debug, Clone)]
 enum DeviceErrorKind {
     /// The provided buffer was too small to contain the result
     BufferTooSmall,
+    /// More breakpoints were requested than the device supports
+    TooManyBreakpoints,
 }
 
 /// A device error
@@ -36,15 +69,145 @@ impl fmt::Display for DeviceError {
 
 impl error::Error for DeviceError {}
 
+/// Selects how the host accesses device memory
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum AccessMode {
+    /// Memory is accessed by sending commands through the device's host interface
+    /// This costs simulated cycles but exercises the same path a real host would use.
+    Frontdoor,
+    /// Memory is accessed directly inside the simulation without clocking the device
+    Backdoor,
+}
+
+/// Execution state of the cpu as defined by cpu_state in hw/cpu.sv
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CpuState {
+    Idle,
+    Fetch,
+    FetchWait,
+    Decode,
+    Execute,
+    MemoryLoadWait,
+    MemoryLoadExecute,
+    MemoryStoreWait,
+    /// A state that isn't known by this version of the host
+    Unknown(u32),
+}
+
+impl From<u32> for CpuState {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => CpuState::Idle,
+            1 => CpuState::Fetch,
+            2 => CpuState::FetchWait,
+            3 => CpuState::Decode,
+            4 => CpuState::Execute,
+            5 => CpuState::MemoryLoadWait,
+            6 => CpuState::MemoryLoadExecute,
+            7 => CpuState::MemoryStoreWait,
+            _ => CpuState::Unknown(value),
+        }
+    }
+}
+
+/// Snapshot of the cpu's internal state
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CpuDebugState {
+    /// Values of x0-x31
+    pub regs: [u32; 32],
+    pub pc: u32,
+    /// The most recently fetched instruction
+    pub inst: u32,
+    pub state: CpuState,
+    /// Number of instructions executed since the device was reset
+    pub inst_count: u32,
+}
+
+/// Number of extra cycles the cpu waits on each memory access
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct MemoryWaitStates {
+    /// Wait states of each instruction fetch
+    pub fetch: u8,
+    /// Wait states of each data load or store
+    pub data: u8,
+}
+
+/// Counts of how the cpu spent its cycles since the device was reset
+/// Cycles spent idle or halted by the host aren't counted.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct StallStats {
+    /// Cycles spent out of the idle state
+    pub active_cycles: u32,
+    /// Cycles spent waiting for instruction fetches
+    pub fetch_stall_cycles: u32,
+    /// Cycles spent waiting for data loads and stores
+    pub data_stall_cycles: u32,
+}
+
+impl StallStats {
+    /// Returns the number of active cycles that weren't spent waiting on memory
+    /// The counters are 32 bits wide in hardware, so the difference is taken with wrapping arithmetic in case they
+    /// wrapped at different times.
+    pub fn execute_cycles(&self) -> u32 {
+        self.active_cycles
+            .wrapping_sub(self.fetch_stall_cycles)
+            .wrapping_sub(self.data_stall_cycles)
+    }
+}
+
+/// Values of the cpu's Zicsr performance counters
+/// Like the stall statistics, cycles spent idle or halted by the host aren't counted.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct PerfCounters {
+    /// Value of the cycle csr, which the time csr also reads since the device has no real time clock
+    pub cycles: u64,
+    /// Value of the instret csr
+    pub instret: u64,
+}
+
+impl PerfCounters {
+    /// Returns the average number of cycles per retired instruction, or None if no instructions were retired
+    pub fn cpi(&self) -> Option<f64> {
+        if self.instret > 0 {
+            Some(self.cycles as f64 / self.instret as f64)
+        } else {
+            None
+        }
+    }
+}
+
 pub struct Device {
     bridge: ProtoBridge,
+    access_mode: AccessMode,
+    /// Frame count returned by the most recent wait for a present
+    presented_frame_count: u32,
 }
 
 impl Device {
     /// Creates a new device
     pub fn new() -> Self {
-        let bridge = ProtoBridge::new();
-        Device { bridge }
+        Self::with_backend(Backend::builtin())
+    }
+
+    /// Creates a new device that's simulated by the provided backend
+    pub fn with_backend(backend: Arc<Backend>) -> Self {
+        let bridge = ProtoBridge::with_backend(backend);
+        Device {
+            bridge,
+            access_mode: AccessMode::Frontdoor,
+            presented_frame_count: 0,
+        }
+    }
+
+    /// Returns the mode used for memory accesses
+    pub fn access_mode(&self) -> AccessMode {
+        self.access_mode
+    }
+
+    /// Selects the mode used by all future memory accesses
+    /// Frontdoor accesses are used by default so the device sees the same traffic that a real host would generate.
+    pub fn set_access_mode(&mut self, mode: AccessMode) {
+        self.access_mode = mode;
     }
 
     /// Returns the number of clock cycles that have elapsed on the device
@@ -52,6 +215,85 @@ impl Device {
         self.bridge.clocks()
     }
 
+    /// Controls whether the device is clocked in large batches or one cycle at a time
+    /// Batching is enabled by default and should only be disabled for performance comparisons
+    pub fn set_clock_batching(&mut self, is_enabled: bool) {
+        self.bridge.set_batching_enabled(is_enabled);
+    }
+
+    /// Sets the largest number of bytes moved by each transfer command on the host interface
+    /// Larger frontdoor accesses are split into multiple commands. The default is the largest encodable size.
+    pub fn set_max_transfer_size(&mut self, size: u32) -> Result<()> {
+        self.bridge.set_max_transfer_size(size)
+    }
+
+    /// Writes the provided data into device memory at the specified address
+    pub fn write_memory(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        match self.access_mode {
+            AccessMode::Frontdoor => self.bridge.write_bytes(addr, data),
+            AccessMode::Backdoor => self.bridge.backdoor_write_bytes(addr, data),
+        }
+    }
+
+    /// Reads device memory at the specified address into the provided buffer
+    pub fn read_memory(&mut self, addr: u32, data: &mut [u8]) -> Result<()> {
+        match self.access_mode {
+            AccessMode::Frontdoor => self.bridge.read_bytes(addr, data, WAIT_INFINITE_CYCLES),
+            AccessMode::Backdoor => self.bridge.backdoor_read_bytes(addr, data),
+        }
+    }
+
+    /// Submits a read of device memory without waiting for the data to arrive
+    /// The data can be retrieved with poll() once the device has been clocked enough to return it.
+    pub fn submit_read_memory(&mut self, addr: u32, len: usize) -> Result<Ticket> {
+        match self.access_mode {
+            AccessMode::Frontdoor => self.bridge.submit_read(addr, len),
+            AccessMode::Backdoor => {
+                let mut data = vec![0; len];
+                self.bridge.backdoor_read_bytes(addr, &mut data)?;
+                Ok(self.bridge.submit_completed(data))
+            }
+        }
+    }
+
+    /// Returns the data for a previously submitted read if it has completed
+    pub fn poll(&mut self, ticket: Ticket) -> Option<Vec<u8>> {
+        self.bridge.poll(ticket)
+    }
+
+    /// Clocks the device for the specified number of cycles
+    /// Outstanding reads make progress while the device executes.
+    pub fn run(&mut self, num_cycles: usize) {
+        self.bridge.clock_cycles(num_cycles);
+    }
+
+    /// Clocks the device until every outstanding command has completed
+    pub fn clock_until_idle(&mut self) -> Result<()> {
+        self.bridge.clock_until_idle()
+    }
+
+    /// Begins capturing a waveform trace of the device into the file at the provided path
+    /// The trace is restricted to the most recent trace window if one was set.
+    pub fn start_trace(&mut self, path: impl AsRef<Path>, format: TraceFormat) -> Result<()> {
+        self.bridge.start_trace(path.as_ref(), format)
+    }
+
+    /// Writes everything traced so far to the trace file so it can be read while the trace continues
+    pub fn flush_trace(&mut self) {
+        self.bridge.flush_trace()
+    }
+
+    /// Stops the current waveform trace
+    pub fn stop_trace(&mut self) -> Result<()> {
+        self.bridge.stop_trace()
+    }
+
+    /// Restricts traces started after this call to the cycles between start_cycle and end_cycle
+    /// This keeps traces of long running programs small when only a specific window is interesting.
+    pub fn trace_window(&mut self, start_cycle: u64, end_cycle: u64) {
+        self.bridge.set_trace_window(Some(start_cycle..end_cycle));
+    }
+
     /// Loads an elf into device memory from the path provided
     pub fn load_elf(&mut self, elf_path: impl AsRef<Path>) -> Result<()> {
         let buffer = fs::read(elf_path)?;
@@ -63,7 +305,7 @@ impl Device {
                             ..(header.p_offset + header.p_filesz) as usize];
                         let program_addr = header.p_paddr as u32;
 
-                        self.bridge.write_bytes(program_addr, program_data);
+                        self.write_memory(program_addr, program_data)?;
 
                         println!(
                             "Uploaded {} byte loadable program segment to address {:#06x} in device memory",
@@ -87,7 +329,9 @@ impl Device {
     /// This allows the device to begin executing any code that was previously loaded into memory
     pub fn enable(&mut self) {
         // Enable the device
-        self.bridge.write_reg(REG_IDX_DEV_EN, 1);
+        self.bridge
+            .write_reg(REG_IDX_DEV_EN, 1)
+            .expect("Failed to write device enable register!");
     }
 
     /// Disables the device
@@ -95,7 +339,9 @@ impl Device {
     /// Execution can be resumed later with enable()
     pub fn disable(&mut self) {
         // Disable the device
-        self.bridge.write_reg(REG_IDX_DEV_EN, 0);
+        self.bridge
+            .write_reg(REG_IDX_DEV_EN, 0)
+            .expect("Failed to write device enable register!");
     }
 
     /// Queries the device to determine if it's still executing
@@ -114,6 +360,195 @@ impl Device {
         }
     }
 
+    /// Reads the value of a device register
+    /// An error is returned if the index is outside of the device's register space
+    pub fn read_reg(&mut self, idx: u16) -> Result<u32> {
+        self.bridge
+            .read_reg(RegIdx::new(idx)?, WAIT_INFINITE_CYCLES)
+    }
+
+    /// Halts the cpu and returns the previous value of the halt register
+    /// Callers restore the returned value afterwards so a cpu that was already halted by the user stays halted.
+    fn halt_cpu(&mut self) -> Result<u32> {
+        let prev_halt = self
+            .bridge
+            .read_reg(REG_IDX_DBG_HALT, WAIT_INFINITE_CYCLES)?;
+        self.bridge.write_reg(REG_IDX_DBG_HALT, 1)?;
+
+        Ok(prev_halt)
+    }
+
+    /// Reads the internal state of the cpu through the debug registers
+    /// The cpu is halted while its state is read so every value comes from the same cycle.
+    pub fn read_cpu_state(&mut self) -> Result<CpuDebugState> {
+        let prev_halt = self.halt_cpu()?;
+
+        // Every read is submitted up front so they're pipelined through the host interface
+        let info_tickets = [
+            REG_IDX_DBG_PC,
+            REG_IDX_DBG_INST,
+            REG_IDX_DBG_CPU_STATE,
+            REG_IDX_DBG_INST_COUNT,
+        ]
+        .iter()
+        .map(|idx| self.bridge.submit_read_reg(*idx))
+        .collect::<Vec<_>>();
+        let reg_tickets = (0..NUM_CPU_REGS)
+            .map(|n| Ok(self.bridge.submit_read_reg(reg_idx_dbg_x(n)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut info = [0; 4];
+        for (value, ticket) in info.iter_mut().zip(info_tickets) {
+            let data = self.bridge.wait(ticket, WAIT_INFINITE_CYCLES)?;
+            *value = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        }
+        let mut regs = [0; 32];
+        for (value, ticket) in regs.iter_mut().zip(reg_tickets) {
+            let data = self.bridge.wait(ticket, WAIT_INFINITE_CYCLES)?;
+            *value = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        }
+
+        self.bridge.write_reg(REG_IDX_DBG_HALT, prev_halt)?;
+
+        Ok(CpuDebugState {
+            regs,
+            pc: info[0],
+            inst: info[1],
+            state: CpuState::from(info[2]),
+            inst_count: info[3],
+        })
+    }
+
+    /// Sets the addresses of the hardware breakpoints and disables any that aren't used
+    /// The cpu halts when it's about to fetch an instruction from one of the addresses.
+    pub fn set_breakpoints(&mut self, addrs: &[u32]) -> Result<()> {
+        if addrs.len() > NUM_BREAKPOINTS {
+            return Err(DeviceError::from(DeviceErrorKind::TooManyBreakpoints).into());
+        }
+
+        for n in 0..NUM_BREAKPOINTS {
+            // Bit 0 enables the breakpoint since instruction addresses are always word aligned
+            let value = addrs.get(n).map_or(0, |addr| (addr & !0x3) | 1);
+            self.bridge.write_reg(reg_idx_dbg_breakpoint(n)?, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the cpu is halted at a breakpoint
+    pub fn query_is_at_breakpoint(&mut self) -> Result<bool> {
+        Ok(self
+            .bridge
+            .read_reg(REG_IDX_DBG_BREAK, WAIT_INFINITE_CYCLES)?
+            != 0)
+    }
+
+    /// Resumes execution after the cpu halted at a breakpoint
+    /// The instruction at the breakpoint is executed instead of immediately halting again.
+    pub fn resume_from_breakpoint(&mut self) -> Result<()> {
+        self.bridge.write_reg(REG_IDX_DBG_BREAK, 0)?;
+        Ok(())
+    }
+
+    /// Sets the number of extra cycles the cpu waits on memory accesses
+    /// The wait states can only be changed while the cpu is idle, so they should be set before enabling the device.
+    pub fn set_memory_wait_states(&mut self, wait_states: MemoryWaitStates) -> Result<()> {
+        let value = ((wait_states.data as u32) << 8) | (wait_states.fetch as u32);
+        self.bridge.write_reg(REG_IDX_MEM_WAIT_STATES, value)?;
+        Ok(())
+    }
+
+    /// Queries the number of extra cycles the cpu waits on memory accesses
+    pub fn query_memory_wait_states(&mut self) -> Result<MemoryWaitStates> {
+        let value = self
+            .bridge
+            .read_reg(REG_IDX_MEM_WAIT_STATES, WAIT_INFINITE_CYCLES)?;
+
+        Ok(MemoryWaitStates {
+            fetch: value as u8,
+            data: (value >> 8) as u8,
+        })
+    }
+
+    /// Queries how many cycles the cpu spent waiting on memory since the device was reset
+    /// The cpu is halted while the counters are read so they all come from the same cycle.
+    pub fn query_stall_stats(&mut self) -> Result<StallStats> {
+        let prev_halt = self.halt_cpu()?;
+
+        let tickets = [
+            REG_IDX_DBG_ACTIVE_CYCLES,
+            REG_IDX_DBG_FETCH_STALL_CYCLES,
+            REG_IDX_DBG_DATA_STALL_CYCLES,
+        ]
+        .iter()
+        .map(|idx| self.bridge.submit_read_reg(*idx))
+        .collect::<Vec<_>>();
+
+        let mut counters = [0; 3];
+        for (value, ticket) in counters.iter_mut().zip(tickets) {
+            let data = self.bridge.wait(ticket, WAIT_INFINITE_CYCLES)?;
+            *value = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        }
+
+        self.bridge.write_reg(REG_IDX_DBG_HALT, prev_halt)?;
+
+        Ok(StallStats {
+            active_cycles: counters[0],
+            fetch_stall_cycles: counters[1],
+            data_stall_cycles: counters[2],
+        })
+    }
+
+    /// Reads the cpu's performance counters, which firmware reads with rdcycle and rdinstret
+    /// The cpu is halted while the counters are read so the low and high halves are consistent.
+    pub fn perf_counters(&mut self) -> Result<PerfCounters> {
+        let prev_halt = self.halt_cpu()?;
+
+        let tickets = [
+            REG_IDX_DBG_CYCLE_LO,
+            REG_IDX_DBG_CYCLE_HI,
+            REG_IDX_DBG_INSTRET_LO,
+            REG_IDX_DBG_INSTRET_HI,
+        ]
+        .iter()
+        .map(|idx| self.bridge.submit_read_reg(*idx))
+        .collect::<Vec<_>>();
+
+        let mut halves = [0; 4];
+        for (value, ticket) in halves.iter_mut().zip(tickets) {
+            let data = self.bridge.wait(ticket, WAIT_INFINITE_CYCLES)?;
+            *value = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as u64;
+        }
+
+        self.bridge.write_reg(REG_IDX_DBG_HALT, prev_halt)?;
+
+        Ok(PerfCounters {
+            cycles: (halves[1] << 32) | halves[0],
+            instret: (halves[3] << 32) | halves[2],
+        })
+    }
+
+    /// Queries the size in bytes of the device memory
+    /// The cpu sees the device registers at the addresses directly after memory. Transfers through the host interface
+    /// are checked against the queried size from then on.
+    pub fn query_memory_size(&mut self) -> Result<usize> {
+        let mem_size = self
+            .bridge
+            .read_reg(REG_IDX_DEV_MEM_SIZE, WAIT_INFINITE_CYCLES)?;
+        self.bridge.set_memory_size(mem_size);
+
+        Ok(mem_size as usize)
+    }
+
+    /// Queries the number of bytes that fit inside each of the device's host interface fifos
+    pub fn query_fifo_depth(&mut self) -> Result<usize> {
+        let fifo_depth = self
+            .bridge
+            .read_reg(REG_IDX_DEV_FIFO_DEPTH, WAIT_INFINITE_CYCLES)?;
+
+        Ok(fifo_depth as usize)
+    }
+
     /// Queries the framebuffer size from the device
     pub fn query_framebuffer_size(&mut self) -> Result<(u32, u32)> {
         let fb_config = self
@@ -126,6 +561,66 @@ impl Device {
         Ok((fb_width, fb_height))
     }
 
+    /// Queries the address of the front buffer in device memory
+    /// This is the buffer that's displayed, while the firmware may be drawing into any of the others.
+    pub fn query_framebuffer_addr(&mut self) -> Result<u32> {
+        let fb_addr = self
+            .bridge
+            .read_reg(REG_IDX_FB_ADDR, WAIT_INFINITE_CYCLES)?;
+
+        Ok(fb_addr)
+    }
+
+    /// Queries the index of the framebuffer that's currently displayed
+    pub fn query_front_buffer(&mut self) -> Result<usize> {
+        let front_buffer = self
+            .bridge
+            .read_reg(REG_IDX_FB_FRONT, WAIT_INFINITE_CYCLES)?;
+
+        Ok(front_buffer as usize)
+    }
+
+    /// Queries the address of each framebuffer in device memory
+    pub fn query_framebuffer_addrs(&mut self) -> Result<[u32; NUM_FB_BUFFERS]> {
+        let mut addrs = [0; NUM_FB_BUFFERS];
+        for (n, addr) in addrs.iter_mut().enumerate() {
+            *addr = self
+                .bridge
+                .read_reg(reg_idx_fb_buffer(n)?, WAIT_INFINITE_CYCLES)?;
+        }
+
+        Ok(addrs)
+    }
+
+    /// Queries the number of frames the firmware has presented since the device was reset
+    pub fn query_frame_count(&mut self) -> Result<u32> {
+        let frame_count = self
+            .bridge
+            .read_reg(REG_IDX_FB_FRAME_COUNT, WAIT_INFINITE_CYCLES)?;
+
+        Ok(frame_count)
+    }
+
+    /// Clocks the device until the firmware presents a new frame or max_cycles have elapsed
+    /// Returns the new frame count, or None if no frame was presented in time or the device halted without presenting
+    /// one. Frames presented since the previous call are returned immediately. The framebuffer should be read right
+    /// after this returns since the firmware may begin drawing the next frame immediately.
+    pub fn wait_for_present(&mut self, max_cycles: u64) -> Result<Option<u32>> {
+        let start_clocks = self.clocks();
+
+        loop {
+            let frame_count = self.query_frame_count()?;
+            if frame_count != self.presented_frame_count {
+                self.presented_frame_count = frame_count;
+                return Ok(Some(frame_count));
+            }
+            // Halted firmware can't present any more frames until it's enabled again
+            if self.query_is_halted()? || ((self.clocks() - start_clocks) >= max_cycles) {
+                return Ok(None);
+            }
+        }
+    }
+
     /// Dumps a snapshot of the device framebuffer into the buffer provided by the caller
     /// The buffer should be large enough to hold the data contained within the framebuffer or an error will be returned
     pub fn dump_framebuffer(&mut self, dst: &mut [u8]) -> Result<()> {
@@ -134,17 +629,23 @@ impl Device {
 
         // Make sure the destination buffer is large enough
         if fb_num_pixels <= dst.len() {
-            let fb_addr = self
-                .bridge
-                .read_reg(REG_IDX_FB_ADDR, WAIT_INFINITE_CYCLES)?;
+            let fb_addr = self.query_framebuffer_addr()?;
 
-            self.bridge.read_bytes(fb_addr, dst, WAIT_INFINITE_CYCLES)?;
+            self.read_memory(fb_addr, dst)?;
 
             Ok(())
         } else {
             Err(DeviceError::from(DeviceErrorKind::BufferTooSmall).into())
         }
     }
+
+    /// Submits a read of the device framebuffer without waiting for the data to arrive
+    pub fn submit_read_framebuffer(&mut self) -> Result<Ticket> {
+        let (fb_width, fb_height) = self.query_framebuffer_size()?;
+        let fb_addr = self.query_framebuffer_addr()?;
+
+        self.submit_read_memory(fb_addr, (fb_width * fb_height * 4) as usize)
+    }
 }
 
 impl Default for Device {
@@ -152,3 +653,86 @@ impl Default for Device {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a device that runs the software model, which has no cpu so the cpu stays idle
+    fn model_device() -> Device {
+        Device::with_backend(Backend::model())
+    }
+
+    fn read_halt(device: &mut Device) -> u32 {
+        device
+            .bridge
+            .read_reg(REG_IDX_DBG_HALT, WAIT_INFINITE_CYCLES)
+            .unwrap()
+    }
+
+    #[test]
+    fn wait_for_present_stops_when_halted() {
+        let mut device = model_device();
+        assert!(device.query_is_halted().unwrap());
+
+        let max_cycles = 1_000_000;
+        assert_eq!(device.wait_for_present(max_cycles).unwrap(), None);
+        assert!(device.clocks() < max_cycles / 100);
+    }
+
+    #[test]
+    fn debug_reads_restore_halt() {
+        for prev_halt in [0, 1] {
+            let mut device = model_device();
+            device
+                .bridge
+                .write_reg(REG_IDX_DBG_HALT, prev_halt)
+                .unwrap();
+
+            let cpu_state = device.read_cpu_state().unwrap();
+            assert_eq!((cpu_state.pc, cpu_state.regs), (0, [0; 32]));
+            assert_eq!(read_halt(&mut device), prev_halt);
+
+            let perf_counters = device.perf_counters().unwrap();
+            assert_eq!((perf_counters.cycles, perf_counters.instret), (0, 0));
+            assert_eq!(read_halt(&mut device), prev_halt);
+
+            let stall_stats = device.query_stall_stats().unwrap();
+            assert_eq!(stall_stats.active_cycles, 0);
+            assert_eq!(stall_stats.fetch_stall_cycles, 0);
+            assert_eq!(stall_stats.data_stall_cycles, 0);
+            assert_eq!(read_halt(&mut device), prev_halt);
+        }
+    }
+
+    #[test]
+    fn set_breakpoints() {
+        let mut device = model_device();
+        let read_breakpoints = |device: &mut Device| {
+            (0..NUM_BREAKPOINTS)
+                .map(|n| {
+                    device
+                        .bridge
+                        .read_reg(reg_idx_dbg_breakpoint(n).unwrap(), WAIT_INFINITE_CYCLES)
+                        .unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Addresses are word aligned and enabled through bit 0, and the unused breakpoints are disabled
+        device.set_breakpoints(&[0x100, 0x203]).unwrap();
+        assert_eq!(read_breakpoints(&mut device), [0x101, 0x201, 0, 0]);
+
+        let err = device
+            .set_breakpoints(&[0; NUM_BREAKPOINTS + 1])
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DeviceError>().unwrap().kind,
+            DeviceErrorKind::TooManyBreakpoints
+        ));
+        assert_eq!(read_breakpoints(&mut device), [0x101, 0x201, 0, 0]);
+
+        device.set_breakpoints(&[]).unwrap();
+        assert_eq!(read_breakpoints(&mut device), [0; NUM_BREAKPOINTS]);
+    }
+}
@@ -0,0 +1,223 @@
+//! Disassembler for the RV32I instructions executed by the device cpu
+
+/// ABI names of the integer registers indexed by register number
+pub const ABI_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+fn reg(idx: u32) -> &'static str {
+    ABI_NAMES[(idx & 0x1f) as usize]
+}
+
+/// Sign extends the low `bits` bits of a value
+fn sign_extend(value: u32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    ((value << shift) as i32) >> shift
+}
+
+fn imm_i(inst: u32) -> i32 {
+    sign_extend(inst >> 20, 12)
+}
+
+fn imm_s(inst: u32) -> i32 {
+    sign_extend(((inst >> 25) << 5) | ((inst >> 7) & 0x1f), 12)
+}
+
+fn imm_b(inst: u32) -> i32 {
+    let imm = (((inst >> 31) & 0x1) << 12)
+        | (((inst >> 7) & 0x1) << 11)
+        | (((inst >> 25) & 0x3f) << 5)
+        | (((inst >> 8) & 0xf) << 1);
+    sign_extend(imm, 13)
+}
+
+fn imm_j(inst: u32) -> i32 {
+    let imm = (((inst >> 31) & 0x1) << 20)
+        | (((inst >> 12) & 0xff) << 12)
+        | (((inst >> 20) & 0x1) << 11)
+        | (((inst >> 21) & 0x3ff) << 1);
+    sign_extend(imm, 21)
+}
+
+/// Returns the address that a jal or branch instruction at the provided pc transfers control to
+/// Returns None for every other instruction, including jalr since its target depends on a register.
+pub fn branch_target(inst: u32, pc: u32) -> Option<u32> {
+    match inst & 0x7f {
+        0b1101111 => Some(pc.wrapping_add(imm_j(inst) as u32)),
+        0b1100011 => Some(pc.wrapping_add(imm_b(inst) as u32)),
+        _ => None,
+    }
+}
+
+/// Returns the name of a control and status register
+fn csr_name(csr: u32) -> String {
+    match csr {
+        0xc00 => "cycle".to_owned(),
+        0xc01 => "time".to_owned(),
+        0xc02 => "instret".to_owned(),
+        0xc80 => "cycleh".to_owned(),
+        0xc81 => "timeh".to_owned(),
+        0xc82 => "instreth".to_owned(),
+        _ => format!("{:#x}", csr),
+    }
+}
+
+/// Converts an instruction into assembly text in the style of objdump
+/// The pc is used to display the absolute targets of jumps and branches.
+pub fn disassemble(inst: u32, pc: u32) -> String {
+    let rd = reg(inst >> 7);
+    let rs1 = reg(inst >> 15);
+    let rs2 = reg(inst >> 20);
+    let funct3 = (inst >> 12) & 0x7;
+    let funct7 = inst >> 25;
+
+    let text = match inst & 0x7f {
+        0b0110111 => Some(format!("lui {}, {:#x}", rd, inst >> 12)),
+        0b0010111 => Some(format!("auipc {}, {:#x}", rd, inst >> 12)),
+        0b1101111 => Some(format!(
+            "jal {}, {:#x}",
+            rd,
+            pc.wrapping_add(imm_j(inst) as u32)
+        )),
+        0b1100111 if funct3 == 0 => Some(format!("jalr {}, {}({})", rd, imm_i(inst), rs1)),
+        0b1100011 => {
+            let name = match funct3 {
+                0b000 => Some("beq"),
+                0b001 => Some("bne"),
+                0b100 => Some("blt"),
+                0b101 => Some("bge"),
+                0b110 => Some("bltu"),
+                0b111 => Some("bgeu"),
+                _ => None,
+            };
+            name.map(|name| {
+                format!(
+                    "{} {}, {}, {:#x}",
+                    name,
+                    rs1,
+                    rs2,
+                    pc.wrapping_add(imm_b(inst) as u32)
+                )
+            })
+        }
+        0b0000011 => {
+            let name = match funct3 {
+                0b000 => Some("lb"),
+                0b001 => Some("lh"),
+                0b010 => Some("lw"),
+                0b100 => Some("lbu"),
+                0b101 => Some("lhu"),
+                _ => None,
+            };
+            name.map(|name| format!("{} {}, {}({})", name, rd, imm_i(inst), rs1))
+        }
+        0b0100011 => {
+            let name = match funct3 {
+                0b000 => Some("sb"),
+                0b001 => Some("sh"),
+                0b010 => Some("sw"),
+                _ => None,
+            };
+            name.map(|name| format!("{} {}, {}({})", name, rs2, imm_s(inst), rs1))
+        }
+        0b0010011 => {
+            let shamt = (inst >> 20) & 0x1f;
+            match (funct3, funct7) {
+                (0b000, _) => Some(format!("addi {}, {}, {}", rd, rs1, imm_i(inst))),
+                (0b010, _) => Some(format!("slti {}, {}, {}", rd, rs1, imm_i(inst))),
+                (0b011, _) => Some(format!("sltiu {}, {}, {}", rd, rs1, imm_i(inst))),
+                (0b100, _) => Some(format!("xori {}, {}, {}", rd, rs1, imm_i(inst))),
+                (0b110, _) => Some(format!("ori {}, {}, {}", rd, rs1, imm_i(inst))),
+                (0b111, _) => Some(format!("andi {}, {}, {}", rd, rs1, imm_i(inst))),
+                (0b001, 0b0000000) => Some(format!("slli {}, {}, {:#x}", rd, rs1, shamt)),
+                (0b101, 0b0000000) => Some(format!("srli {}, {}, {:#x}", rd, rs1, shamt)),
+                (0b101, 0b0100000) => Some(format!("srai {}, {}, {:#x}", rd, rs1, shamt)),
+                _ => None,
+            }
+        }
+        0b0110011 => {
+            let name = match (funct3, funct7) {
+                (0b000, 0b0000000) => Some("add"),
+                (0b000, 0b0100000) => Some("sub"),
+                (0b001, 0b0000000) => Some("sll"),
+                (0b010, 0b0000000) => Some("slt"),
+                (0b011, 0b0000000) => Some("sltu"),
+                (0b100, 0b0000000) => Some("xor"),
+                (0b101, 0b0000000) => Some("srl"),
+                (0b101, 0b0100000) => Some("sra"),
+                (0b110, 0b0000000) => Some("or"),
+                (0b111, 0b0000000) => Some("and"),
+                _ => None,
+            };
+            name.map(|name| format!("{} {}, {}, {}", name, rd, rs1, rs2))
+        }
+        0b0001111 => match funct3 {
+            0b000 => Some("fence".to_owned()),
+            0b001 => Some("fence.i".to_owned()),
+            _ => None,
+        },
+        0b1110011 => {
+            let csr = csr_name(inst >> 20);
+            let zimm = (inst >> 15) & 0x1f;
+            match funct3 {
+                0b000 => match inst {
+                    0x00000073 => Some("ecall".to_owned()),
+                    0x00100073 => Some("ebreak".to_owned()),
+                    0x10500073 => Some("wfi".to_owned()),
+                    _ => None,
+                },
+                0b001 => Some(format!("csrrw {}, {}, {}", rd, csr, rs1)),
+                0b010 => Some(format!("csrrs {}, {}, {}", rd, csr, rs1)),
+                0b011 => Some(format!("csrrc {}, {}, {}", rd, csr, rs1)),
+                0b101 => Some(format!("csrrwi {}, {}, {}", rd, csr, zimm)),
+                0b110 => Some(format!("csrrsi {}, {}, {}", rd, csr, zimm)),
+                0b111 => Some(format!("csrrci {}, {}, {}", rd, csr, zimm)),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+
+    text.unwrap_or_else(|| format!(".word {:#010x}", inst))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disassemble_instructions() {
+        let cases = [
+            (0x12345537, "lui a0, 0x12345"),
+            (0x00000097, "auipc ra, 0x0"),
+            (0xff010113, "addi sp, sp, -16"),
+            (0x00112623, "sw ra, 12(sp)"),
+            (0x00c12083, "lw ra, 12(sp)"),
+            (0xfff54503, "lbu a0, -1(a0)"),
+            (0x40b50533, "sub a0, a0, a1"),
+            (0x41f55593, "srai a1, a0, 0x1f"),
+            (0x00008067, "jalr zero, 0(ra)"),
+            (0x10500073, "wfi"),
+            (0xc0002573, "csrrs a0, cycle, zero"),
+            (0x00000000, ".word 0x00000000"),
+        ];
+        for (inst, text) in cases.iter() {
+            assert_eq!(disassemble(*inst, 0), *text, "{:#010x}", inst);
+        }
+    }
+
+    #[test]
+    fn branch_targets() {
+        // jal ra, -8
+        assert_eq!(disassemble(0xff9ff0ef, 0x100), "jal ra, 0xf8");
+        assert_eq!(branch_target(0xff9ff0ef, 0x100), Some(0xf8));
+
+        // bne a0, a1, +16
+        assert_eq!(disassemble(0x00b51863, 0x100), "bne a0, a1, 0x110");
+        assert_eq!(branch_target(0x00b51863, 0x100), Some(0x110));
+
+        assert_eq!(branch_target(0x00008067, 0x100), None);
+    }
+}
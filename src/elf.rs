@@ -0,0 +1,227 @@
+//! Debug information read from the ELF files that are executed on the device
+
+use goblin::elf::{section_header, sym, Elf};
+use goblin::Object;
+use std::fs;
+use std::path::Path;
+
+mod line;
+pub use line::{LineTable, SourceLocation};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Parses an ELF file that's already in memory
+fn parse_elf(buffer: &[u8]) -> Result<Elf<'_>> {
+    match Object::parse(buffer)? {
+        Object::Elf(elf) => Ok(elf),
+        _ => Err(goblin::error::Error::Malformed("Invalid elf specified".to_owned()).into()),
+    }
+}
+
+/// Returns the contents of the section with the provided name
+fn section_data<'a>(elf: &Elf, buffer: &'a [u8], name: &str) -> Option<&'a [u8]> {
+    elf.section_headers
+        .iter()
+        .find(|header| matches!(elf.shdr_strtab.get(header.sh_name), Some(Ok(header_name)) if header_name == name))
+        .and_then(|header| buffer.get(header.file_range()))
+}
+
+/// Instructions loaded into device memory by an ELF file
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CodeSection {
+    pub addr: u32,
+    pub data: Vec<u8>,
+}
+
+/// Reads the executable sections of the ELF file at the provided path
+pub fn load_code_sections(elf_path: impl AsRef<Path>) -> Result<Vec<CodeSection>> {
+    parse_code_sections(&fs::read(elf_path)?)
+}
+
+/// Reads the executable sections of an ELF file that's already in memory
+pub fn parse_code_sections(buffer: &[u8]) -> Result<Vec<CodeSection>> {
+    let elf = parse_elf(buffer)?;
+
+    let mut sections = Vec::new();
+    for header in &elf.section_headers {
+        if !header.is_executable() || (header.sh_type == section_header::SHT_NOBITS) {
+            continue;
+        }
+
+        let data = buffer
+            .get(header.file_range())
+            .ok_or_else(|| goblin::error::Error::Malformed("Invalid section range".to_owned()))?;
+        sections.push(CodeSection {
+            addr: header.sh_addr as u32,
+            data: data.to_vec(),
+        });
+    }
+
+    sections.sort_by_key(|section| section.addr);
+    Ok(sections)
+}
+
+/// A named location in device memory
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub addr: u32,
+    /// Size of the symbol in bytes, or zero if it's unknown
+    pub size: u32,
+}
+
+impl Symbol {
+    /// Returns true if the symbol covers the provided address
+    pub fn contains(&self, addr: u32) -> bool {
+        (addr >= self.addr) && ((addr - self.addr) < self.size)
+    }
+}
+
+/// Symbols defined by an ELF file, sorted by address
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    /// Creates a table from a list of symbols in any order
+    pub fn new(mut symbols: Vec<Symbol>) -> Self {
+        symbols.sort_by(|a, b| a.addr.cmp(&b.addr).then_with(|| a.name.cmp(&b.name)));
+        SymbolTable { symbols }
+    }
+
+    /// Reads the symbol table of the ELF file at the provided path
+    pub fn load(elf_path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&fs::read(elf_path)?)
+    }
+
+    /// Reads the symbol table of an ELF file that's already in memory
+    pub fn parse(buffer: &[u8]) -> Result<Self> {
+        let elf = parse_elf(buffer)?;
+
+        let mut symbols = Vec::new();
+        for elf_sym in elf.syms.iter() {
+            let is_named_location = matches!(
+                elf_sym.st_type(),
+                sym::STT_NOTYPE | sym::STT_OBJECT | sym::STT_FUNC
+            );
+            if !is_named_location || elf_sym.is_import() {
+                continue;
+            }
+
+            let name = match elf.strtab.get(elf_sym.st_name) {
+                Some(name) => name?,
+                None => continue,
+            };
+
+            // Local labels generated by the compiler aren't useful to people reading the program
+            if name.is_empty() || name.starts_with(".L") {
+                continue;
+            }
+
+            symbols.push(Symbol {
+                name: name.to_owned(),
+                addr: elf_sym.st_value as u32,
+                size: elf_sym.st_size as u32,
+            });
+        }
+
+        Ok(Self::new(symbols))
+    }
+
+    /// Returns all symbols in increasing address order
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// Returns the symbol with the provided name
+    pub fn find(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.name == name)
+    }
+
+    /// Returns the symbol that contains an address
+    /// Symbols without a size are treated as extending up to the next symbol, which is common for assembly labels.
+    pub fn lookup(&self, addr: u32) -> Option<&Symbol> {
+        let idx = self
+            .symbols
+            .partition_point(|symbol| symbol.addr <= addr)
+            .checked_sub(1)?;
+
+        // Prefer a sized symbol when several start at the same address
+        let start_addr = self.symbols[idx].addr;
+        let mut candidates = self.symbols[..=idx]
+            .iter()
+            .rev()
+            .take_while(|symbol| symbol.addr == start_addr);
+        let symbol = candidates
+            .clone()
+            .find(|symbol| symbol.size != 0)
+            .or_else(|| candidates.next())?;
+
+        if (symbol.size == 0) || symbol.contains(addr) {
+            Some(symbol)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_table() -> SymbolTable {
+        SymbolTable::new(vec![
+            Symbol {
+                name: "main".to_owned(),
+                addr: 0x100,
+                size: 0x20,
+            },
+            Symbol {
+                name: "_start".to_owned(),
+                addr: 0x0,
+                size: 0,
+            },
+            Symbol {
+                name: "loop".to_owned(),
+                addr: 0x100,
+                size: 0,
+            },
+            Symbol {
+                name: "buffer".to_owned(),
+                addr: 0x200,
+                size: 0x10,
+            },
+        ])
+    }
+
+    #[test]
+    fn find_symbols() {
+        let table = test_table();
+
+        assert_eq!(table.symbols()[0].name, "_start");
+        assert_eq!(table.find("buffer").map(|symbol| symbol.addr), Some(0x200));
+        assert_eq!(table.find("missing"), None);
+    }
+
+    #[test]
+    fn lookup_addresses() {
+        let table = test_table();
+        let lookup = |addr| table.lookup(addr).map(|symbol| symbol.name.as_str());
+
+        // Unsized symbols extend up to the next symbol
+        assert_eq!(lookup(0x0), Some("_start"));
+        assert_eq!(lookup(0xfc), Some("_start"));
+
+        assert_eq!(lookup(0x100), Some("main"));
+        assert_eq!(lookup(0x11c), Some("main"));
+        assert_eq!(lookup(0x120), None);
+        assert_eq!(lookup(0x20f), Some("buffer"));
+        assert_eq!(lookup(0x210), None);
+    }
+
+    #[test]
+    fn parse_invalid_elf() {
+        assert!(SymbolTable::parse(&[0; 16]).is_err());
+    }
+}
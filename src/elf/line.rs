@@ -0,0 +1,550 @@
+//! Parser for the DWARF .debug_line section, which maps instruction addresses to source lines
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+// Standard opcodes
+const DW_LNS_COPY: u8 = 1;
+const DW_LNS_ADVANCE_PC: u8 = 2;
+const DW_LNS_ADVANCE_LINE: u8 = 3;
+const DW_LNS_SET_FILE: u8 = 4;
+const DW_LNS_NEGATE_STMT: u8 = 6;
+const DW_LNS_CONST_ADD_PC: u8 = 8;
+const DW_LNS_FIXED_ADVANCE_PC: u8 = 9;
+
+// Extended opcodes
+const DW_LNE_END_SEQUENCE: u8 = 1;
+const DW_LNE_SET_ADDRESS: u8 = 2;
+const DW_LNE_DEFINE_FILE: u8 = 3;
+
+// Content types of DWARF 5 directory and file entries
+const DW_LNCT_PATH: u64 = 1;
+const DW_LNCT_DIRECTORY_INDEX: u64 = 2;
+
+// Attribute forms used by DWARF 5 directory and file entries
+const DW_FORM_BLOCK: u64 = 0x09;
+const DW_FORM_DATA1: u64 = 0x0b;
+const DW_FORM_DATA2: u64 = 0x05;
+const DW_FORM_DATA4: u64 = 0x06;
+const DW_FORM_DATA8: u64 = 0x07;
+const DW_FORM_DATA16: u64 = 0x1e;
+const DW_FORM_STRING: u64 = 0x08;
+const DW_FORM_STRP: u64 = 0x0e;
+const DW_FORM_UDATA: u64 = 0x0f;
+const DW_FORM_LINE_STRP: u64 = 0x1f;
+
+/// Little endian reader over a DWARF section
+#[derive(Clone)]
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or("Truncated DWARF line program")?;
+        let bytes = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    /// Reads an unsigned little endian value of up to 8 bytes
+    fn uint(&mut self, len: usize) -> Result<u64> {
+        Ok(self
+            .bytes(len)?
+            .iter()
+            .rev()
+            .fold(0, |value, byte| (value << 8) | *byte as u64))
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn uleb(&mut self) -> Result<u64> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= ((byte & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if (byte & 0x80) == 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn sleb(&mut self) -> Result<i64> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= ((byte & 0x7f) as i64) << shift;
+            }
+            shift += 7;
+            if (byte & 0x80) == 0 {
+                if (shift < 64) && ((byte & 0x40) != 0) {
+                    value |= -1 << shift;
+                }
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Reads a null terminated string
+    fn string(&mut self) -> Result<&'a str> {
+        let len = self.data[self.offset.min(self.data.len())..]
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or("Unterminated string in DWARF line program")?;
+        let string = std::str::from_utf8(self.bytes(len)?)?;
+        self.offset += 1;
+        Ok(string)
+    }
+}
+
+/// Reads a null terminated string at an offset into a string section
+fn section_string(section: &[u8], offset: u64) -> Result<&str> {
+    let mut reader = Reader::new(section);
+    reader.offset = offset as usize;
+    reader.string()
+}
+
+/// String sections that DWARF 5 line programs refer to
+#[derive(Clone, Copy, Default)]
+struct StringSections<'a> {
+    /// Contents of .debug_str
+    debug_str: &'a [u8],
+    /// Contents of .debug_line_str
+    debug_line_str: &'a [u8],
+}
+
+/// Reads a directory or file entry attribute from a DWARF 5 line program header
+/// Returns the string for string forms and the number for numeric forms.
+fn read_attribute<'a>(
+    reader: &mut Reader<'a>,
+    form: u64,
+    offset_size: usize,
+    strings: StringSections<'a>,
+) -> Result<(Option<&'a str>, u64)> {
+    Ok(match form {
+        DW_FORM_STRING => (Some(reader.string()?), 0),
+        DW_FORM_LINE_STRP => (
+            Some(section_string(
+                strings.debug_line_str,
+                reader.uint(offset_size)?,
+            )?),
+            0,
+        ),
+        DW_FORM_STRP => (
+            Some(section_string(
+                strings.debug_str,
+                reader.uint(offset_size)?,
+            )?),
+            0,
+        ),
+        DW_FORM_UDATA => (None, reader.uleb()?),
+        DW_FORM_DATA1 => (None, reader.uint(1)?),
+        DW_FORM_DATA2 => (None, reader.uint(2)?),
+        DW_FORM_DATA4 => (None, reader.uint(4)?),
+        DW_FORM_DATA8 => (None, reader.uint(8)?),
+        DW_FORM_DATA16 => {
+            reader.bytes(16)?;
+            (None, 0)
+        }
+        DW_FORM_BLOCK => {
+            let len = reader.uleb()? as usize;
+            reader.bytes(len)?;
+            (None, 0)
+        }
+        _ => return Err(format!("Unsupported DWARF form {:#x} in line program", form).into()),
+    })
+}
+
+/// Reads a DWARF 5 directory or file entry table and returns the path and directory index of each entry
+fn read_entry_table<'a>(
+    reader: &mut Reader<'a>,
+    offset_size: usize,
+    strings: StringSections<'a>,
+) -> Result<Vec<(&'a str, u64)>> {
+    let num_formats = reader.u8()?;
+    let mut formats = Vec::new();
+    for _ in 0..num_formats {
+        formats.push((reader.uleb()?, reader.uleb()?));
+    }
+
+    let num_entries = reader.uleb()?;
+    let mut entries = Vec::new();
+    for _ in 0..num_entries {
+        let mut path = "";
+        let mut dir_idx = 0;
+        for (content_type, form) in &formats {
+            let (string, value) = read_attribute(reader, *form, offset_size, strings)?;
+            match *content_type {
+                DW_LNCT_PATH => path = string.unwrap_or(""),
+                DW_LNCT_DIRECTORY_INDEX => dir_idx = value,
+                _ => {}
+            }
+        }
+        entries.push((path, dir_idx));
+    }
+
+    Ok(entries)
+}
+
+/// A row of the line table
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct Row {
+    addr: u32,
+    /// Index into the table's list of files
+    file: usize,
+    line: u32,
+    /// Marks the first address after a sequence of instructions, which has no source line
+    is_end_sequence: bool,
+}
+
+/// A line of source code
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SourceLocation<'a> {
+    pub path: &'a Path,
+    /// Line number starting from 1
+    pub line: u32,
+}
+
+/// Maps instruction addresses to the source lines they were compiled from
+#[derive(Debug, Clone, Default)]
+pub struct LineTable {
+    files: Vec<PathBuf>,
+    /// Rows sorted by address
+    rows: Vec<Row>,
+}
+
+impl LineTable {
+    /// Reads the line table of the ELF file at the provided path
+    pub fn load(elf_path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&fs::read(elf_path)?)
+    }
+
+    /// Reads the line table of an ELF file that's already in memory
+    /// Files without a .debug_line section produce an empty table.
+    pub fn parse(buffer: &[u8]) -> Result<Self> {
+        let elf = super::parse_elf(buffer)?;
+        let data = match super::section_data(&elf, buffer, ".debug_line") {
+            Some(data) => data,
+            None => return Ok(Self::default()),
+        };
+        let strings = StringSections {
+            debug_str: super::section_data(&elf, buffer, ".debug_str").unwrap_or(&[]),
+            debug_line_str: super::section_data(&elf, buffer, ".debug_line_str").unwrap_or(&[]),
+        };
+
+        Self::parse_section(data, strings)
+    }
+
+    /// Parses the contents of a .debug_line section
+    fn parse_section(data: &[u8], strings: StringSections) -> Result<Self> {
+        let mut table = LineTable::default();
+        let mut reader = Reader::new(data);
+        while !reader.is_empty() {
+            table.parse_unit(&mut reader, strings)?;
+        }
+
+        // End of sequence rows sort first so a sequence that starts where another ends takes priority
+        table.rows.sort_by(|a, b| {
+            a.addr
+                .cmp(&b.addr)
+                .then_with(|| b.is_end_sequence.cmp(&a.is_end_sequence))
+        });
+
+        Ok(table)
+    }
+
+    /// Parses the line program of a single compilation unit
+    fn parse_unit(&mut self, reader: &mut Reader, strings: StringSections) -> Result<()> {
+        let (unit_len, offset_size) = match reader.uint(4)? {
+            0xffff_ffff => (reader.uint(8)?, 8),
+            len => (len, 4),
+        };
+        let mut unit = Reader::new(reader.bytes(unit_len as usize)?);
+
+        let version = unit.uint(2)?;
+        if !(2..=5).contains(&version) {
+            return Err(format!("Unsupported DWARF line program version {}", version).into());
+        }
+        if version >= 5 {
+            // Address and segment selector sizes
+            unit.bytes(2)?;
+        }
+
+        let header_len = unit.uint(offset_size)? as usize;
+        let program_offset = unit.offset + header_len;
+
+        let min_inst_len = unit.u8()? as u32;
+        if version >= 4 {
+            // Maximum operations per instruction, which only matters for VLIW architectures
+            unit.u8()?;
+        }
+        let default_is_stmt = unit.u8()? != 0;
+        let line_base = unit.u8()? as i8 as i64;
+        let line_range = unit.u8()?;
+        let opcode_base = unit.u8()?;
+        let standard_opcode_lengths = unit.bytes((opcode_base as usize).saturating_sub(1))?;
+        if line_range == 0 {
+            return Err("Invalid line range in DWARF line program".into());
+        }
+
+        // Files are numbered from 1 before DWARF 5 and from 0 afterwards, so a placeholder keeps indices aligned
+        // with the file register of the line program.
+        let first_file = self.files.len();
+        if version >= 5 {
+            let dirs = read_entry_table(&mut unit, offset_size, strings)?;
+            for (path, dir_idx) in read_entry_table(&mut unit, offset_size, strings)? {
+                let dir = dirs.get(dir_idx as usize).map_or("", |(dir, _)| *dir);
+                self.files.push(Path::new(dir).join(path));
+            }
+        } else {
+            let mut dirs = vec![""];
+            loop {
+                let dir = unit.string()?;
+                if dir.is_empty() {
+                    break;
+                }
+                dirs.push(dir);
+            }
+
+            self.files.push(PathBuf::new());
+            loop {
+                let path = unit.string()?;
+                if path.is_empty() {
+                    break;
+                }
+                let dir_idx = unit.uleb()?;
+                // Modification time and length
+                unit.uleb()?;
+                unit.uleb()?;
+
+                let dir = dirs.get(dir_idx as usize).copied().unwrap_or("");
+                self.files.push(Path::new(dir).join(path));
+            }
+        }
+
+        unit.offset = program_offset;
+
+        let mut addr = 0u64;
+        let mut file = 1;
+        let mut line = 1i64;
+        let mut is_stmt = default_is_stmt;
+        while !unit.is_empty() {
+            let mut is_row = false;
+            let mut is_end_sequence = false;
+
+            let opcode = unit.u8()?;
+            if opcode >= opcode_base {
+                let adjusted = opcode - opcode_base;
+                addr += (adjusted / line_range) as u64 * min_inst_len as u64;
+                line += line_base + (adjusted % line_range) as i64;
+                is_row = true;
+            } else if opcode == 0 {
+                let len = unit.uleb()? as usize;
+                let mut extended = Reader::new(unit.bytes(len)?);
+                match extended.u8()? {
+                    DW_LNE_END_SEQUENCE => {
+                        is_row = true;
+                        is_end_sequence = true;
+                    }
+                    DW_LNE_SET_ADDRESS => addr = extended.uint(len - 1)?,
+                    DW_LNE_DEFINE_FILE => {
+                        let path = extended.string()?;
+                        self.files.push(PathBuf::from(path));
+                    }
+                    // Other extended opcodes don't affect the mapping
+                    _ => {}
+                }
+            } else {
+                match opcode {
+                    DW_LNS_COPY => is_row = true,
+                    DW_LNS_ADVANCE_PC => addr += unit.uleb()? * min_inst_len as u64,
+                    DW_LNS_ADVANCE_LINE => line += unit.sleb()?,
+                    DW_LNS_SET_FILE => file = unit.uleb()?,
+                    DW_LNS_CONST_ADD_PC => {
+                        addr += ((255 - opcode_base) / line_range) as u64 * min_inst_len as u64
+                    }
+                    DW_LNS_FIXED_ADVANCE_PC => addr += unit.uint(2)?,
+                    DW_LNS_NEGATE_STMT => is_stmt = !is_stmt,
+                    _ => {
+                        // Unused opcodes are skipped using the operand counts from the header
+                        for _ in 0..standard_opcode_lengths[opcode as usize - 1] {
+                            unit.uleb()?;
+                        }
+                    }
+                }
+            }
+
+            // Rows that aren't statements are in the middle of a line so they're left out of the table
+            if is_row && (is_stmt || is_end_sequence) && ((line > 0) || is_end_sequence) {
+                self.rows.push(Row {
+                    addr: addr as u32,
+                    file: first_file + file as usize,
+                    line: line as u32,
+                    is_end_sequence,
+                });
+            }
+
+            if is_end_sequence {
+                addr = 0;
+                file = 1;
+                line = 1;
+                is_stmt = default_is_stmt;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the table has no rows, which happens when the ELF file has no debug info
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Returns the source line that the instruction at an address was compiled from
+    pub fn lookup(&self, addr: u32) -> Option<SourceLocation<'_>> {
+        let idx = self
+            .rows
+            .partition_point(|row| row.addr <= addr)
+            .checked_sub(1)?;
+        let row = &self.rows[idx];
+        if row.is_end_sequence {
+            return None;
+        }
+
+        Some(SourceLocation {
+            path: self.files.get(row.file)?,
+            line: row.line,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wraps a line program header and program into a unit of the provided DWARF version
+    fn build_unit(version: u16, header: &[u8], program: &[u8]) -> Vec<u8> {
+        let mut unit: Vec<u8> = Vec::new();
+        unit.extend(&version.to_le_bytes());
+        if version >= 5 {
+            // Address size and segment selector size
+            unit.extend(&[4, 0]);
+        }
+        unit.extend(&(header.len() as u32).to_le_bytes());
+        unit.extend(header);
+        unit.extend(program);
+
+        let mut data = (unit.len() as u32).to_le_bytes().to_vec();
+        data.extend(unit);
+        data
+    }
+
+    /// Header fields shared by every test unit
+    /// min_inst_len = 4, max_ops = 1, default_is_stmt = 1, line_base = -5, line_range = 14, opcode_base = 13
+    const COMMON_HEADER: [u8; 18] = [4, 1, 1, 0xfb, 14, 13, 0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1];
+
+    /// Line program that maps 0x100 to line 10, 0x104-0x10c to line 12 and ends at 0x110
+    #[rustfmt::skip]
+    const PROGRAM: [u8; 16] = [
+        // set_address 0x100
+        0, 5, DW_LNE_SET_ADDRESS, 0x00, 0x01, 0x00, 0x00,
+        // advance_line 9, copy
+        DW_LNS_ADVANCE_LINE, 9, DW_LNS_COPY,
+        // special opcode: addr += 4, line += 2
+        13 + (2 + 5) + 14,
+        // advance_pc 3, end_sequence
+        DW_LNS_ADVANCE_PC, 3,
+        0, 1, DW_LNE_END_SEQUENCE,
+    ];
+
+    fn check_table(table: &LineTable, path: &Path) {
+        let lookup = |addr| table.lookup(addr).map(|location| location.line);
+
+        assert_eq!(lookup(0xfc), None);
+        assert_eq!(lookup(0x100), Some(10));
+        assert_eq!(lookup(0x104), Some(12));
+        assert_eq!(lookup(0x10c), Some(12));
+        assert_eq!(lookup(0x110), None);
+        assert_eq!(
+            table.lookup(0x100).map(|location| location.path),
+            Some(path)
+        );
+    }
+
+    #[test]
+    fn parse_dwarf4_line_program() {
+        let mut header = COMMON_HEADER.to_vec();
+        header.extend(b"src\0\0");
+        header.extend(b"main.rs\0\x01\0\0\0");
+
+        let data = build_unit(4, &header, &PROGRAM);
+        let table = LineTable::parse_section(&data, StringSections::default()).unwrap();
+        check_table(&table, Path::new("src/main.rs"));
+    }
+
+    #[test]
+    fn parse_dwarf5_line_program() {
+        let debug_line_str = b"/firmware\0main.rs\0";
+
+        let mut header = COMMON_HEADER.to_vec();
+        // Directories: path as line_strp
+        header.extend(&[1, DW_LNCT_PATH as u8, DW_FORM_LINE_STRP as u8, 1]);
+        header.extend(&0u32.to_le_bytes());
+        // Files: path as line_strp, directory index as udata
+        // Compilers repeat the primary source file as file 0 and file 1.
+        header.extend(&[
+            2,
+            DW_LNCT_PATH as u8,
+            DW_FORM_LINE_STRP as u8,
+            DW_LNCT_DIRECTORY_INDEX as u8,
+            DW_FORM_UDATA as u8,
+            2,
+        ]);
+        for _ in 0..2 {
+            header.extend(&10u32.to_le_bytes());
+            header.push(0);
+        }
+
+        let data = build_unit(5, &header, &PROGRAM);
+        let strings = StringSections {
+            debug_str: &[],
+            debug_line_str,
+        };
+        let table = LineTable::parse_section(&data, strings).unwrap();
+        check_table(&table, Path::new("/firmware/main.rs"));
+    }
+
+    #[test]
+    fn parse_truncated_line_program() {
+        let mut header = COMMON_HEADER.to_vec();
+        header.extend(b"\0\0");
+        let data = build_unit(4, &header, &PROGRAM);
+
+        assert!(
+            LineTable::parse_section(&data[..data.len() - 4], StringSections::default()).is_err()
+        );
+    }
+}
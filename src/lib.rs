@@ -1,3 +1,12 @@
 pub mod device;
+pub mod disasm;
+pub mod elf;
 mod protobridge;
+pub mod vcd;
 pub mod vkutil;
+
+/// Parameters that hw/top.sv was built with, which can be overridden through DEVSIM_* environment variables
+/// A device built from another version of the hardware can differ, so Device queries them at runtime instead.
+pub mod hw_params {
+    include!(concat!(env!("OUT_DIR"), "/hw_params.rs"));
+}
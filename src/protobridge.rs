@@ -1,9 +1,17 @@
+use crate::hw_params;
 use std::cmp;
-use std::collections::VecDeque;
-use std::ffi::c_void;
+use std::collections::{HashMap, VecDeque};
+use std::error;
+use std::ffi::{c_void, CString};
+use std::fmt;
+use std::fs::File;
 use std::io;
-use std::io::{Read, Write};
+use std::io::Write;
+use std::ops::Range;
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
 use std::ptr;
+use std::sync::Arc;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
@@ -11,44 +19,413 @@ type ProtoBridgeHandle = *mut c_void;
 
 pub const WAIT_INFINITE_CYCLES: usize = 0xffffffff;
 
+/// Largest value that can be encoded into the 30 bit address and size fields of a command
+pub const MAX_CMD_FIELD_VALUE: u32 = 0x3fffffff;
+
+/// Largest number of bytes that can be moved by a single transfer command
+pub const MAX_TRANSFER_SIZE: u32 = MAX_CMD_FIELD_VALUE;
+
+/// Address of the first register in the command address space
+/// Device memory always ends at or below this address.
+pub const REG_SPACE_BASE: u32 = 0x3ffff000;
+
+/// Number of 32 bit registers that are addressable through the register space
+pub const NUM_REGS: u16 = 1024;
+
+/// Enumeration of possible bridge error types
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ProtoBridgeErrorKind {
+    /// The requested transfer doesn't fit inside device memory
+    AddressOutOfRange { addr: u32, len: usize },
+    /// The transfer size can't be encoded into a command
+    InvalidTransferSize(u32),
+    /// The register index is outside of the register space
+    InvalidRegisterIndex(u16),
+    /// The index doesn't select one of the registers in an array like the breakpoints or framebuffer addresses
+    RegisterArrayIndexOutOfRange { n: usize, len: usize },
+    /// The register data doesn't fit into the size field of a command
+    RegisterDataTooLarge(u32),
+    /// The ticket doesn't belong to any outstanding request
+    UnknownTicket,
+    /// The simulation couldn't write a trace in the requested format to the provided path
+    /// Each hardware simulation only supports the trace format it was built with.
+    TraceFailed { path: PathBuf, format: TraceFormat },
+}
+
+/// A bridge error
+#[derive(Debug, Clone)]
+pub struct ProtoBridgeError {
+    kind: ProtoBridgeErrorKind,
+}
+
+impl ProtoBridgeError {
+    fn from(kind: ProtoBridgeErrorKind) -> Self {
+        ProtoBridgeError { kind }
+    }
+
+    /// Returns the type of error that occurred
+    pub fn kind(&self) -> &ProtoBridgeErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for ProtoBridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#?}", self.kind)
+    }
+}
+
+impl error::Error for ProtoBridgeError {}
+
+/// Index of a register inside the register space
+/// Register indices are checked on creation so they can always be encoded into a command.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RegIdx(u16);
+
+impl RegIdx {
+    /// Creates a register index if it's within the register space
+    pub fn new(idx: u16) -> std::result::Result<Self, ProtoBridgeError> {
+        if idx < NUM_REGS {
+            Ok(RegIdx(idx))
+        } else {
+            Err(ProtoBridgeError::from(
+                ProtoBridgeErrorKind::InvalidRegisterIndex(idx),
+            ))
+        }
+    }
+
+    /// Returns the offset of the register from the start of the register space
+    pub const fn offset(self) -> u32 {
+        (self.0 as u32) << 2
+    }
+
+    /// Returns the command address associated with the register
+    fn addr(self) -> u32 {
+        REG_SPACE_BASE | self.offset()
+    }
+}
+
+/// File formats that waveform traces can be written in
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TraceFormat {
+    /// Value change dump
+    Vcd,
+    /// Fast signal trace, which is much smaller than vcd for long captures
+    Fst,
+}
+
+const TRACE_FORMAT_VCD: u32 = 0;
+const TRACE_FORMAT_FST: u32 = 1;
+
+impl TraceFormat {
+    fn id(self) -> u32 {
+        match self {
+            TraceFormat::Vcd => TRACE_FORMAT_VCD,
+            TraceFormat::Fst => TRACE_FORMAT_FST,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum TraceState {
+    /// Waiting for the first cycle of the trace window
+    Pending,
+    Active,
+    /// The end of the trace window was reached
+    Finished,
+    /// The simulation was unable to begin writing the trace
+    Failed,
+}
+
+/// A waveform trace that was requested by the host
+struct Trace {
+    path: PathBuf,
+    format: TraceFormat,
+    window: Range<u64>,
+    state: TraceState,
+}
+
 #[repr(C)]
 struct DataStatus {
     is_input_full: u8,
     is_output_empty: u8,
 }
 
+#[cfg(feature = "verilator")]
 extern "C" {
     fn CreateProtoBridge(p_handle: *mut ProtoBridgeHandle) -> u32;
     fn DestroyProtoBridge(handle: ProtoBridgeHandle);
 
     fn QueryProtoBridgeDataStatus(handle: ProtoBridgeHandle) -> DataStatus;
     fn ClockProtoBridge(handle: ProtoBridgeHandle, p_input: *const u8, p_output: *mut u8);
+    fn ClockProtoBridgeBatch(
+        handle: ProtoBridgeHandle,
+        p_input: *const u8,
+        input_size: usize,
+        p_input_consumed: *mut usize,
+        p_output: *mut u8,
+        output_capacity: usize,
+        p_output_produced: *mut usize,
+        max_cycles: u64,
+    ) -> u64;
+
+    fn ReadProtoBridgeMemory(
+        handle: ProtoBridgeHandle,
+        addr: u32,
+        p_data: *mut u8,
+        size: usize,
+    ) -> usize;
+    fn WriteProtoBridgeMemory(
+        handle: ProtoBridgeHandle,
+        addr: u32,
+        p_data: *const u8,
+        size: usize,
+    ) -> usize;
+
+    fn StartProtoBridgeTrace(handle: ProtoBridgeHandle, p_path: *const c_char, format: u32) -> u32;
+    fn FlushProtoBridgeTrace(handle: ProtoBridgeHandle);
+    fn StopProtoBridgeTrace(handle: ProtoBridgeHandle);
+}
+
+// When the verilator model isn't available, the same entry points are provided by a software model of the hardware.
+// The model is also used by the unit tests as a reference for the command protocol.
+#[cfg(any(test, not(feature = "verilator")))]
+mod model;
+#[cfg(not(feature = "verilator"))]
+use model::{
+    ClockProtoBridge, ClockProtoBridgeBatch, CreateProtoBridge, DestroyProtoBridge,
+    FlushProtoBridgeTrace, QueryProtoBridgeDataStatus, ReadProtoBridgeMemory,
+    StartProtoBridgeTrace, StopProtoBridgeTrace, WriteProtoBridgeMemory,
+};
+
+/// Table of entry points used to drive a hardware simulation
+/// The built in backend is linked into the library, but other backends can be loaded from shared libraries at
+/// runtime so the hardware can be rebuilt without restarting the host.
+pub struct Backend {
+    create: unsafe extern "C" fn(p_handle: *mut ProtoBridgeHandle) -> u32,
+    destroy: unsafe extern "C" fn(handle: ProtoBridgeHandle),
+    query_data_status: unsafe extern "C" fn(handle: ProtoBridgeHandle) -> DataStatus,
+    clock: unsafe extern "C" fn(handle: ProtoBridgeHandle, p_input: *const u8, p_output: *mut u8),
+    clock_batch: unsafe extern "C" fn(
+        handle: ProtoBridgeHandle,
+        p_input: *const u8,
+        input_size: usize,
+        p_input_consumed: *mut usize,
+        p_output: *mut u8,
+        output_capacity: usize,
+        p_output_produced: *mut usize,
+        max_cycles: u64,
+    ) -> u64,
+    read_memory: unsafe extern "C" fn(
+        handle: ProtoBridgeHandle,
+        addr: u32,
+        p_data: *mut u8,
+        size: usize,
+    ) -> usize,
+    write_memory: unsafe extern "C" fn(
+        handle: ProtoBridgeHandle,
+        addr: u32,
+        p_data: *const u8,
+        size: usize,
+    ) -> usize,
+    start_trace:
+        unsafe extern "C" fn(handle: ProtoBridgeHandle, p_path: *const c_char, format: u32) -> u32,
+    flush_trace: unsafe extern "C" fn(handle: ProtoBridgeHandle),
+    stop_trace: unsafe extern "C" fn(handle: ProtoBridgeHandle),
+
+    /// Keeps the shared library loaded for as long as its entry points may be used
+    _library: Option<libloading::Library>,
+}
+
+impl Backend {
+    /// Returns the backend that was linked into the library at build time
+    pub fn builtin() -> Arc<Self> {
+        Arc::new(Backend {
+            create: CreateProtoBridge,
+            destroy: DestroyProtoBridge,
+            query_data_status: QueryProtoBridgeDataStatus,
+            clock: ClockProtoBridge,
+            clock_batch: ClockProtoBridgeBatch,
+            read_memory: ReadProtoBridgeMemory,
+            write_memory: WriteProtoBridgeMemory,
+            start_trace: StartProtoBridgeTrace,
+            flush_trace: FlushProtoBridgeTrace,
+            stop_trace: StopProtoBridgeTrace,
+            _library: None,
+        })
+    }
+
+    /// Returns a backend that runs the software model even when the verilator model is linked into the library
+    #[cfg(test)]
+    pub(crate) fn model() -> Arc<Self> {
+        Arc::new(Backend {
+            create: model::CreateProtoBridge,
+            destroy: model::DestroyProtoBridge,
+            query_data_status: model::QueryProtoBridgeDataStatus,
+            clock: model::ClockProtoBridge,
+            clock_batch: model::ClockProtoBridgeBatch,
+            read_memory: model::ReadProtoBridgeMemory,
+            write_memory: model::WriteProtoBridgeMemory,
+            start_trace: model::StartProtoBridgeTrace,
+            flush_trace: model::FlushProtoBridgeTrace,
+            stop_trace: model::StopProtoBridgeTrace,
+            _library: None,
+        })
+    }
+
+    /// Loads a backend from a protobridge shared library
+    pub fn load(path: &Path) -> Result<Arc<Self>> {
+        unsafe {
+            let library = libloading::Library::new(path)?;
+            Ok(Arc::new(Backend {
+                create: *library.get(b"CreateProtoBridge\0")?,
+                destroy: *library.get(b"DestroyProtoBridge\0")?,
+                query_data_status: *library.get(b"QueryProtoBridgeDataStatus\0")?,
+                clock: *library.get(b"ClockProtoBridge\0")?,
+                clock_batch: *library.get(b"ClockProtoBridgeBatch\0")?,
+                read_memory: *library.get(b"ReadProtoBridgeMemory\0")?,
+                write_memory: *library.get(b"WriteProtoBridgeMemory\0")?,
+                start_trace: *library.get(b"StartProtoBridgeTrace\0")?,
+                flush_trace: *library.get(b"FlushProtoBridgeTrace\0")?,
+                stop_trace: *library.get(b"StopProtoBridgeTrace\0")?,
+                _library: Some(library),
+            }))
+        }
+    }
+}
+
+impl fmt::Debug for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Backend")
+            .field("is_builtin", &self._library.is_none())
+            .finish()
+    }
+}
+
+/// Handle to a read request that was submitted to the device
+/// Results are returned in the order the requests were submitted.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Ticket(u64);
+
+/// A read request that hasn't received all of its data yet
+struct PendingRead {
+    ticket: Ticket,
+    len: usize,
+    /// Results of internal requests are dropped instead of being returned to a caller
+    is_discarded: bool,
 }
 
 pub struct ProtoBridge {
+    backend: Arc<Backend>,
     handle: ProtoBridgeHandle,
     clocks: u64,
     input_queue: VecDeque<u8>,
     output_queue: VecDeque<u8>,
+    batch_output: Vec<u8>,
+    is_batching_enabled: bool,
+    /// Size of device memory that transfers are checked against
+    memory_size: u32,
+    /// Largest number of bytes moved by each transfer command
+    max_transfer_size: u32,
+    next_ticket: u64,
+    pending_reads: VecDeque<PendingRead>,
+    completed_reads: HashMap<Ticket, Vec<u8>>,
+    has_unfenced_writes: bool,
+    trace: Option<Trace>,
+    trace_window: Option<Range<u64>>,
 }
 
 const CMD_ID_READ: u8 = 1;
 const CMD_ID_WRITE: u8 = 2;
 
-pub const REG_IDX_DEV_EN: u16 = 0;
-pub const REG_IDX_FB_ADDR: u16 = 1;
-pub const REG_IDX_FB_CONFIG: u16 = 2;
+pub const REG_IDX_DEV_EN: RegIdx = RegIdx(0);
+pub const REG_IDX_FB_ADDR: RegIdx = RegIdx(1);
+pub const REG_IDX_FB_CONFIG: RegIdx = RegIdx(2);
+pub const REG_IDX_DBG_PC: RegIdx = RegIdx(3);
+pub const REG_IDX_DBG_INST: RegIdx = RegIdx(4);
+pub const REG_IDX_DBG_CPU_STATE: RegIdx = RegIdx(5);
+pub const REG_IDX_DBG_INST_COUNT: RegIdx = RegIdx(6);
+pub const REG_IDX_DBG_HALT: RegIdx = RegIdx(7);
+pub const REG_IDX_DBG_BREAK: RegIdx = RegIdx(8);
+pub const REG_IDX_FB_PRESENT: RegIdx = RegIdx(13);
+pub const REG_IDX_FB_FRAME_COUNT: RegIdx = RegIdx(14);
+pub const REG_IDX_FB_FRONT: RegIdx = RegIdx(15);
+/// Index of the register that holds the address of the first framebuffer, the other buffers follow it
+pub const REG_IDX_FB_BUFFER_BASE: RegIdx = RegIdx(16);
+pub const REG_IDX_DEV_MEM_SIZE: RegIdx = RegIdx(64);
+pub const REG_IDX_DEV_FIFO_DEPTH: RegIdx = RegIdx(65);
+pub const REG_IDX_MEM_WAIT_STATES: RegIdx = RegIdx(66);
+pub const REG_IDX_DBG_ACTIVE_CYCLES: RegIdx = RegIdx(67);
+pub const REG_IDX_DBG_FETCH_STALL_CYCLES: RegIdx = RegIdx(68);
+pub const REG_IDX_DBG_DATA_STALL_CYCLES: RegIdx = RegIdx(69);
+pub const REG_IDX_DBG_CYCLE_LO: RegIdx = RegIdx(70);
+pub const REG_IDX_DBG_CYCLE_HI: RegIdx = RegIdx(71);
+pub const REG_IDX_DBG_INSTRET_LO: RegIdx = RegIdx(72);
+pub const REG_IDX_DBG_INSTRET_HI: RegIdx = RegIdx(73);
 
-impl io::Read for ProtoBridge {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let bytes_read = cmp::min(self.output_queue.len(), buf.len());
-        for (index, byte) in self.output_queue.drain(0..bytes_read).enumerate() {
-            buf[index] = byte;
-        }
-        Ok(bytes_read)
+/// Number of hardware breakpoints supported by the device
+pub const NUM_BREAKPOINTS: usize = 4;
+
+/// Number of cpu registers that can be read through the debug registers
+pub const NUM_CPU_REGS: usize = 32;
+
+/// Returns the index of register n in an array of len registers that starts at base
+fn reg_idx_in_array(
+    base: RegIdx,
+    n: usize,
+    len: usize,
+) -> std::result::Result<RegIdx, ProtoBridgeError> {
+    if n < len {
+        Ok(RegIdx(base.0 + n as u16))
+    } else {
+        Err(ProtoBridgeError::from(
+            ProtoBridgeErrorKind::RegisterArrayIndexOutOfRange { n, len },
+        ))
     }
 }
 
+/// Returns the index of the register that holds hardware breakpoint n
+pub fn reg_idx_dbg_breakpoint(n: usize) -> std::result::Result<RegIdx, ProtoBridgeError> {
+    reg_idx_in_array(RegIdx(9), n, NUM_BREAKPOINTS)
+}
+
+/// Number of framebuffers the device can flip between
+pub const NUM_FB_BUFFERS: usize = 2;
+
+/// Returns the index of the register that holds the address of framebuffer n
+pub fn reg_idx_fb_buffer(n: usize) -> std::result::Result<RegIdx, ProtoBridgeError> {
+    reg_idx_in_array(REG_IDX_FB_BUFFER_BASE, n, NUM_FB_BUFFERS)
+}
+
+/// Returns the index of the debug register that holds the value of cpu register x[n]
+pub fn reg_idx_dbg_x(n: usize) -> std::result::Result<RegIdx, ProtoBridgeError> {
+    reg_idx_in_array(RegIdx(32), n, NUM_CPU_REGS)
+}
+
+/// Splits a memory transfer into pieces of at most max_size bytes that can each be encoded into a single command
+/// Returns the device address and the range of the caller's buffer associated with each piece.
+fn split_transfer(
+    addr: u32,
+    len: usize,
+    memory_size: u32,
+    max_size: u32,
+) -> std::result::Result<Vec<(u32, Range<usize>)>, ProtoBridgeError> {
+    if (addr as u64 + len as u64) > memory_size as u64 {
+        return Err(ProtoBridgeError::from(
+            ProtoBridgeErrorKind::AddressOutOfRange { addr, len },
+        ));
+    }
+
+    // Zero sized commands still transfer a single byte in hardware so empty transfers don't produce any pieces
+    Ok((0..len)
+        .step_by(max_size as usize)
+        .map(|offset| {
+            let end = cmp::min(offset + max_size as usize, len);
+            (addr + offset as u32, offset..end)
+        })
+        .collect())
+}
+
 impl io::Write for ProtoBridge {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.input_queue.extend(buf);
@@ -62,15 +439,31 @@ impl io::Write for ProtoBridge {
 
 impl ProtoBridge {
     pub fn new() -> Self {
+        Self::with_backend(Backend::builtin())
+    }
+
+    /// Creates a bridge to a new simulation instance from the provided backend
+    pub fn with_backend(backend: Arc<Backend>) -> Self {
         let mut handle = ptr::null_mut();
         unsafe {
-            CreateProtoBridge(&mut handle);
+            (backend.create)(&mut handle);
         }
         ProtoBridge {
+            backend,
             handle,
             clocks: 0,
             input_queue: VecDeque::new(),
             output_queue: VecDeque::new(),
+            batch_output: Vec::new(),
+            is_batching_enabled: true,
+            memory_size: hw_params::MEM_SIZE,
+            max_transfer_size: MAX_TRANSFER_SIZE,
+            next_ticket: 0,
+            pending_reads: VecDeque::new(),
+            completed_reads: HashMap::new(),
+            has_unfenced_writes: false,
+            trace: None,
+            trace_window: None,
         }
     }
 
@@ -78,19 +471,46 @@ impl ProtoBridge {
         self.clocks
     }
 
+    /// Sets the size of device memory that transfers are checked against
+    /// This defaults to the memory size of the hardware this library was built with.
+    pub fn set_memory_size(&mut self, memory_size: u32) {
+        self.memory_size = memory_size;
+    }
+
+    /// Sets the largest number of bytes moved by each transfer command
+    /// Larger transfers are split into multiple commands. This defaults to MAX_TRANSFER_SIZE.
+    pub fn set_max_transfer_size(&mut self, size: u32) -> Result<()> {
+        if (size == 0) || (size > MAX_TRANSFER_SIZE) {
+            return Err(
+                ProtoBridgeError::from(ProtoBridgeErrorKind::InvalidTransferSize(size)).into(),
+            );
+        }
+        self.max_transfer_size = size;
+        Ok(())
+    }
+
+    /// Controls whether the device is clocked in batches inside protobridge or one cycle at a time
+    /// Batching is enabled by default. The unbatched path is mostly useful for performance comparisons.
+    pub fn set_batching_enabled(&mut self, is_enabled: bool) {
+        self.is_batching_enabled = is_enabled;
+    }
+
     // Internal helper functions
+    // Callers are responsible for keeping addr and size within MAX_CMD_FIELD_VALUE, any higher bits are discarded.
     fn build_cmd(id: u8, addr: u32, size: u32) -> u64 {
-        ((id as u64 & 0xf) << 60) | ((addr as u64 & 0x3fffffff) << 30) | (size as u64 & 0x3fffffff)
+        ((id as u64 & 0xf) << 60)
+            | (((addr & MAX_CMD_FIELD_VALUE) as u64) << 30)
+            | ((size & MAX_CMD_FIELD_VALUE) as u64)
     }
 
-    fn build_reg_cmd(id: u8, idx: u16, data: u32) -> u64 {
-        ((id as u64 & 0xf) << 60)
-            | ((((idx << 2) as u64 | 0x3ffff000) & 0x3fffffff) << 30)
-            | (data as u64 & 0x3fffffff)
+    fn build_reg_cmd(id: u8, idx: RegIdx, data: u32) -> u64 {
+        Self::build_cmd(id, idx.addr(), data)
     }
 
     fn clock(&mut self) {
-        let status = unsafe { QueryProtoBridgeDataStatus(self.handle) };
+        self.update_trace();
+
+        let status = unsafe { (self.backend.query_data_status)(self.handle) };
 
         let mut p_in = ptr::null();
         let mut p_out = ptr::null_mut();
@@ -107,7 +527,7 @@ impl ProtoBridge {
         }
 
         unsafe {
-            ClockProtoBridge(self.handle, p_in, p_out);
+            (self.backend.clock)(self.handle, p_in, p_out);
         }
 
         if !p_in.is_null() {
@@ -121,13 +541,75 @@ impl ProtoBridge {
         self.clocks += 1;
     }
 
+    /// Clocks the device until max_cycles have elapsed or output_capacity bytes have been received
+    /// Input data is streamed into the device from the input queue as space becomes available. When output_capacity
+    /// is zero, no output is received and the device is always clocked for max_cycles.
+    fn clock_batch(&mut self, max_cycles: usize, output_capacity: usize) {
+        let mut cycles_remaining = max_cycles as u64;
+        let mut output_remaining = output_capacity;
+
+        // Batches are split at trace boundaries so traces start and stop on exactly the requested cycles
+        while cycles_remaining > 0 {
+            self.update_trace();
+
+            let num_cycles = cmp::min(cycles_remaining, self.cycles_until_trace_update());
+            let (cycles, output_produced) = self.clock_batch_chunk(num_cycles, output_remaining);
+            if cycles == 0 {
+                // The chunk can't make progress, so looping again would never finish
+                break;
+            }
+            cycles_remaining -= cycles;
+
+            if output_capacity > 0 {
+                output_remaining -= output_produced;
+                if output_remaining == 0 {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Performs a single batch clocking call and returns the number of cycles and output bytes it produced
+    fn clock_batch_chunk(&mut self, max_cycles: u64, output_capacity: usize) -> (u64, usize) {
+        self.batch_output.resize(output_capacity, 0);
+
+        let input = self.input_queue.make_contiguous();
+        let mut input_consumed = 0;
+        let mut output_produced = 0;
+
+        let cycles = unsafe {
+            (self.backend.clock_batch)(
+                self.handle,
+                input.as_ptr(),
+                input.len(),
+                &mut input_consumed,
+                self.batch_output.as_mut_ptr(),
+                self.batch_output.len(),
+                &mut output_produced,
+                max_cycles,
+            )
+        };
+
+        self.input_queue.drain(..input_consumed);
+        self.output_queue
+            .extend(&self.batch_output[..output_produced]);
+
+        self.clocks += cycles;
+
+        (cycles, output_produced)
+    }
+
     pub fn wait_for_output(&mut self, num_bytes: usize, max_wait_cycles: usize) -> Result<usize> {
         // If we don't have enough data, we'll attempt to clock the device until we have enough.
         if self.output_queue.len() < num_bytes {
-            for _wait_cycle_idx in 0..max_wait_cycles {
-                self.clock();
-                if self.output_queue.len() >= num_bytes {
-                    break;
+            if self.is_batching_enabled {
+                self.clock_batch(max_wait_cycles, num_bytes - self.output_queue.len());
+            } else {
+                for _wait_cycle_idx in 0..max_wait_cycles {
+                    self.clock();
+                    if self.output_queue.len() >= num_bytes {
+                        break;
+                    }
                 }
             }
         }
@@ -139,6 +621,54 @@ impl ProtoBridge {
         }
     }
 
+    /// Moves received output data into the results of any requests it completes
+    fn process_output(&mut self) {
+        while let Some(pending) = self.pending_reads.front() {
+            if self.output_queue.len() < pending.len {
+                break;
+            }
+
+            let pending = self.pending_reads.pop_front().unwrap();
+            let data = self.output_queue.drain(..pending.len).collect::<Vec<_>>();
+            if !pending.is_discarded {
+                self.completed_reads.insert(pending.ticket, data);
+            }
+        }
+    }
+
+    /// Returns the number of output bytes that are still required to complete every outstanding request
+    fn outstanding_bytes(&self) -> usize {
+        let total_bytes = self
+            .pending_reads
+            .iter()
+            .map(|pending| pending.len)
+            .sum::<usize>();
+        total_bytes.saturating_sub(self.output_queue.len())
+    }
+
+    fn alloc_ticket(&mut self) -> Ticket {
+        let ticket = Ticket(self.next_ticket);
+        self.next_ticket += 1;
+        ticket
+    }
+
+    fn push_pending_read(&mut self, len: usize, is_discarded: bool) -> Ticket {
+        let ticket = self.alloc_ticket();
+        if len > 0 {
+            self.pending_reads.push_back(PendingRead {
+                ticket,
+                len,
+                is_discarded,
+            });
+
+            // Commands are processed in order so any earlier writes will be complete once this read returns
+            self.has_unfenced_writes = false;
+        } else if !is_discarded {
+            self.completed_reads.insert(ticket, Vec::new());
+        }
+        ticket
+    }
+
     // Command helper functions
     fn write_cmd(&mut self, cmd: u64) {
         self.write_all(&cmd.to_le_bytes())
@@ -149,52 +679,291 @@ impl ProtoBridge {
         self.write_cmd(Self::build_cmd(CMD_ID_READ, addr, size));
     }
 
-    fn cmd_read_reg(&mut self, idx: u16) {
-        self.write_cmd(Self::build_reg_cmd(CMD_ID_READ, idx, 0xffffffff));
+    fn cmd_read_reg(&mut self, idx: RegIdx) {
+        self.write_cmd(Self::build_reg_cmd(CMD_ID_READ, idx, MAX_CMD_FIELD_VALUE));
     }
 
     fn cmd_write_bytes(&mut self, addr: u32, size: u32) {
         self.write_cmd(Self::build_cmd(CMD_ID_WRITE, addr, size));
     }
 
-    fn cmd_write_reg(&mut self, idx: u16, data: u32) {
+    fn cmd_write_reg(&mut self, idx: RegIdx, data: u32) {
         self.write_cmd(Self::build_reg_cmd(CMD_ID_WRITE, idx, data));
     }
 
     // High level functions
-    pub fn write_bytes(&mut self, addr: u32, buf: &[u8]) {
-        self.cmd_write_bytes(addr, buf.len() as u32);
-        self.write_all(buf)
-            .expect("Failed to write bytes into internal buffer!");
+    pub fn write_bytes(&mut self, addr: u32, buf: &[u8]) -> Result<()> {
+        let pieces = split_transfer(addr, buf.len(), self.memory_size, self.max_transfer_size)?;
+        for (piece_addr, range) in pieces {
+            self.cmd_write_bytes(piece_addr, range.len() as u32);
+            self.write_all(&buf[range])
+                .expect("Failed to write bytes into internal buffer!");
+            self.has_unfenced_writes = true;
+        }
+        Ok(())
     }
 
     pub fn read_bytes(&mut self, addr: u32, buf: &mut [u8], max_wait_cycles: usize) -> Result<()> {
-        self.cmd_read_bytes(addr, buf.len() as u32);
-        match self.wait_for_output(buf.len(), max_wait_cycles) {
-            Ok(_) => {
-                self.read_exact(buf)
-                    .expect("Failed to read bytes from internal buffer after waiting!");
-                Ok(())
-            }
-            Err(err) => Err(err),
+        let ticket = self.submit_read(addr, buf.len())?;
+        let data = self.wait(ticket, max_wait_cycles)?;
+        buf.copy_from_slice(&data);
+        Ok(())
+    }
+
+    /// Queues a read of device memory and returns a ticket that can be used to retrieve the data later
+    /// The device isn't clocked by this function so multiple requests can be in flight at once.
+    pub fn submit_read(&mut self, addr: u32, len: usize) -> Result<Ticket> {
+        let pieces = split_transfer(addr, len, self.memory_size, self.max_transfer_size)?;
+        for (piece_addr, range) in pieces {
+            self.cmd_read_bytes(piece_addr, range.len() as u32);
         }
+        Ok(self.push_pending_read(len, false))
     }
 
-    pub fn read_reg(&mut self, idx: u16, max_wait_cycles: usize) -> Result<u32> {
+    /// Queues a read of a device register and returns a ticket that can be used to retrieve the data later
+    pub fn submit_read_reg(&mut self, idx: RegIdx) -> Ticket {
         self.cmd_read_reg(idx);
-        match self.wait_for_output(4, max_wait_cycles) {
-            Ok(_) => {
-                let mut buf = [0; 4];
-                self.read_exact(&mut buf)
-                    .expect("Failed to read bytes from internal buffer after waiting!");
-                Ok(u32::from_le_bytes(buf))
+        self.push_pending_read(4, false)
+    }
+
+    /// Returns a ticket for data that was produced without involving the device
+    /// This allows results from other access paths to be returned through the same interface as device reads.
+    pub fn submit_completed(&mut self, data: Vec<u8>) -> Ticket {
+        let ticket = self.alloc_ticket();
+        self.completed_reads.insert(ticket, data);
+        ticket
+    }
+
+    /// Returns the data associated with the ticket if the request has completed
+    /// The device isn't clocked by this function. Once data has been returned, the ticket is no longer valid.
+    pub fn poll(&mut self, ticket: Ticket) -> Option<Vec<u8>> {
+        self.process_output();
+        self.completed_reads.remove(&ticket)
+    }
+
+    /// Clocks the device until the request associated with the ticket completes and returns its data
+    pub fn wait(&mut self, ticket: Ticket, max_wait_cycles: usize) -> Result<Vec<u8>> {
+        if let Some(data) = self.poll(ticket) {
+            return Ok(data);
+        }
+
+        // Requests complete in order so every request ahead of this one must complete first
+        let position = self
+            .pending_reads
+            .iter()
+            .position(|pending| pending.ticket == ticket)
+            .ok_or_else(|| ProtoBridgeError::from(ProtoBridgeErrorKind::UnknownTicket))?;
+        let num_bytes = self
+            .pending_reads
+            .iter()
+            .take(position + 1)
+            .map(|pending| pending.len)
+            .sum();
+
+        self.wait_for_output(num_bytes, max_wait_cycles)?;
+
+        Ok(self
+            .poll(ticket)
+            .expect("Request was not completed after waiting!"))
+    }
+
+    /// Clocks the device for the specified number of cycles
+    /// Queued commands are sent to the device and any data it returns is collected for outstanding requests.
+    pub fn clock_cycles(&mut self, num_cycles: usize) {
+        if self.is_batching_enabled {
+            let mut cycles_remaining = num_cycles as u64;
+            while cycles_remaining > 0 {
+                let prev_clocks = self.clocks;
+                self.clock_batch(cycles_remaining as usize, self.outstanding_bytes());
+
+                let cycles = self.clocks - prev_clocks;
+                if cycles == 0 {
+                    // The batch can't make progress, so looping again would never finish
+                    break;
+                }
+                cycles_remaining -= cycles;
+            }
+        } else {
+            for _cycle_idx in 0..num_cycles {
+                self.clock();
             }
-            Err(err) => Err(err),
         }
+
+        self.process_output();
     }
 
-    pub fn write_reg(&mut self, idx: u16, data: u32) {
+    /// Clocks the device until every queued command has been completely processed
+    pub fn clock_until_idle(&mut self) -> Result<()> {
+        // Writes don't produce any output so a register read is queued behind them to detect when they complete
+        if self.has_unfenced_writes {
+            self.cmd_read_reg(REG_IDX_DEV_EN);
+            self.push_pending_read(4, true);
+        }
+
+        let num_bytes = self.pending_reads.iter().map(|pending| pending.len).sum();
+        self.wait_for_output(num_bytes, WAIT_INFINITE_CYCLES)?;
+        self.process_output();
+
+        Ok(())
+    }
+
+    /// Begins capturing a waveform trace of the device into the file at the provided path
+    /// If a trace window was set, the trace only begins once the device reaches the start of the window.
+    pub fn start_trace(&mut self, path: &Path, format: TraceFormat) -> Result<()> {
+        self.stop_trace()?;
+
+        // Creating the file here reports invalid paths immediately even when the trace begins later
+        File::create(path)?;
+
+        self.trace = Some(Trace {
+            path: path.to_path_buf(),
+            format,
+            window: self.trace_window.clone().unwrap_or(0..u64::MAX),
+            state: TraceState::Pending,
+        });
+        self.update_trace();
+
+        match &self.trace {
+            Some(trace) if trace.state == TraceState::Failed => self.stop_trace(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes everything traced so far to the trace file so it can be read while the trace continues
+    pub fn flush_trace(&mut self) {
+        if let Some(trace) = &self.trace {
+            if trace.state == TraceState::Active {
+                unsafe { (self.backend.flush_trace)(self.handle) }
+            }
+        }
+    }
+
+    /// Stops the current waveform trace
+    /// An error is returned if the simulation was unable to write the trace.
+    pub fn stop_trace(&mut self) -> Result<()> {
+        match self.trace.take() {
+            Some(trace) => match trace.state {
+                TraceState::Active => {
+                    unsafe { (self.backend.stop_trace)(self.handle) }
+                    Ok(())
+                }
+                TraceState::Failed => {
+                    Err(ProtoBridgeError::from(ProtoBridgeErrorKind::TraceFailed {
+                        path: trace.path,
+                        format: trace.format,
+                    })
+                    .into())
+                }
+                TraceState::Pending | TraceState::Finished => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// Restricts traces started after this call to the provided range of cycles
+    pub fn set_trace_window(&mut self, window: Option<Range<u64>>) {
+        self.trace_window = window;
+    }
+
+    /// Starts or stops the current trace if the device reached one of its window boundaries
+    fn update_trace(&mut self) {
+        if let Some(trace) = &mut self.trace {
+            if (trace.state == TraceState::Pending) && (self.clocks >= trace.window.start) {
+                if self.clocks < trace.window.end {
+                    let is_started = match CString::new(trace.path.to_string_lossy().as_bytes()) {
+                        Ok(path) => unsafe {
+                            (self.backend.start_trace)(
+                                self.handle,
+                                path.as_ptr(),
+                                trace.format.id(),
+                            ) == 0
+                        },
+                        Err(_) => false,
+                    };
+                    trace.state = if is_started {
+                        TraceState::Active
+                    } else {
+                        TraceState::Failed
+                    };
+                } else {
+                    trace.state = TraceState::Finished;
+                }
+            }
+
+            if (trace.state == TraceState::Active) && (self.clocks >= trace.window.end) {
+                unsafe { (self.backend.stop_trace)(self.handle) }
+                trace.state = TraceState::Finished;
+            }
+        }
+    }
+
+    /// Returns the number of cycles until the current trace needs to be started or stopped
+    fn cycles_until_trace_update(&self) -> u64 {
+        match &self.trace {
+            Some(trace) => match trace.state {
+                TraceState::Pending => trace.window.start - self.clocks,
+                TraceState::Active => trace.window.end - self.clocks,
+                TraceState::Finished | TraceState::Failed => u64::MAX,
+            },
+            None => u64::MAX,
+        }
+    }
+
+    /// Writes directly into device memory without sending any commands
+    /// The device is only clocked if it's required to complete previously queued commands first.
+    pub fn backdoor_write_bytes(&mut self, addr: u32, buf: &[u8]) -> Result<()> {
+        self.clock_until_idle()?;
+
+        let bytes_written =
+            unsafe { (self.backend.write_memory)(self.handle, addr, buf.as_ptr(), buf.len()) };
+        if bytes_written == buf.len() {
+            Ok(())
+        } else {
+            Err(
+                ProtoBridgeError::from(ProtoBridgeErrorKind::AddressOutOfRange {
+                    addr,
+                    len: buf.len(),
+                })
+                .into(),
+            )
+        }
+    }
+
+    /// Reads directly from device memory without sending any commands
+    /// The device is only clocked if it's required to complete previously queued commands first.
+    pub fn backdoor_read_bytes(&mut self, addr: u32, buf: &mut [u8]) -> Result<()> {
+        self.clock_until_idle()?;
+
+        let bytes_read =
+            unsafe { (self.backend.read_memory)(self.handle, addr, buf.as_mut_ptr(), buf.len()) };
+        if bytes_read == buf.len() {
+            Ok(())
+        } else {
+            Err(
+                ProtoBridgeError::from(ProtoBridgeErrorKind::AddressOutOfRange {
+                    addr,
+                    len: buf.len(),
+                })
+                .into(),
+            )
+        }
+    }
+
+    pub fn read_reg(&mut self, idx: RegIdx, max_wait_cycles: usize) -> Result<u32> {
+        let ticket = self.submit_read_reg(idx);
+        let data = self.wait(ticket, max_wait_cycles)?;
+        Ok(u32::from_le_bytes([data[0], data[1], data[2], data[3]]))
+    }
+
+    pub fn write_reg(&mut self, idx: RegIdx, data: u32) -> Result<()> {
+        if data > MAX_CMD_FIELD_VALUE {
+            return Err(
+                ProtoBridgeError::from(ProtoBridgeErrorKind::RegisterDataTooLarge(data)).into(),
+            );
+        }
         self.cmd_write_reg(idx, data);
+        self.has_unfenced_writes = true;
+        Ok(())
     }
 }
 
@@ -204,9 +973,14 @@ impl Default for ProtoBridge {
     }
 }
 
+// The simulation handle is exclusively owned by the bridge so it can safely be moved to another thread
+unsafe impl Send for ProtoBridge {}
+
 impl Drop for ProtoBridge {
     fn drop(&mut self) {
-        unsafe { DestroyProtoBridge(self.handle) }
+        // Errors can't be reported here and failed traces have nothing left to clean up
+        let _ = self.stop_trace();
+        unsafe { (self.backend.destroy)(self.handle) }
     }
 }
 
@@ -224,7 +998,7 @@ mod tests {
             input_data.push(i as u8);
         }
 
-        bridge.write_bytes(0, &input_data);
+        bridge.write_bytes(0, &input_data).unwrap();
 
         let mut output_data = vec![0; memory_size];
 
@@ -236,4 +1010,365 @@ mod tests {
             assert_eq!(input_data[i], output_data[i]);
         }
     }
+
+    #[test]
+    fn batched_and_unbatched_clocking_match() {
+        let input_data = (0..4096).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+
+        let mut results = Vec::new();
+        for &is_batching_enabled in &[false, true] {
+            let mut bridge = ProtoBridge::new();
+            bridge.set_batching_enabled(is_batching_enabled);
+
+            bridge.write_bytes(0x1000, &input_data).unwrap();
+            let mut output_data = vec![0; input_data.len()];
+            bridge
+                .read_bytes(0x1000, &mut output_data, WAIT_INFINITE_CYCLES)
+                .unwrap();
+            let fb_addr = bridge
+                .read_reg(REG_IDX_FB_ADDR, WAIT_INFINITE_CYCLES)
+                .unwrap();
+
+            assert_eq!(output_data, input_data);
+            results.push((bridge.clocks(), fb_addr));
+        }
+
+        // Batching must not change the behavior of the device, only how it's driven
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[test]
+    fn backdoor_matches_frontdoor() {
+        let mut bridge = ProtoBridge::new();
+
+        bridge.backdoor_write_bytes(0x100, &[1, 2, 3, 4]).unwrap();
+        let mut data = [0; 4];
+        bridge
+            .read_bytes(0x100, &mut data, WAIT_INFINITE_CYCLES)
+            .unwrap();
+        assert_eq!(data, [1, 2, 3, 4]);
+
+        bridge.write_bytes(0x200, &[5, 6, 7, 8]).unwrap();
+        bridge
+            .read_reg(REG_IDX_FB_ADDR, WAIT_INFINITE_CYCLES)
+            .unwrap();
+        let clocks = bridge.clocks();
+        bridge.backdoor_read_bytes(0x200, &mut data).unwrap();
+        assert_eq!(data, [5, 6, 7, 8]);
+
+        // Backdoor accesses never consume device cycles
+        assert_eq!(bridge.clocks(), clocks);
+    }
+
+    #[test]
+    fn backdoor_out_of_range() {
+        let mut bridge = ProtoBridge::new();
+
+        assert!(bridge.backdoor_write_bytes(u32::MAX, &[0; 4]).is_err());
+        assert!(bridge
+            .backdoor_read_bytes(u32::MAX - 1, &mut [0; 4])
+            .is_err());
+    }
+
+    #[test]
+    fn pipelined_reads() {
+        let mut bridge = ProtoBridge::new();
+
+        bridge
+            .write_bytes(0x100, &[1, 2, 3, 4, 5, 6, 7, 8])
+            .unwrap();
+
+        let first = bridge.submit_read(0x100, 4).unwrap();
+        let reg = bridge.submit_read_reg(REG_IDX_FB_ADDR);
+        let second = bridge.submit_read(0x104, 4).unwrap();
+        let empty = bridge.submit_read(0x104, 0).unwrap();
+
+        // Nothing has been clocked yet so only the empty request is complete
+        assert_eq!(bridge.poll(first), None);
+        assert_eq!(bridge.poll(empty), Some(Vec::new()));
+
+        // Results can be retrieved in any order
+        assert_eq!(
+            bridge.wait(second, WAIT_INFINITE_CYCLES).unwrap(),
+            vec![5, 6, 7, 8]
+        );
+        assert_eq!(bridge.poll(first), Some(vec![1, 2, 3, 4]));
+        assert_eq!(
+            bridge.poll(reg),
+            Some(bridge_default_fb_addr().to_le_bytes().to_vec())
+        );
+
+        // Tickets are only valid until their data has been returned
+        assert_eq!(bridge.poll(first), None);
+        assert!(bridge.wait(first, WAIT_INFINITE_CYCLES).is_err());
+    }
+
+    #[test]
+    fn clock_cycles_completes_requests() {
+        for is_batching_enabled in [false, true] {
+            let mut bridge = ProtoBridge::new();
+            bridge.set_batching_enabled(is_batching_enabled);
+
+            let ticket = bridge.submit_read_reg(REG_IDX_FB_ADDR);
+            bridge.clock_cycles(1000);
+            assert_eq!(bridge.clocks(), 1000);
+            assert_eq!(
+                bridge.poll(ticket),
+                Some(bridge_default_fb_addr().to_le_bytes().to_vec())
+            );
+
+            // Clocking without any outstanding requests still advances the device
+            bridge.clock_cycles(100);
+            assert_eq!(bridge.clocks(), 1100);
+        }
+    }
+
+    #[test]
+    fn clock_until_idle_completes_writes() {
+        let mut bridge = ProtoBridge::new();
+
+        bridge.write_bytes(0x100, &[0xaa; 64]).unwrap();
+        bridge.clock_until_idle().unwrap();
+
+        let mut data = [0; 64];
+        let clocks = bridge.clocks();
+        bridge.backdoor_read_bytes(0x100, &mut data).unwrap();
+        assert_eq!(data, [0xaa; 64]);
+        assert_eq!(bridge.clocks(), clocks);
+
+        // Nothing is outstanding so this shouldn't need to clock the device
+        bridge.clock_until_idle().unwrap();
+        assert_eq!(bridge.clocks(), clocks);
+    }
+
+    fn bridge_default_fb_addr() -> u32 {
+        let mut bridge = ProtoBridge::new();
+        bridge
+            .read_reg(REG_IDX_FB_ADDR, WAIT_INFINITE_CYCLES)
+            .unwrap()
+    }
+
+    #[cfg(not(feature = "verilator"))]
+    #[test]
+    fn trace_window_bounds() {
+        for is_batching_enabled in [false, true] {
+            let path = std::env::temp_dir().join(format!(
+                "devsim_trace_window_{}_{}.vcd",
+                std::process::id(),
+                is_batching_enabled
+            ));
+
+            let mut bridge = ProtoBridge::new();
+            bridge.set_batching_enabled(is_batching_enabled);
+            bridge.set_trace_window(Some(100..200));
+            bridge.start_trace(&path, TraceFormat::Vcd).unwrap();
+
+            // Keep the command state machine changing state for the entire window
+            for _read_idx in 0..32 {
+                bridge.submit_read_reg(REG_IDX_FB_ADDR);
+            }
+            bridge.clock_cycles(300);
+            bridge.stop_trace().unwrap();
+            bridge.clock_until_idle().unwrap();
+
+            let trace = std::fs::read_to_string(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            let times = trace
+                .lines()
+                .filter_map(|line| line.strip_prefix('#'))
+                .map(|time| time.parse::<u64>().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(times.first(), Some(&100));
+            assert!(times.len() > 1);
+            assert!(times.iter().all(|time| (100..=200).contains(time)));
+        }
+    }
+
+    #[test]
+    fn trace_failures() {
+        let mut bridge = ProtoBridge::new();
+
+        let missing_dir_path = std::env::temp_dir()
+            .join("devsim_missing_dir")
+            .join("trace.vcd");
+        assert!(bridge
+            .start_trace(&missing_dir_path, TraceFormat::Vcd)
+            .is_err());
+
+        // Traces are only written in the format the simulation was built with
+        #[cfg(not(feature = "verilator"))]
+        {
+            let path = std::env::temp_dir()
+                .join(format!("devsim_trace_failure_{}.fst", std::process::id()));
+            let err = bridge.start_trace(&path, TraceFormat::Fst).unwrap_err();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(
+                err.downcast_ref::<ProtoBridgeError>().unwrap().kind(),
+                &ProtoBridgeErrorKind::TraceFailed {
+                    path,
+                    format: TraceFormat::Fst
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn backend_load_failure() {
+        let path = std::env::temp_dir().join("devsim_missing_backend.so");
+        assert!(Backend::load(&path).is_err());
+    }
+
+    #[test]
+    fn reg_idx_bounds() {
+        assert_eq!(RegIdx::new(0).unwrap(), REG_IDX_DEV_EN);
+        assert_eq!(RegIdx::new(2).unwrap(), REG_IDX_FB_CONFIG);
+        assert_eq!(
+            RegIdx::new(NUM_REGS).unwrap_err().kind(),
+            &ProtoBridgeErrorKind::InvalidRegisterIndex(NUM_REGS)
+        );
+        assert!(RegIdx::new(u16::MAX).is_err());
+
+        // The highest register must still land inside the register space after encoding
+        let cmd = model::Cmd::decode(ProtoBridge::build_reg_cmd(
+            CMD_ID_READ,
+            RegIdx::new(NUM_REGS - 1).unwrap(),
+            0,
+        ));
+        assert_eq!(cmd.addr, REG_SPACE_BASE + (NUM_REGS as u32 - 1) * 4);
+    }
+
+    #[test]
+    fn reg_idx_arrays() {
+        assert_eq!(reg_idx_dbg_breakpoint(0).unwrap(), RegIdx(9));
+        assert_eq!(
+            reg_idx_dbg_breakpoint(NUM_BREAKPOINTS - 1).unwrap(),
+            RegIdx(12)
+        );
+        assert_eq!(reg_idx_fb_buffer(1).unwrap(), RegIdx(17));
+        assert_eq!(reg_idx_dbg_x(NUM_CPU_REGS - 1).unwrap(), RegIdx(63));
+
+        // Out of range indices must not wrap around to another register of the array
+        assert_eq!(
+            reg_idx_dbg_breakpoint(NUM_BREAKPOINTS).unwrap_err().kind(),
+            &ProtoBridgeErrorKind::RegisterArrayIndexOutOfRange {
+                n: NUM_BREAKPOINTS,
+                len: NUM_BREAKPOINTS
+            }
+        );
+        assert!(reg_idx_fb_buffer(NUM_FB_BUFFERS).is_err());
+        assert!(reg_idx_dbg_x(NUM_CPU_REGS).is_err());
+        assert!(reg_idx_dbg_x(usize::MAX).is_err());
+    }
+
+    /// Returns the kind of the bridge error that caused a request to fail
+    fn error_kind(result: Result<()>) -> ProtoBridgeErrorKind {
+        result
+            .unwrap_err()
+            .downcast_ref::<ProtoBridgeError>()
+            .expect("Request failed with an error from outside the bridge")
+            .kind()
+            .clone()
+    }
+
+    #[test]
+    fn transfers_are_checked_against_memory_size() {
+        let mut bridge = ProtoBridge::new();
+        let memory_size = hw_params::MEM_SIZE;
+        assert_eq!(bridge.memory_size, memory_size);
+
+        // The last bytes of memory are still accessible
+        let mut data = [0; 4];
+        bridge.write_bytes(memory_size - 4, &[1, 2, 3, 4]).unwrap();
+        bridge
+            .read_bytes(memory_size - 4, &mut data, WAIT_INFINITE_CYCLES)
+            .unwrap();
+        assert_eq!(data, [1, 2, 3, 4]);
+
+        assert_eq!(
+            error_kind(bridge.write_bytes(memory_size - 4, &[0; 5])),
+            ProtoBridgeErrorKind::AddressOutOfRange {
+                addr: memory_size - 4,
+                len: 5
+            }
+        );
+        assert_eq!(
+            error_kind(bridge.read_bytes(memory_size, &mut [0; 1], WAIT_INFINITE_CYCLES)),
+            ProtoBridgeErrorKind::AddressOutOfRange {
+                addr: memory_size,
+                len: 1
+            }
+        );
+        assert!(bridge.submit_read(u32::MAX, 1).is_err());
+
+        // A smaller device rejects addresses that were valid before
+        bridge.set_memory_size(0x1000);
+        assert!(bridge.write_bytes(0xffc, &[0; 8]).is_err());
+        bridge.write_bytes(0xffc, &[0; 4]).unwrap();
+    }
+
+    #[test]
+    fn large_transfers_are_split() {
+        let mut bridge = ProtoBridge::new();
+        bridge.set_max_transfer_size(16).unwrap();
+
+        let input_data = (0..40).collect::<Vec<u8>>();
+        bridge.write_bytes(0x100, &input_data).unwrap();
+
+        // Each of the three pieces is preceded by its own command
+        let commands = bridge.input_queue.iter().copied().collect::<Vec<u8>>();
+        assert_eq!(commands.len(), (3 * 8) + input_data.len());
+        let piece_offsets = [0, 8 + 16, (2 * 8) + 32];
+        let piece_sizes = [16, 16, 8];
+        for (idx, (offset, size)) in piece_offsets.iter().zip(&piece_sizes).enumerate() {
+            let mut cmd_bytes = [0; 8];
+            cmd_bytes.copy_from_slice(&commands[*offset..(*offset + 8)]);
+            let cmd = model::Cmd::decode(u64::from_le_bytes(cmd_bytes));
+            assert_eq!(cmd.addr, 0x100 + (idx as u32 * 16));
+            assert_eq!(cmd.size, *size);
+        }
+
+        let mut output_data = vec![0; input_data.len()];
+        bridge
+            .read_bytes(0x100, &mut output_data, WAIT_INFINITE_CYCLES)
+            .unwrap();
+        assert_eq!(output_data, input_data);
+
+        assert_eq!(
+            error_kind(bridge.set_max_transfer_size(0)),
+            ProtoBridgeErrorKind::InvalidTransferSize(0)
+        );
+        assert!(bridge.set_max_transfer_size(MAX_TRANSFER_SIZE + 1).is_err());
+        bridge.set_max_transfer_size(MAX_TRANSFER_SIZE).unwrap();
+    }
+
+    #[test]
+    fn invalid_transfers_are_rejected() {
+        let mut bridge = ProtoBridge::new();
+
+        assert!(bridge.write_bytes(REG_SPACE_BASE, &[0; 4]).is_err());
+        assert!(bridge
+            .read_bytes(REG_SPACE_BASE - 2, &mut [0; 4], WAIT_INFINITE_CYCLES)
+            .is_err());
+        assert!(bridge
+            .write_reg(REG_IDX_DEV_EN, MAX_CMD_FIELD_VALUE + 1)
+            .is_err());
+
+        // Rejected requests must not leave any partial commands behind
+        let mut data = [0; 4];
+        bridge.write_bytes(0x100, &[1, 2, 3, 4]).unwrap();
+        bridge
+            .read_bytes(0x100, &mut data, WAIT_INFINITE_CYCLES)
+            .unwrap();
+        assert_eq!(data, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn empty_transfers_are_skipped() {
+        let mut bridge = ProtoBridge::new();
+
+        bridge.write_bytes(0x100, &[]).unwrap();
+        bridge.read_bytes(0x100, &mut [], 0).unwrap();
+        assert_eq!(bridge.clocks(), 0);
+    }
 }
@@ -0,0 +1,1027 @@
+//! Software reference model of the hardware host interface
+//!
+//! This module is a cycle level translation of the command handling logic in hw/top.sv and hw/cmd_parser.sv. It
+//! stands in for the verilator model when the `verilator` feature is disabled and is used by the unit tests to check
+//! the command framing produced by ProtoBridge. The model doesn't contain a cpu so the device never begins executing.
+
+// These functions intentionally mirror the names of the C entry points exported by protobridge
+#![allow(non_snake_case)]
+
+#[cfg(any(test, not(feature = "verilator")))]
+use super::{DataStatus, ProtoBridgeHandle, NUM_BREAKPOINTS, NUM_FB_BUFFERS, TRACE_FORMAT_VCD};
+use crate::hw_params;
+#[cfg(any(test, not(feature = "verilator")))]
+use std::ffi::CStr;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+#[cfg(any(test, not(feature = "verilator")))]
+use std::os::raw::c_char;
+use std::path::Path;
+#[cfg(any(test, not(feature = "verilator")))]
+use std::slice;
+
+/// Number of bytes that fit inside each of the fifos in hw/top.sv
+const FIFO_DEPTH: usize = hw_params::FIFO_DEPTH as usize;
+
+/// Size of the device memory in bytes
+const MEM_SIZE: usize = hw_params::MEM_SIZE as usize;
+
+/// Address of the first register in the command address space
+const REG_SPACE_BASE: u32 = 0x3ffff000;
+
+/// Default framebuffer address and dimensions from hw/top.sv
+const FB_ADDR: u32 = hw_params::FB_ADDR;
+const FB_DIM: u32 = hw_params::FB_DIM_LOG2 - 1;
+const FB_SIZE: u32 = (1 << (FB_DIM + 1)) * (1 << (FB_DIM + 1)) * 4;
+
+/// Command identifiers from hw/common.sv
+const CMD_ID_RESET: u8 = 0;
+const CMD_ID_READ: u8 = 1;
+const CMD_ID_WRITE: u8 = 2;
+
+/// A decoded bridge command
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Cmd {
+    pub id: u8,
+    pub addr: u32,
+    pub size: u32,
+}
+
+impl Cmd {
+    /// Decodes a command the same way the outputs of hw/cmd_parser.sv are assigned
+    pub fn decode(cmd: u64) -> Self {
+        Cmd {
+            id: ((cmd >> 60) & 0xf) as u8,
+            addr: ((cmd >> 30) & 0x3fffffff) as u32,
+            size: (cmd & 0x3fffffff) as u32,
+        }
+    }
+}
+
+/// Model of hw/fifo.v
+struct Fifo {
+    data: [u8; FIFO_DEPTH],
+    read_ptr: usize,
+    write_ptr: usize,
+}
+
+impl Fifo {
+    fn new() -> Self {
+        Fifo {
+            data: [0; FIFO_DEPTH],
+            read_ptr: 0,
+            write_ptr: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.read_ptr == self.write_ptr
+    }
+
+    fn is_full(&self) -> bool {
+        (self.read_ptr % FIFO_DEPTH == self.write_ptr % FIFO_DEPTH) && !self.is_empty()
+    }
+
+    /// Returns the value of o_data for the provided write input
+    fn output(&self, write: Option<u8>) -> u8 {
+        match write {
+            Some(byte) if self.is_empty() => byte,
+            _ => self.data[self.read_ptr % FIFO_DEPTH],
+        }
+    }
+
+    /// Updates the fifo state on the rising edge of the clock
+    fn clock(&mut self, write: Option<u8>, read: bool) {
+        let is_empty = self.is_empty();
+        let is_full = self.is_full();
+
+        if read && !is_empty {
+            self.read_ptr = (self.read_ptr + 1) % (FIFO_DEPTH * 2);
+        }
+        if let Some(byte) = write {
+            if !is_full {
+                self.data[self.write_ptr % FIFO_DEPTH] = byte;
+                self.write_ptr = (self.write_ptr + 1) % (FIFO_DEPTH * 2);
+            }
+        }
+    }
+}
+
+/// Model of hw/cmd_parser.sv
+struct CmdParser {
+    cmd_buf: u64,
+    byte_counter: u8,
+}
+
+impl CmdParser {
+    fn new() -> Self {
+        CmdParser {
+            cmd_buf: 0,
+            byte_counter: 0,
+        }
+    }
+
+    fn is_cmd_valid(&self, clear_cmd: bool) -> bool {
+        (self.byte_counter & 0x8) != 0 && !clear_cmd
+    }
+
+    fn is_cmd_valid_next(&self, clear_cmd: bool) -> bool {
+        ((self.byte_counter + 1) & 0x8) != 0 && !clear_cmd
+    }
+
+    fn cmd(&self) -> Cmd {
+        Cmd::decode(self.cmd_buf)
+    }
+
+    fn clock(&mut self, data: Option<u8>, clear_cmd: bool) {
+        if clear_cmd {
+            self.cmd_buf = 0;
+            self.byte_counter = 0;
+        } else if let Some(byte) = data {
+            if !self.is_cmd_valid(clear_cmd) {
+                self.cmd_buf = ((byte as u64) << 56) | (self.cmd_buf >> 8);
+                self.byte_counter = (self.byte_counter + 1) & 0xf;
+            }
+        }
+    }
+}
+
+/// Model of the cmd_state enumeration in hw/top.sv
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum CmdState {
+    Idle,
+    Reset,
+    Read,
+    Write,
+}
+
+/// Signals written into traces of the model
+/// Each entry contains the identifier code, bit width and name of the signal in hw/top.sv.
+const TRACE_SIGNALS: [(&str, u32, &str); 8] = [
+    ("!", 2, "r_state"),
+    ("\"", 1, "o_input_full"),
+    ("#", 1, "o_output_empty"),
+    ("%", 1, "w_cpu_mem_write_en"),
+    ("&", 2, "w_cpu_mem_req_size_out"),
+    ("'", 32, "w_cpu_mem_addr_out"),
+    ("(", 32, "w_cpu_mem_data_out"),
+    (")", 32, "r_cpu_mem_data_in"),
+];
+
+/// Signals of hw/cpu.sv written into traces of the model, which are declared inside the cpu scope
+/// The model doesn't contain a cpu so these keep their reset values, but they let traces of the model be viewed the
+/// same way as traces of the hardware.
+const CPU_TRACE_SIGNALS: [(&str, u32, &str); 2] = [("*", 32, "r_pc"), ("+", 3, "r_state")];
+
+/// Number of values in each dump of a trace
+const NUM_TRACE_VALUES: usize = TRACE_SIGNALS.len() + CPU_TRACE_SIGNALS.len();
+
+/// Writes a vcd trace of the model's externally visible state
+struct VcdTrace {
+    writer: BufWriter<File>,
+    prev_values: Option<[u32; NUM_TRACE_VALUES]>,
+}
+
+impl VcdTrace {
+    fn create(path: &Path) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writeln!(writer, "$timescale 1ns $end")?;
+        writeln!(writer, "$scope module top $end")?;
+        for (id, width, name) in TRACE_SIGNALS.iter() {
+            writeln!(writer, "$var wire {} {} {} $end", width, id, name)?;
+        }
+        writeln!(writer, "$scope module cpu $end")?;
+        for (id, width, name) in CPU_TRACE_SIGNALS.iter() {
+            writeln!(writer, "$var wire {} {} {} $end", width, id, name)?;
+        }
+        writeln!(writer, "$upscope $end")?;
+        writeln!(writer, "$upscope $end")?;
+        writeln!(writer, "$enddefinitions $end")?;
+
+        Ok(VcdTrace {
+            writer,
+            prev_values: None,
+        })
+    }
+
+    /// Writes any values that changed since the previous dump
+    fn dump(&mut self, time: u64, values: [u32; NUM_TRACE_VALUES]) -> io::Result<()> {
+        if self.prev_values == Some(values) {
+            return Ok(());
+        }
+
+        writeln!(self.writer, "#{}", time)?;
+        let signals = TRACE_SIGNALS.iter().chain(CPU_TRACE_SIGNALS.iter());
+        for (idx, (id, width, _name)) in signals.enumerate() {
+            if self.prev_values.map(|prev| prev[idx]) != Some(values[idx]) {
+                if *width == 1 {
+                    writeln!(self.writer, "{}{}", values[idx], id)?;
+                } else {
+                    writeln!(self.writer, "b{:b} {}", values[idx], id)?;
+                }
+            }
+        }
+        self.prev_values = Some(values);
+
+        Ok(())
+    }
+}
+
+/// Model of the command handling portion of hw/top.sv
+pub struct Model {
+    input_fifo: Fifo,
+    output_fifo: Fifo,
+    cmd_parser: CmdParser,
+    mem: Vec<u8>,
+
+    state: CmdState,
+    in_fifo_read: bool,
+    out_fifo_write: bool,
+    out_fifo_input: u8,
+    transfer_cur_addr: u32,
+    cmd_parser_clear_cmd: bool,
+    reg_read_data: u32,
+    reg_read_bytes_remaining: u8,
+    fb_buffers: [u32; NUM_FB_BUFFERS],
+    fb_front: u32,
+    fb_config: u32,
+    dbg_halt: bool,
+    dbg_break: bool,
+    dbg_breakpoints: [u32; NUM_BREAKPOINTS],
+    mem_wait_states: u32,
+
+    cycles: u64,
+    trace: Option<VcdTrace>,
+}
+
+impl Model {
+    pub fn new() -> Self {
+        // The buffers are placed next to each other like the reset values in hw/top.sv
+        let mut fb_buffers = [0; NUM_FB_BUFFERS];
+        for (n, fb_buffer) in fb_buffers.iter_mut().enumerate() {
+            *fb_buffer = FB_ADDR + FB_SIZE * n as u32;
+        }
+
+        Model {
+            input_fifo: Fifo::new(),
+            output_fifo: Fifo::new(),
+            cmd_parser: CmdParser::new(),
+            mem: vec![0; MEM_SIZE],
+            state: CmdState::Idle,
+            in_fifo_read: false,
+            out_fifo_write: false,
+            out_fifo_input: 0,
+            transfer_cur_addr: 0,
+            cmd_parser_clear_cmd: false,
+            reg_read_data: 0,
+            reg_read_bytes_remaining: 0,
+            fb_buffers,
+            fb_front: 0,
+            fb_config: (FB_DIM << 3) | FB_DIM,
+            dbg_halt: false,
+            dbg_break: false,
+            dbg_breakpoints: [0; NUM_BREAKPOINTS],
+            mem_wait_states: (hw_params::DATA_WAIT_STATES << 8) | hw_params::FETCH_WAIT_STATES,
+            cycles: 0,
+            trace: None,
+        }
+    }
+
+    /// Begins writing a vcd trace of the model into the file at the provided path
+    /// The current state is written immediately and every following cycle is captured until the trace is stopped.
+    pub fn start_trace(&mut self, path: &Path) -> io::Result<()> {
+        self.trace = Some(VcdTrace::create(path)?);
+        self.dump_trace()
+    }
+
+    /// Writes everything traced so far to disk without stopping the trace
+    pub fn flush_trace(&mut self) -> io::Result<()> {
+        match &mut self.trace {
+            Some(trace) => trace.writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Stops the current trace and flushes it to disk
+    pub fn stop_trace(&mut self) -> io::Result<()> {
+        match self.trace.take() {
+            Some(mut trace) => trace.writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn dump_trace(&mut self) -> io::Result<()> {
+        // The cpu memory bus and the cpu itself stay idle
+        let values = [
+            self.state as u32,
+            self.is_input_full() as u32,
+            self.is_output_empty() as u32,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ];
+        match &mut self.trace {
+            Some(trace) => trace.dump(self.cycles, values),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns true if the input fifo can't accept any more data
+    pub fn is_input_full(&self) -> bool {
+        self.input_fifo.is_full()
+    }
+
+    /// Returns true if there's no data available in the output fifo
+    pub fn is_output_empty(&self) -> bool {
+        self.output_fifo.is_empty()
+    }
+
+    fn read_reg(&self, idx: u32) -> u32 {
+        match idx {
+            // DEV_EN: There is no cpu in the model so it's always idle
+            0 => 0,
+            // FB_ADDR: The front buffer is never flipped without a cpu
+            1 => self.fb_buffers[self.fb_front as usize],
+            2 => self.fb_config,
+            // DBG_HALT: Halting has no effect without a cpu but the register is still stored
+            7 => self.dbg_halt as u32,
+            // DBG_BREAK: Breakpoints are never hit without a cpu
+            8 => self.dbg_break as u32,
+            // DBG_BREAKPOINT0 - DBG_BREAKPOINT3
+            9..=12 => self.dbg_breakpoints[idx as usize - 9],
+            // FB_FRAME_COUNT: Frames are only presented by the cpu
+            14 => 0,
+            15 => self.fb_front,
+            // FB_BUFFER0 - FB_BUFFERn
+            16..=31 if (idx as usize - 16) < NUM_FB_BUFFERS => self.fb_buffers[idx as usize - 16],
+            // DEV_MEM_SIZE
+            64 => MEM_SIZE as u32,
+            // DEV_FIFO_DEPTH
+            65 => FIFO_DEPTH as u32,
+            // MEM_WAIT_STATES: Stored even though the model has no memory latency
+            66 => self.mem_wait_states,
+            // Return 0 for unknown registers, which includes the other debug registers since there's no cpu
+            _ => 0,
+        }
+    }
+
+    /// Stores a register write
+    /// The debug registers are stored so they read back correctly, but they have no effect without a cpu.
+    fn write_reg(&mut self, idx: u32, data: u32) {
+        match idx {
+            7 => self.dbg_halt = (data & 1) != 0,
+            8 => self.dbg_break = (data & 1) != 0,
+            9..=12 => self.dbg_breakpoints[idx as usize - 9] = data,
+            // The cpu is always idle so the wait states can always be written
+            66 => self.mem_wait_states = data & 0xffff,
+            // Other register writes only affect the cpu which isn't present in the model
+            _ => {}
+        }
+    }
+
+    fn read_mem(&self, addr: u32) -> u8 {
+        self.mem.get(addr as usize).copied().unwrap_or(0)
+    }
+
+    fn write_mem(&mut self, addr: u32, data: u8) {
+        if let Some(byte) = self.mem.get_mut(addr as usize) {
+            *byte = data;
+        }
+    }
+
+    /// Returns the range of memory covered by an access, or None if any part of it is outside of memory
+    fn mem_range(&self, addr: u32, len: usize) -> Option<Range<usize>> {
+        let start = addr as usize;
+        let end = start.checked_add(len)?;
+        if end <= self.mem.len() {
+            Some(start..end)
+        } else {
+            None
+        }
+    }
+
+    /// Copies device memory into the provided buffer and returns the number of bytes that were read
+    /// Nothing is read if any part of the range is outside of memory, which matches ReadProtoBridgeMemory.
+    pub fn backdoor_read(&self, addr: u32, data: &mut [u8]) -> usize {
+        match self.mem_range(addr, data.len()) {
+            Some(range) => {
+                data.copy_from_slice(&self.mem[range]);
+                data.len()
+            }
+            None => 0,
+        }
+    }
+
+    /// Copies the provided data into device memory and returns the number of bytes that were written
+    /// Nothing is written if any part of the range is outside of memory, which matches WriteProtoBridgeMemory.
+    pub fn backdoor_write(&mut self, addr: u32, data: &[u8]) -> usize {
+        match self.mem_range(addr, data.len()) {
+            Some(range) => {
+                self.mem[range].copy_from_slice(data);
+                data.len()
+            }
+            None => 0,
+        }
+    }
+
+    /// Clocks the model once
+    ///
+    /// The input byte is pushed into the input fifo and the head of the output fifo is popped if the caller requests
+    /// it. This matches the behavior of ClockProtoBridge.
+    pub fn clock(&mut self, input: Option<u8>, read_output: bool) -> Option<u8> {
+        let output = if read_output {
+            Some(self.output_fifo.output(None))
+        } else {
+            None
+        };
+
+        // Sample all of the combinational signals before any registers are updated
+        let in_fifo_output = self.input_fifo.output(input);
+        let is_data_available = !self.input_fifo.is_empty();
+        let is_space_available = !self.output_fifo.is_full();
+
+        let clear_cmd = self.cmd_parser_clear_cmd;
+        let cmd_valid = self.cmd_parser.is_cmd_valid(clear_cmd);
+        let cmd_valid_next = self.cmd_parser.is_cmd_valid_next(clear_cmd);
+        let cmd = self.cmd_parser.cmd();
+
+        let transfer_end_addr = if cmd.size > 0 {
+            cmd.addr.wrapping_add(cmd.size - 1)
+        } else {
+            cmd.addr
+        };
+        let cmd_addr_is_reg = cmd.addr >= REG_SPACE_BASE;
+        let cmd_reg_idx = (cmd.addr >> 2) & 0x3ff;
+
+        let parser_data = if (self.state == CmdState::Idle) && self.in_fifo_read {
+            Some(in_fifo_output)
+        } else {
+            None
+        };
+        let in_fifo_read = self.in_fifo_read;
+        let out_fifo_write = if self.out_fifo_write {
+            Some(self.out_fifo_input)
+        } else {
+            None
+        };
+
+        match self.state {
+            CmdState::Idle => {
+                self.out_fifo_write = false;
+                self.cmd_parser_clear_cmd = false;
+
+                if self.reg_read_bytes_remaining > 0 {
+                    self.reg_read_bytes_remaining -= 1;
+                    self.out_fifo_input = self.reg_read_data as u8;
+                    self.out_fifo_write = true;
+                    self.reg_read_data >>= 8;
+                }
+
+                if cmd_valid {
+                    match cmd.id {
+                        CMD_ID_RESET => {
+                            self.state = CmdState::Reset;
+                        }
+                        CMD_ID_READ => {
+                            if cmd_addr_is_reg {
+                                self.state = CmdState::Idle;
+                                self.cmd_parser_clear_cmd = true;
+                                self.reg_read_data = self.read_reg(cmd_reg_idx);
+                                self.reg_read_bytes_remaining = 4;
+                            } else {
+                                self.state = CmdState::Read;
+                                self.transfer_cur_addr = cmd.addr;
+                            }
+                        }
+                        CMD_ID_WRITE => {
+                            if cmd_addr_is_reg {
+                                self.write_reg(cmd_reg_idx, cmd.size);
+                                self.state = CmdState::Idle;
+                                self.cmd_parser_clear_cmd = true;
+                            } else {
+                                self.state = CmdState::Write;
+                                self.transfer_cur_addr = cmd.addr;
+                                self.in_fifo_read = is_data_available;
+                            }
+                        }
+                        _ => {
+                            self.state = CmdState::Idle;
+                        }
+                    }
+                } else if is_data_available && !self.in_fifo_read {
+                    self.in_fifo_read = !clear_cmd;
+                } else if is_data_available && self.in_fifo_read {
+                    self.in_fifo_read = !cmd_valid_next;
+                }
+            }
+            CmdState::Reset => {
+                self.state = CmdState::Idle;
+                self.cmd_parser_clear_cmd = true;
+            }
+            CmdState::Read => {
+                if self.transfer_cur_addr <= transfer_end_addr {
+                    if is_space_available {
+                        self.out_fifo_write = true;
+                        self.out_fifo_input = self.read_mem(self.transfer_cur_addr);
+
+                        if self.transfer_cur_addr == transfer_end_addr {
+                            self.state = CmdState::Idle;
+                            self.cmd_parser_clear_cmd = true;
+                        } else {
+                            self.transfer_cur_addr += 1;
+                        }
+                    } else {
+                        self.state = CmdState::Idle;
+                        self.cmd_parser_clear_cmd = true;
+                        self.out_fifo_write = false;
+                    }
+                } else {
+                    self.state = CmdState::Idle;
+                    self.cmd_parser_clear_cmd = true;
+                }
+            }
+            CmdState::Write => {
+                if self.transfer_cur_addr <= transfer_end_addr {
+                    if is_data_available {
+                        self.in_fifo_read = true;
+                        self.write_mem(self.transfer_cur_addr, in_fifo_output);
+
+                        if self.transfer_cur_addr == transfer_end_addr {
+                            self.state = CmdState::Idle;
+                            self.cmd_parser_clear_cmd = true;
+                            self.in_fifo_read = false;
+                        } else {
+                            self.transfer_cur_addr += 1;
+                        }
+                    } else {
+                        self.state = CmdState::Idle;
+                        self.cmd_parser_clear_cmd = true;
+                        self.in_fifo_read = false;
+                    }
+                } else {
+                    self.state = CmdState::Idle;
+                    self.cmd_parser_clear_cmd = true;
+                }
+            }
+        }
+
+        // Update the submodules with the values their inputs had before the clock edge
+        self.cmd_parser.clock(parser_data, clear_cmd);
+        self.input_fifo.clock(input, in_fifo_read);
+        self.output_fifo.clock(out_fifo_write, read_output);
+
+        self.cycles += 1;
+        if self.dump_trace().is_err() {
+            // The hardware simulation can't report errors while clocking so a failed trace is simply abandoned
+            self.trace = None;
+        }
+
+        output
+    }
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(test, not(feature = "verilator")))]
+pub unsafe extern "C" fn CreateProtoBridge(p_handle: *mut ProtoBridgeHandle) -> u32 {
+    *p_handle = Box::into_raw(Box::new(Model::new())) as ProtoBridgeHandle;
+    0
+}
+
+#[cfg(any(test, not(feature = "verilator")))]
+pub unsafe extern "C" fn DestroyProtoBridge(handle: ProtoBridgeHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle as *mut Model));
+    }
+}
+
+#[cfg(any(test, not(feature = "verilator")))]
+pub unsafe extern "C" fn QueryProtoBridgeDataStatus(handle: ProtoBridgeHandle) -> DataStatus {
+    let model = &*(handle as *const Model);
+    DataStatus {
+        is_input_full: model.is_input_full() as u8,
+        is_output_empty: model.is_output_empty() as u8,
+    }
+}
+
+#[cfg(any(test, not(feature = "verilator")))]
+pub unsafe extern "C" fn ClockProtoBridge(
+    handle: ProtoBridgeHandle,
+    p_input: *const u8,
+    p_output: *mut u8,
+) {
+    let model = &mut *(handle as *mut Model);
+
+    let input = if p_input.is_null() {
+        None
+    } else {
+        Some(*p_input)
+    };
+
+    if let Some(output) = model.clock(input, !p_output.is_null()) {
+        *p_output = output;
+    }
+}
+
+#[cfg(any(test, not(feature = "verilator")))]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn ClockProtoBridgeBatch(
+    handle: ProtoBridgeHandle,
+    p_input: *const u8,
+    input_size: usize,
+    p_input_consumed: *mut usize,
+    p_output: *mut u8,
+    output_capacity: usize,
+    p_output_produced: *mut usize,
+    max_cycles: u64,
+) -> u64 {
+    let model = &mut *(handle as *mut Model);
+
+    let mut input_consumed = 0;
+    let mut output_produced = 0;
+    let mut cycles = 0;
+
+    // A zero capacity runs the full cycle count without receiving any output
+    while (cycles < max_cycles) && ((output_capacity == 0) || (output_produced < output_capacity)) {
+        let input = if (input_consumed < input_size) && !model.is_input_full() {
+            Some(*p_input.add(input_consumed))
+        } else {
+            None
+        };
+        let read_output = (output_produced < output_capacity) && !model.is_output_empty();
+
+        if let Some(output) = model.clock(input, read_output) {
+            *p_output.add(output_produced) = output;
+            output_produced += 1;
+        }
+        if input.is_some() {
+            input_consumed += 1;
+        }
+
+        cycles += 1;
+    }
+
+    *p_input_consumed = input_consumed;
+    *p_output_produced = output_produced;
+
+    cycles
+}
+
+#[cfg(any(test, not(feature = "verilator")))]
+pub unsafe extern "C" fn ReadProtoBridgeMemory(
+    handle: ProtoBridgeHandle,
+    addr: u32,
+    p_data: *mut u8,
+    size: usize,
+) -> usize {
+    let model = &*(handle as *const Model);
+    model.backdoor_read(addr, slice::from_raw_parts_mut(p_data, size))
+}
+
+#[cfg(any(test, not(feature = "verilator")))]
+pub unsafe extern "C" fn WriteProtoBridgeMemory(
+    handle: ProtoBridgeHandle,
+    addr: u32,
+    p_data: *const u8,
+    size: usize,
+) -> usize {
+    let model = &mut *(handle as *mut Model);
+    model.backdoor_write(addr, slice::from_raw_parts(p_data, size))
+}
+
+#[cfg(any(test, not(feature = "verilator")))]
+pub unsafe extern "C" fn StartProtoBridgeTrace(
+    handle: ProtoBridgeHandle,
+    p_path: *const c_char,
+    format: u32,
+) -> u32 {
+    let model = &mut *(handle as *mut Model);
+
+    // The model only supports vcd traces
+    if format != TRACE_FORMAT_VCD {
+        return 1;
+    }
+
+    match CStr::from_ptr(p_path).to_str() {
+        Ok(path) => model.start_trace(Path::new(path)).is_err() as u32,
+        Err(_) => 1,
+    }
+}
+
+#[cfg(any(test, not(feature = "verilator")))]
+pub unsafe extern "C" fn FlushProtoBridgeTrace(handle: ProtoBridgeHandle) {
+    let model = &mut *(handle as *mut Model);
+    let _ = model.flush_trace();
+}
+
+#[cfg(any(test, not(feature = "verilator")))]
+pub unsafe extern "C" fn StopProtoBridgeTrace(handle: ProtoBridgeHandle) {
+    let model = &mut *(handle as *mut Model);
+    let _ = model.stop_trace();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobridge::{
+        reg_idx_dbg_breakpoint, reg_idx_fb_buffer, ProtoBridge, RegIdx, REG_IDX_DBG_HALT,
+        REG_IDX_DEV_FIFO_DEPTH, REG_IDX_DEV_MEM_SIZE, REG_IDX_FB_ADDR, REG_IDX_FB_CONFIG,
+        REG_IDX_FB_FRAME_COUNT, REG_IDX_FB_FRONT, REG_IDX_FB_PRESENT, REG_IDX_MEM_WAIT_STATES,
+    };
+    use proptest::prelude::*;
+    use std::collections::VecDeque;
+
+    /// Streams the input bytes into the model the same way ProtoBridge does and returns the first num_bytes of output
+    fn transact(model: &mut Model, input: &[u8], num_bytes: usize) -> Vec<u8> {
+        let mut input: VecDeque<u8> = input.iter().copied().collect();
+        let mut output = Vec::new();
+
+        for _ in 0..(input.len() + num_bytes + 64) * 4 {
+            let byte = if model.is_input_full() {
+                None
+            } else {
+                input.front().copied()
+            };
+            let read_output = !model.is_output_empty();
+
+            if let Some(data) = model.clock(byte, read_output) {
+                output.push(data);
+            }
+            if byte.is_some() {
+                input.pop_front();
+            }
+            if input.is_empty() && output.len() >= num_bytes {
+                break;
+            }
+        }
+
+        output
+    }
+
+    fn parse(bytes: &[u8]) -> Cmd {
+        let mut parser = CmdParser::new();
+        for byte in bytes {
+            parser.clock(Some(*byte), false);
+        }
+        assert!(parser.is_cmd_valid(false));
+        parser.cmd()
+    }
+
+    proptest! {
+        #[test]
+        fn cmd_round_trip(id in 0u8..16, addr in 0u32..0x40000000, size in 0u32..0x40000000) {
+            let cmd = ProtoBridge::build_cmd(id, addr, size);
+            prop_assert_eq!(parse(&cmd.to_le_bytes()), Cmd { id, addr, size });
+        }
+
+        #[test]
+        fn cmd_truncates_to_30_bits(id: u8, addr: u32, size: u32) {
+            let cmd = Cmd::decode(ProtoBridge::build_cmd(id, addr, size));
+            prop_assert_eq!(cmd, Cmd { id: id & 0xf, addr: addr & 0x3fffffff, size: size & 0x3fffffff });
+        }
+
+        #[test]
+        fn reg_cmd_targets_register_space(idx in 0u16..1024, data: u32) {
+            let cmd = parse(&ProtoBridge::build_reg_cmd(CMD_ID_WRITE, RegIdx::new(idx).unwrap(), data).to_le_bytes());
+            prop_assert!(cmd.addr >= REG_SPACE_BASE);
+            prop_assert_eq!((cmd.addr >> 2) & 0x3ff, idx as u32);
+            prop_assert_eq!(cmd.size, data & 0x3fffffff);
+        }
+
+        #[test]
+        fn memory_round_trip(addr in 0u32..(MEM_SIZE as u32 - 256), data in prop::collection::vec(any::<u8>(), 1..256)) {
+            let mut model = Model::new();
+
+            let mut input = ProtoBridge::build_cmd(CMD_ID_WRITE, addr, data.len() as u32).to_le_bytes().to_vec();
+            input.extend(&data);
+            input.extend(&ProtoBridge::build_cmd(CMD_ID_READ, addr, data.len() as u32).to_le_bytes());
+
+            prop_assert_eq!(transact(&mut model, &input, data.len()), data);
+        }
+    }
+
+    #[test]
+    fn zero_size_read_returns_one_byte() {
+        let mut model = Model::new();
+
+        let mut input = ProtoBridge::build_cmd(CMD_ID_WRITE, 0x100, 2)
+            .to_le_bytes()
+            .to_vec();
+        input.extend(&[0xab, 0xcd]);
+        input.extend(&ProtoBridge::build_cmd(CMD_ID_READ, 0x100, 0).to_le_bytes());
+
+        // The hardware computes the final address of a transfer as addr + max(size - 1, 0) so zero sized transfers
+        // still move a single byte.
+        assert_eq!(transact(&mut model, &input, 2), vec![0xab]);
+    }
+
+    #[test]
+    fn backdoor_access() {
+        let mut model = Model::new();
+
+        assert_eq!(model.backdoor_write(0x100, &[1, 2, 3, 4]), 4);
+        let input = ProtoBridge::build_cmd(CMD_ID_READ, 0x100, 4).to_le_bytes();
+        assert_eq!(transact(&mut model, &input, 4), vec![1, 2, 3, 4]);
+
+        // Accesses that run past the end of memory are rejected as a whole
+        let mut data = [0; 4];
+        assert_eq!(model.backdoor_write(MEM_SIZE as u32 - 2, &[5; 4]), 0);
+        assert_eq!(model.backdoor_read(MEM_SIZE as u32 - 4, &mut data), 4);
+        assert_eq!(data, [0; 4]);
+        assert_eq!(model.backdoor_read(MEM_SIZE as u32 - 2, &mut data), 0);
+        assert_eq!(model.backdoor_read(u32::MAX, &mut data), 0);
+    }
+
+    #[test]
+    fn reg_read() {
+        let mut model = Model::new();
+
+        let input =
+            ProtoBridge::build_reg_cmd(CMD_ID_READ, REG_IDX_FB_ADDR, 0xffffffff).to_le_bytes();
+        assert_eq!(transact(&mut model, &input, 4), FB_ADDR.to_le_bytes());
+
+        let input =
+            ProtoBridge::build_reg_cmd(CMD_ID_READ, REG_IDX_FB_CONFIG, 0xffffffff).to_le_bytes();
+        assert_eq!(
+            transact(&mut model, &input, 4),
+            ((FB_DIM << 3) | FB_DIM).to_le_bytes()
+        );
+    }
+
+    #[test]
+    fn reg_read_unknown_returns_zero() {
+        let mut model = Model::new();
+
+        let input = ProtoBridge::build_reg_cmd(CMD_ID_READ, RegIdx::new(1023).unwrap(), 0xffffffff)
+            .to_le_bytes();
+        assert_eq!(transact(&mut model, &input, 4), vec![0; 4]);
+    }
+
+    #[test]
+    fn fb_buffers_follow_each_other() {
+        let mut model = Model::new();
+
+        let mut input = ProtoBridge::build_reg_cmd(CMD_ID_READ, REG_IDX_FB_FRONT, 0xffffffff)
+            .to_le_bytes()
+            .to_vec();
+        for n in 0..NUM_FB_BUFFERS {
+            input.extend(
+                &ProtoBridge::build_reg_cmd(CMD_ID_READ, reg_idx_fb_buffer(n).unwrap(), 0xffffffff)
+                    .to_le_bytes(),
+            );
+        }
+
+        let mut expected = 0u32.to_le_bytes().to_vec();
+        for n in 0..NUM_FB_BUFFERS {
+            expected.extend(&(FB_ADDR + FB_SIZE * n as u32).to_le_bytes());
+        }
+        assert_eq!(transact(&mut model, &input, expected.len()), expected);
+    }
+
+    #[test]
+    fn host_present_is_ignored() {
+        let mut model = Model::new();
+
+        // Only the cpu can present frames so the frame count doesn't change
+        let mut input = ProtoBridge::build_reg_cmd(CMD_ID_WRITE, REG_IDX_FB_PRESENT, 1)
+            .to_le_bytes()
+            .to_vec();
+        input.extend(
+            &ProtoBridge::build_reg_cmd(CMD_ID_READ, REG_IDX_FB_FRAME_COUNT, 0xffffffff)
+                .to_le_bytes(),
+        );
+        assert_eq!(transact(&mut model, &input, 4), 0u32.to_le_bytes());
+    }
+
+    #[test]
+    fn config_matches_build() {
+        let mut model = Model::new();
+
+        let mut input = ProtoBridge::build_reg_cmd(CMD_ID_READ, REG_IDX_DEV_MEM_SIZE, 0xffffffff)
+            .to_le_bytes()
+            .to_vec();
+        input.extend(
+            &ProtoBridge::build_reg_cmd(CMD_ID_READ, REG_IDX_DEV_FIFO_DEPTH, 0xffffffff)
+                .to_le_bytes(),
+        );
+
+        let mut expected = hw_params::MEM_SIZE.to_le_bytes().to_vec();
+        expected.extend(&hw_params::FIFO_DEPTH.to_le_bytes());
+        assert_eq!(transact(&mut model, &input, 8), expected);
+    }
+
+    #[test]
+    fn mem_wait_states_round_trip() {
+        let mut model = Model::new();
+
+        // Only the low 16 bits hold wait states
+        let mut input = ProtoBridge::build_reg_cmd(CMD_ID_WRITE, REG_IDX_MEM_WAIT_STATES, 0x30201)
+            .to_le_bytes()
+            .to_vec();
+        input.extend(
+            &ProtoBridge::build_reg_cmd(CMD_ID_READ, REG_IDX_MEM_WAIT_STATES, 0xffffffff)
+                .to_le_bytes(),
+        );
+        assert_eq!(transact(&mut model, &input, 4), 0x201u32.to_le_bytes());
+    }
+
+    #[test]
+    fn trace_records_changes() {
+        let path =
+            std::env::temp_dir().join(format!("devsim_model_trace_{}.vcd", std::process::id()));
+
+        let mut model = Model::new();
+        model.start_trace(&path).unwrap();
+        let input = ProtoBridge::build_cmd(CMD_ID_READ, 0, 4).to_le_bytes();
+        transact(&mut model, &input, 4);
+        model.stop_trace().unwrap();
+
+        let trace = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(trace.contains("$var wire 2 ! r_state $end"));
+        assert!(trace.contains("$scope module cpu $end\n$var wire 32 * r_pc $end"));
+        assert!(trace.contains("$var wire 32 ' w_cpu_mem_addr_out $end"));
+
+        // The cpu signals are nested the same way as in traces of the hardware
+        let vcd = crate::vcd::parse(&trace).unwrap();
+        assert!(vcd
+            .signals
+            .iter()
+            .any(|signal| signal.name == "top.cpu.r_pc"));
+        assert!(trace.contains("#0\nb0 !\n0\"\n1#\n"));
+
+        // The state machine leaves idle to return the memory data and then goes back
+        assert!(trace.contains("b10 !"));
+        assert!(trace.lines().filter(|line| line.starts_with('#')).count() > 2);
+    }
+
+    #[test]
+    fn dbg_halt_is_stored() {
+        let mut model = Model::new();
+
+        let mut input = ProtoBridge::build_reg_cmd(CMD_ID_WRITE, REG_IDX_DBG_HALT, 1)
+            .to_le_bytes()
+            .to_vec();
+        input.extend(
+            &ProtoBridge::build_reg_cmd(CMD_ID_READ, REG_IDX_DBG_HALT, 0xffffffff).to_le_bytes(),
+        );
+        assert_eq!(transact(&mut model, &input, 4), 1u32.to_le_bytes());
+    }
+
+    #[test]
+    fn dbg_breakpoints_are_stored() {
+        let mut model = Model::new();
+
+        let mut input = Vec::new();
+        for n in 0..NUM_BREAKPOINTS {
+            let value = (0x100 * n as u32) | 1;
+            input.extend(
+                &ProtoBridge::build_reg_cmd(
+                    CMD_ID_WRITE,
+                    reg_idx_dbg_breakpoint(n).unwrap(),
+                    value,
+                )
+                .to_le_bytes(),
+            );
+        }
+        for n in 0..NUM_BREAKPOINTS {
+            input.extend(
+                &ProtoBridge::build_reg_cmd(
+                    CMD_ID_READ,
+                    reg_idx_dbg_breakpoint(n).unwrap(),
+                    0xffffffff,
+                )
+                .to_le_bytes(),
+            );
+        }
+
+        let expected = (0..NUM_BREAKPOINTS)
+            .flat_map(|n| ((0x100 * n as u32) | 1).to_le_bytes().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(transact(&mut model, &input, expected.len()), expected);
+    }
+
+    #[test]
+    fn reset_cmd_returns_to_idle() {
+        let mut model = Model::new();
+
+        let mut input = ProtoBridge::build_cmd(CMD_ID_RESET, 0, 0)
+            .to_le_bytes()
+            .to_vec();
+        input.extend(
+            &ProtoBridge::build_reg_cmd(CMD_ID_READ, REG_IDX_FB_ADDR, 0xffffffff).to_le_bytes(),
+        );
+        assert_eq!(transact(&mut model, &input, 4), FB_ADDR.to_le_bytes());
+    }
+}
@@ -9,4 +9,9 @@ uint DynamicConstantOffset()
 uint MaterialIndex()
 {
     return ((uPushConstants.Constant0 >> 24) & 0xFF);
+}
+
+uint SamplerIndex()
+{
+    return (uPushConstants.Constant1 & 0x1);
 }
\ No newline at end of file
@@ -8,5 +8,5 @@ layout(location = 0, index = 0) out vec4 OutFinalColor;
 
 void main()
 {
-    OutFinalColor = vec4(texture(sampler2D(uTextures[0], uSampler), vTexCoord).rgb, 1.0);
+    OutFinalColor = vec4(texture(sampler2D(uTextures[0], uSamplers[SAMPLER_INDEX_LINEAR]), vTexCoord).rgb, 1.0);
 }
\ No newline at end of file
@@ -1,2 +1,5 @@
-layout(set = 0, binding = 1) uniform sampler uSampler;
+#define SAMPLER_INDEX_LINEAR 0
+#define SAMPLER_INDEX_NEAREST 1
+
+layout(set = 0, binding = 1) uniform sampler uSamplers[2];
 layout(set = 0, binding = 2) uniform texture2D uTextures[64];
\ No newline at end of file
@@ -9,5 +9,5 @@ layout(location = 0, index = 0) out vec4 OutFinalColor;
 
 void main()
 {
-    OutFinalColor = vColor * texture(sampler2D(uTextures[MaterialIndex()], uSampler), vTexCoord).r;
+    OutFinalColor = vColor * texture(sampler2D(uTextures[MaterialIndex()], uSamplers[SamplerIndex()]), vTexCoord);
 }
\ No newline at end of file
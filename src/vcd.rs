@@ -0,0 +1,427 @@
+//! Minimal reader for value change dump files
+//!
+//! This only supports the subset of the format that's produced by the device simulation. Values containing x or z
+//! bits are read as zero and real values are ignored.
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+/// Enumeration of possible vcd error types
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum VcdErrorKind {
+    /// The file ended in the middle of a declaration or value change
+    UnexpectedEnd,
+    /// The token couldn't be parsed in the current context
+    InvalidToken(String),
+}
+
+/// A vcd error
+#[derive(Debug, Clone)]
+pub struct VcdError {
+    kind: VcdErrorKind,
+}
+
+impl VcdError {
+    fn from(kind: VcdErrorKind) -> Self {
+        VcdError { kind }
+    }
+
+    /// Returns the type of error that occurred
+    pub fn kind(&self) -> &VcdErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for VcdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#?}", self.kind)
+    }
+}
+
+impl error::Error for VcdError {}
+
+type Result<T> = std::result::Result<T, VcdError>;
+
+/// A signal declared in a vcd file along with all of its value changes
+#[derive(Debug, Clone)]
+pub struct Signal {
+    /// Hierarchical name of the signal with scopes separated by periods
+    pub name: String,
+    pub width: u32,
+    /// Times and values of each change in increasing time order
+    pub changes: Vec<(u64, u64)>,
+}
+
+impl Signal {
+    /// Returns the value of the signal at the provided time if it had been assigned one by then
+    pub fn value_at(&self, time: u64) -> Option<u64> {
+        let idx = self
+            .changes
+            .partition_point(|(change_time, _)| *change_time <= time);
+        if idx > 0 {
+            Some(self.changes[idx - 1].1)
+        } else {
+            None
+        }
+    }
+}
+
+/// Contents of a vcd file
+#[derive(Debug, Clone, Default)]
+pub struct Vcd {
+    pub signals: Vec<Signal>,
+    /// Time of the first timestamp in the file
+    pub start_time: u64,
+    /// Time of the last timestamp in the file
+    pub end_time: u64,
+}
+
+impl Vcd {
+    /// Drops the changes that are no longer needed to know each signal's value at or after the provided time
+    pub fn discard_before(&mut self, time: u64) {
+        for signal in &mut self.signals {
+            let idx = signal
+                .changes
+                .partition_point(|(change_time, _)| *change_time <= time);
+            if idx > 1 {
+                signal.changes.drain(..(idx - 1));
+            }
+        }
+        self.start_time = self.start_time.max(time).min(self.end_time);
+    }
+}
+
+/// Splits text into whitespace separated tokens while tracking how much of it has been consumed
+struct Tokens<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = &self.text[self.pos..];
+        let start = rest.find(|c: char| !c.is_whitespace())?;
+        let rest = &rest[start..];
+        let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        self.pos += start + len;
+        Some(&rest[..len])
+    }
+
+    fn expect(&mut self) -> Result<&'a str> {
+        self.next()
+            .ok_or_else(|| VcdError::from(VcdErrorKind::UnexpectedEnd))
+    }
+
+    /// Consumes tokens up to and including the next $end keyword and returns the ones before it
+    fn until_end(&mut self) -> Result<Vec<&'a str>> {
+        let mut tokens = Vec::new();
+        loop {
+            match self.expect()? {
+                "$end" => return Ok(tokens),
+                token => tokens.push(token),
+            }
+        }
+    }
+}
+
+fn invalid(token: &str) -> VcdError {
+    VcdError::from(VcdErrorKind::InvalidToken(token.to_owned()))
+}
+
+/// Converts a string of binary digits into a value, treating x and z bits as zero
+/// Values wider than 64 bits only keep their least significant bits.
+fn parse_bits(bits: &str) -> u64 {
+    bits.chars()
+        .fold(0, |value, bit| (value << 1) | (bit == '1') as u64)
+}
+
+/// Parses a vcd file as it's being written
+/// Text can be provided in pieces of any size, and anything cut off at the end of a piece is parsed once the rest of
+/// it arrives.
+#[derive(Debug, Default)]
+pub struct Parser {
+    vcd: Vcd,
+    scopes: Vec<String>,
+    ids: HashMap<String, Vec<usize>>,
+    time: Option<u64>,
+    /// Text that hasn't been parsed yet because it may not be complete
+    pending: String,
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the contents of the file that have been parsed so far
+    pub fn vcd(&self) -> &Vcd {
+        &self.vcd
+    }
+
+    /// Returns the contents of the file that have been parsed so far for modification
+    pub fn vcd_mut(&mut self) -> &mut Vcd {
+        &mut self.vcd
+    }
+
+    /// Parses the next piece of the file
+    pub fn feed(&mut self, text: &str) -> Result<()> {
+        let mut pending = std::mem::take(&mut self.pending);
+        pending.push_str(text);
+
+        // The last token may continue in the next piece unless it's followed by whitespace
+        let complete_len = pending
+            .rfind(char::is_whitespace)
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+        let mut tokens = Tokens {
+            text: &pending[..complete_len],
+            pos: 0,
+        };
+
+        let result = loop {
+            let item_pos = tokens.pos;
+            match self.parse_item(&mut tokens) {
+                Ok(true) => {}
+                Ok(false) => break Ok(()),
+                // Declarations and value changes that are cut off are parsed again once the rest of them arrives
+                Err(err) if err.kind() == &VcdErrorKind::UnexpectedEnd => {
+                    tokens.pos = item_pos;
+                    break Ok(());
+                }
+                Err(err) => break Err(err),
+            }
+        };
+
+        let consumed = tokens.pos;
+        pending.drain(..consumed);
+        self.pending = pending;
+        result
+    }
+
+    /// Parses anything left at the end of the file and returns its contents
+    pub fn finish(mut self) -> Result<Vcd> {
+        self.feed("\n")?;
+        if self.pending.trim().is_empty() {
+            Ok(self.vcd)
+        } else {
+            Err(VcdError::from(VcdErrorKind::UnexpectedEnd))
+        }
+    }
+
+    /// Parses the next declaration or value change and returns false if there's nothing left to parse
+    fn parse_item(&mut self, tokens: &mut Tokens) -> Result<bool> {
+        let token = match tokens.next() {
+            Some(token) => token,
+            None => return Ok(false),
+        };
+
+        let (value, id) = match token {
+            "$scope" => {
+                let scope = tokens.until_end()?;
+                let name = scope.get(1).ok_or_else(|| invalid(token))?;
+                self.scopes.push((*name).to_owned());
+                return Ok(true);
+            }
+            "$upscope" => {
+                tokens.until_end()?;
+                self.scopes.pop();
+                return Ok(true);
+            }
+            "$var" => {
+                let var = tokens.until_end()?;
+                if var.len() < 4 {
+                    return Err(invalid(token));
+                }
+
+                let width = var[1].parse().map_err(|_| invalid(var[1]))?;
+                let mut name = self.scopes.join(".");
+                if !name.is_empty() {
+                    name.push('.');
+                }
+                // Bit selects are kept as part of the name
+                name.push_str(&var[3..].join(""));
+
+                self.ids
+                    .entry(var[2].to_owned())
+                    .or_default()
+                    .push(self.vcd.signals.len());
+                self.vcd.signals.push(Signal {
+                    name,
+                    width,
+                    changes: Vec::new(),
+                });
+                return Ok(true);
+            }
+            // Sections of value changes are treated the same as any other changes
+            "$dumpvars" | "$dumpall" | "$dumpon" | "$dumpoff" | "$end" => return Ok(true),
+            _ if token.starts_with('$') => {
+                tokens.until_end()?;
+                return Ok(true);
+            }
+            _ if token.starts_with('#') => {
+                let new_time = token[1..].parse().map_err(|_| invalid(token))?;
+                if self.time.is_none() {
+                    self.vcd.start_time = new_time;
+                }
+                self.vcd.end_time = new_time;
+                self.time = Some(new_time);
+                return Ok(true);
+            }
+            _ if token.starts_with('b') || token.starts_with('B') => {
+                (parse_bits(&token[1..]), tokens.expect()?)
+            }
+            _ if token.starts_with('r') || token.starts_with('R') => {
+                // Real values aren't supported so they're skipped
+                tokens.expect()?;
+                return Ok(true);
+            }
+            _ => {
+                let value = match token.chars().next() {
+                    Some('1') => 1,
+                    Some('0') | Some('x') | Some('X') | Some('z') | Some('Z') => 0,
+                    _ => return Err(invalid(token)),
+                };
+                (value, &token[1..])
+            }
+        };
+
+        let time = self.time.ok_or_else(|| invalid(token))?;
+        for &idx in self.ids.get(id).ok_or_else(|| invalid(id))? {
+            let changes = &mut self.vcd.signals[idx].changes;
+            match changes.last_mut() {
+                // Only the final value of a signal at each time is kept
+                Some(last) if last.0 == time => last.1 = value,
+                _ => changes.push((time, value)),
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Parses the contents of a vcd file
+pub fn parse(text: &str) -> Result<Vcd> {
+    let mut parser = Parser::new();
+    parser.feed(text)?;
+    parser.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_VCD: &str = "$date today $end
+$timescale 1ps $end
+$scope module TOP $end
+$var wire 1 ! i_clk $end
+$scope module top $end
+$var wire 1 ! i_clk $end
+$var wire 8 \" o_data [7:0] $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#10
+$dumpvars
+0!
+b00000000 \"
+$end
+#11
+1!
+#12
+0!
+bx1x1 \"
+#15
+b11111111 \"
+b10 \"
+";
+
+    #[test]
+    fn parse_signals() {
+        let vcd = parse(TEST_VCD).unwrap();
+
+        let names = vcd
+            .signals
+            .iter()
+            .map(|signal| signal.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["TOP.i_clk", "TOP.top.i_clk", "TOP.top.o_data[7:0]"]);
+        assert_eq!(vcd.signals[2].width, 8);
+        assert_eq!(vcd.start_time, 10);
+        assert_eq!(vcd.end_time, 15);
+
+        // Aliased signals receive the same changes
+        assert_eq!(vcd.signals[0].changes, [(10, 0), (11, 1), (12, 0)]);
+        assert_eq!(vcd.signals[1].changes, vcd.signals[0].changes);
+
+        // Unknown bits are read as zero and only the last change at a given time is kept
+        assert_eq!(vcd.signals[2].changes, [(10, 0), (12, 5), (15, 2)]);
+    }
+
+    #[test]
+    fn value_at() {
+        let vcd = parse(TEST_VCD).unwrap();
+        let data = &vcd.signals[2];
+
+        assert_eq!(data.value_at(9), None);
+        assert_eq!(data.value_at(10), Some(0));
+        assert_eq!(data.value_at(14), Some(5));
+        assert_eq!(data.value_at(100), Some(2));
+    }
+
+    #[test]
+    fn parse_in_pieces() {
+        let expected = parse(TEST_VCD).unwrap();
+
+        // Splitting the text anywhere, including in the middle of tokens and declarations, gives the same result
+        for piece_len in 1..TEST_VCD.len() {
+            let mut parser = Parser::new();
+            for piece in TEST_VCD.as_bytes().chunks(piece_len) {
+                parser.feed(std::str::from_utf8(piece).unwrap()).unwrap();
+            }
+            let vcd = parser.finish().unwrap();
+
+            assert_eq!(vcd.signals.len(), expected.signals.len());
+            for (signal, expected) in vcd.signals.iter().zip(&expected.signals) {
+                assert_eq!(signal.name, expected.name);
+                assert_eq!(signal.changes, expected.changes);
+            }
+            assert_eq!((vcd.start_time, vcd.end_time), (10, 15));
+        }
+
+        // Changes are available as soon as they're complete
+        let mut parser = Parser::new();
+        parser
+            .feed(&TEST_VCD[..TEST_VCD.find("#12").unwrap()])
+            .unwrap();
+        assert_eq!(parser.vcd().signals[0].changes, [(10, 0), (11, 1)]);
+        assert_eq!(parser.vcd().end_time, 11);
+    }
+
+    #[test]
+    fn discard_before() {
+        let mut vcd = parse(TEST_VCD).unwrap();
+        vcd.discard_before(13);
+
+        assert_eq!(vcd.start_time, 13);
+        assert_eq!(vcd.signals[0].changes, [(12, 0)]);
+        assert_eq!(vcd.signals[2].changes, [(12, 5), (15, 2)]);
+        assert_eq!(vcd.signals[2].value_at(13), Some(5));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            parse("$scope module top").unwrap_err().kind(),
+            &VcdErrorKind::UnexpectedEnd
+        );
+        assert_eq!(
+            parse("$var wire 1 ! a $end 1!").unwrap_err().kind(),
+            &VcdErrorKind::InvalidToken("1!".to_owned())
+        );
+        assert_eq!(
+            parse("$var wire 1 ! a $end #0 1?").unwrap_err().kind(),
+            &VcdErrorKind::InvalidToken("?".to_owned())
+        );
+    }
+}
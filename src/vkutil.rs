@@ -142,12 +142,14 @@ fn select_instance_layers(enable_validation: bool) -> Vec<CString> {
 }
 
 /// Returns a vector of all desired device extensions
-fn select_device_extensions() -> Vec<CString> {
+fn select_device_extensions(is_presenting: bool) -> Vec<CString> {
     let mut exts = Vec::new();
 
-    // Add the swapchain extension
-    let swapchain_ext_name = CString::new(khr::Swapchain::name().to_bytes()).unwrap();
-    exts.push(swapchain_ext_name);
+    // Add the swapchain extension if the device will present to a surface
+    if is_presenting {
+        let swapchain_ext_name = CString::new(khr::Swapchain::name().to_bytes()).unwrap();
+        exts.push(swapchain_ext_name);
+    }
 
     exts
 }
@@ -189,13 +191,26 @@ pub struct VkInstance {
 
 impl VkInstance {
     pub fn new(window: &winit::window::Window, enable_validation: bool) -> Result<Self> {
+        // Query all extensions required for swapchain usage
+        let surface_extensions = ash_window::enumerate_required_extensions(window)?
+            .iter()
+            .map(|ext| CString::new(ext.to_bytes()).unwrap())
+            .collect::<Vec<_>>();
+        Self::with_surface_extensions(surface_extensions, enable_validation)
+    }
+
+    /// Creates an instance that can't present to a window
+    /// This works on systems without a display, such as CI machines using a software implementation.
+    pub fn new_headless(enable_validation: bool) -> Result<Self> {
+        Self::with_surface_extensions(Vec::new(), enable_validation)
+    }
+
+    fn with_surface_extensions(
+        surface_extensions: Vec<CString>,
+        enable_validation: bool,
+    ) -> Result<Self> {
         unsafe {
             let entry = ash::Entry::new()?;
-            // Query all extensions required for swapchain usage
-            let surface_extensions = ash_window::enumerate_required_extensions(window)?
-                .iter()
-                .map(|ext| CString::new(ext.to_bytes()).unwrap())
-                .collect::<Vec<_>>();
             let instance_extension_strings = select_instance_extensions(surface_extensions);
             let instance_extensions = instance_extension_strings
                 .iter()
@@ -320,13 +335,15 @@ pub struct VkDevice {
 }
 
 impl VkDevice {
+    /// Creates a device that can present to the provided surface, or a device without presentation support if no
+    /// surface is provided
     pub fn new(
         instance: &VkInstance,
         physical_device: vk::PhysicalDevice,
-        surface: &VkSurface,
+        surface: Option<&VkSurface>,
     ) -> Result<Self> {
         unsafe {
-            let device_extension_strings = select_device_extensions();
+            let device_extension_strings = select_device_extensions(surface.is_some());
             let device_extensions = device_extension_strings
                 .iter()
                 .map(|ext| ext.as_ptr())
@@ -337,15 +354,18 @@ impl VkDevice {
                 .get_physical_device_queue_family_properties(physical_device);
 
             // Identify a suitable queue family index for presentation
+            // Without a surface, the graphics queue is used in place of the present queue.
             let mut present_queue_family_index = u32::MAX;
-            for idx in 0..queue_family_properties.len() {
-                if surface.ext.get_physical_device_surface_support(
-                    physical_device,
-                    idx as u32,
-                    surface.inner,
-                )? {
-                    present_queue_family_index = idx as u32;
-                    break;
+            if let Some(surface) = surface {
+                for idx in 0..queue_family_properties.len() {
+                    if surface.ext.get_physical_device_surface_support(
+                        physical_device,
+                        idx as u32,
+                        surface.inner,
+                    )? {
+                        present_queue_family_index = idx as u32;
+                        break;
+                    }
                 }
             }
 
@@ -388,7 +408,11 @@ impl VkDevice {
                 queues[queue_family_indices_by_type[2]],
             ];
 
-            let present_queue = queues[present_queue_family_index as usize];
+            let present_queue = if surface.is_some() {
+                queues[present_queue_family_index as usize]
+            } else {
+                queues_by_type[VkQueueType::Graphics as usize]
+            };
 
             Ok(Self {
                 inner: Arc::new(device),